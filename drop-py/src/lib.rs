@@ -0,0 +1,247 @@
+//! python bindings for `drop_core::api`
+//!
+//! ```python
+//! import drop_rs
+//!
+//! session = drop_rs.Session("./examples", env="base")
+//! record = session.hit("jsonplaceholder.post.login", inputs={"user": "ada"})
+//!
+//! assert record.success
+//! assert record.outputs["response.body.token"]
+//! ```
+
+use drop_core::{
+    api::DropSession,
+    record::CallRecord as DropCallRecord,
+};
+use indexmap::IndexMap;
+use pyo3::{
+    create_exception,
+    exceptions::PyException,
+    prelude::*,
+    types::{PyDict, PyModule},
+};
+
+create_exception!(drop_rs, DropError, PyException, "a drop id couldn't be evaluated or called");
+
+fn to_py_err(err: drop_core::api::DropError) -> PyErr {
+    DropError::new_err(err.to_string())
+}
+
+/// json values cross into python through its json module,
+/// so results are plain dicts, lists and scalars
+fn json_to_py(py: Python<'_>, value: &serde_json::Value) -> PyResult<Py<PyAny>> {
+    let loaded = py
+        .import("json")?
+        .call_method1("loads", (value.to_string(),))?;
+
+    Ok(loaded.unbind())
+}
+
+fn py_to_json(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    let dumped: String = py
+        .import("json")?
+        .call_method1("dumps", (value,))?
+        .extract()?;
+
+    serde_json::from_str(&dumped).map_err(|err| DropError::new_err(err.to_string()))
+}
+
+/// a dropfile directory loaded for an environment
+///
+/// `Session(dir, env="base")`
+#[pyclass(module = "drop_rs", frozen)]
+struct Session {
+    session: DropSession,
+}
+
+#[pymethods]
+impl Session {
+    #[new]
+    #[pyo3(signature = (dir, env = "base"))]
+    fn new(py: Python<'_>, dir: &str, env: &str) -> PyResult<Session> {
+        let session = py
+            .detach(|| DropSession::load(dir, env))
+            .map_err(to_py_err)?;
+
+        Ok(Session { session })
+    }
+
+    #[getter]
+    fn dir(&self) -> &str {
+        self.session.dir()
+    }
+
+    #[getter]
+    fn env(&self) -> &str {
+        self.session.env()
+    }
+
+    /// sends the call or run and runs its after actions- `inputs`
+    /// replace the block's inputs. failed calls return a record
+    /// with `success` false, calls that can't be made raise
+    #[pyo3(signature = (drop_id, inputs = None))]
+    fn hit(
+        &self,
+        py: Python<'_>,
+        drop_id: &str,
+        inputs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<CallRecord> {
+        let mut input_index_map = IndexMap::<String, hcl::Value>::new();
+
+        for (key, value) in inputs.into_iter().flat_map(|inputs| inputs.iter()) {
+            let value = hcl::to_value(py_to_json(py, &value)?)
+                .map_err(|err| DropError::new_err(err.to_string()))?;
+
+            input_index_map.insert(key.extract()?, value);
+        }
+
+        let record = py
+            .detach(|| self.session.hit(drop_id, input_index_map))
+            .map_err(to_py_err)?;
+
+        Ok(CallRecord { record })
+    }
+
+    /// the evaluated request, without sending it or
+    /// running before actions- like `drop give`
+    fn evaluate(&self, py: Python<'_>, drop_id: &str) -> PyResult<Py<PyAny>> {
+        let document = py
+            .detach(|| {
+                self.session.evaluate(drop_id).map(|drop_call| {
+                    serde_json::json!({
+                        "drop_id": drop_id,
+                        "method": drop_call.method.to_string(),
+                        "url": drop_call.full_url(),
+                        "headers": DropCallRecord::headers_to_map(&drop_call.headers),
+                        "body": drop_call.body,
+                    })
+                })
+            })
+            .map_err(to_py_err)?;
+
+        json_to_py(py, &document)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Session(dir={:?}, env={:?})", self.session.dir(), self.session.env())
+    }
+}
+
+/// the result of a call- the fields of `drop hit -o json`,
+/// with the response body
+#[pyclass(module = "drop_rs", frozen)]
+struct CallRecord {
+    record: DropCallRecord,
+}
+
+#[pymethods]
+impl CallRecord {
+    #[getter]
+    fn drop_id(&self) -> &str {
+        &self.record.drop_id
+    }
+
+    #[getter]
+    fn env(&self) -> &str {
+        &self.record.env
+    }
+
+    #[getter]
+    fn method(&self) -> &str {
+        &self.record.method
+    }
+
+    #[getter]
+    fn url(&self) -> &str {
+        &self.record.full_url
+    }
+
+    /// none when the call had no response
+    #[getter]
+    fn status(&self) -> Option<u16> {
+        self.record.status_code.map(|status| status.as_u16())
+    }
+
+    /// the response status was a success- asserts
+    /// aren't checked, see `asserts_passed`
+    #[getter]
+    fn success(&self) -> bool {
+        self.record.is_successful_call
+    }
+
+    /// every assertion passed- true when the call has none
+    #[getter]
+    fn asserts_passed(&self) -> bool {
+        self.record
+            .assert_results
+            .iter()
+            .all(|assert_result| assert_result["success"] == serde_json::Value::Bool(true))
+    }
+
+    #[getter]
+    fn duration_ms(&self) -> Option<u128> {
+        self.record.duration_ms
+    }
+
+    #[getter]
+    fn headers(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        json_to_py(py, &serde_json::json!(self.record.response_headers))
+    }
+
+    #[getter]
+    fn body(&self) -> Option<&str> {
+        self.record.full_response.as_deref()
+    }
+
+    /// the body parsed as json
+    fn json(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let body = self.record.full_response.as_deref().unwrap_or_default();
+
+        let value: serde_json::Value = serde_json::from_str(body)
+            .map_err(|err| DropError::new_err(format!("response body is not json: {err}")))?;
+
+        json_to_py(py, &value)
+    }
+
+    #[getter]
+    fn outputs(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        json_to_py(py, &self.record.to_json()["outputs"])
+    }
+
+    #[getter]
+    fn asserts(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        json_to_py(py, &self.record.to_json()["asserts"])
+    }
+
+    /// the effects of the call's after actions
+    #[getter]
+    fn after(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        json_to_py(py, &self.record.to_json()["after"])
+    }
+
+    fn to_dict(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let mut document = self.record.to_json();
+        document["body"] = self.record.full_response.clone().into();
+
+        json_to_py(py, &document)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CallRecord(drop_id={:?}, status={}, success={})",
+            self.record.drop_id,
+            self.status().map_or("None".to_string(), |status| status.to_string()),
+            if self.record.is_successful_call { "True" } else { "False" }
+        )
+    }
+}
+
+#[pymodule]
+fn drop_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Session>()?;
+    m.add_class::<CallRecord>()?;
+    m.add("DropError", m.py().get_type::<DropError>())?;
+
+    Ok(())
+}
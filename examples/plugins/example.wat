@@ -0,0 +1,34 @@
+;;
+;; an example plugin- drop loads .wasm and .wat files in
+;; [dir]/plugins when built with the wasm feature
+;;
+;; exports named drop_fn_[name] are functions, drop_action_[type]
+;; are after actions- each is passed the pointer and length of a
+;; json document written with alloc, and returns the pointer and
+;; length of its json result as ptr << 32 | len
+;;
+;; tenant() returns "example_tenant", and the audit after
+;; action returns its details
+;;
+(module
+  (memory (export "memory") 1)
+
+  (data (i32.const 0) "{\"ok\":\"example_tenant\"}")
+  (data (i32.const 64) "{\"ok\":{\"audited\":true}}")
+
+  ;; drop writes one input per call, so each
+  ;; input is written to the same place, after
+  ;; the results
+  (func (export "alloc") (param $len i32) (result i32)
+    (if (i32.gt_u (i32.add (i32.const 1024) (local.get $len))
+                  (i32.mul (memory.size) (i32.const 65536)))
+      (then
+        (drop (memory.grow (i32.add (i32.shr_u (local.get $len) (i32.const 16)) (i32.const 1))))))
+    (i32.const 1024))
+
+  (func (export "drop_fn_tenant") (param $ptr i32) (param $len i32) (result i64)
+    (i64.or (i64.shl (i64.const 0) (i64.const 32)) (i64.const 23)))
+
+  (func (export "drop_action_audit") (param $ptr i32) (param $len i32) (result i64)
+    (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 23)))
+)
@@ -0,0 +1,66 @@
+# pytest suite driving the example dropfiles against the mock server
+#
+# cargo run --bin mock
+# cd drop-py && maturin develop && cd ..
+# pytest examples/python
+
+from pathlib import Path
+
+import pytest
+
+import drop_rs
+
+EXAMPLES_DIR = Path(__file__).parent.parent
+
+
+@pytest.fixture(scope="module")
+def session():
+    return drop_rs.Session(str(EXAMPLES_DIR), env="base")
+
+
+def test_signup_stores_outputs(session):
+    record = session.hit("example.post.signup")
+
+    assert record.success
+    assert record.status == 200
+    assert record.outputs["response.headers.csrf_token"]
+
+
+def test_before_actions_log_in_first(session):
+    record = session.hit("example.get.user")
+
+    assert record.success
+    assert record.json()["user_id"] == 42
+
+
+def test_inputs_replace_the_block_inputs(session):
+    record = session.hit("example.get.user_with_input", inputs={"auth_token": "wrong"})
+
+    assert not record.success
+    assert record.status == 404
+
+
+def test_evaluate_does_not_send(session):
+    request = session.evaluate("example.get.user_with_input")
+
+    assert request["method"] == "GET"
+    assert request["url"].endswith("/user")
+
+
+def test_after_actions_are_skipped_on_failure(session):
+    record = session.hit("example.get.report_unavailable")
+
+    assert record.status == 503
+    assert any(effect.get("skipped") for effect in record.after)
+
+
+def test_script_outputs(session):
+    record = session.hit("script.get.report")
+
+    assert record.success
+    assert record.outputs["script.report_key"].startswith("report-")
+
+
+def test_unknown_drop_id_raises(session):
+    with pytest.raises(drop_rs.DropError, match="example.get.nope"):
+        session.hit("example.get.nope")
@@ -1,3 +1,4 @@
+use crate::util::exit;
 use std::collections::HashMap;
 use colored::Colorize;
 use hcl::expr::Traversal;
@@ -44,7 +45,7 @@ impl PostActionAccessor {
             return t;
         }
         log::error!("{key:#?} not found in set action");
-        std::process::exit(1)
+        exit::exit(1)
     }
     
     pub fn get_bool_or_panic<'a>(
@@ -55,6 +56,29 @@ impl PostActionAccessor {
             return *t;
         }
         log::error!("{key:#?} not found in set action");
-        std::process::exit(1)
+        exit::exit(1)
     }
-}
\ No newline at end of file
+
+    pub fn get_optional_string(action_config: &HashMap<String, ActionValue>, key: &str) -> Option<String> {
+        match action_config.get(key) {
+            Some(ActionValue::String(st)) => Some(st.to_string()),
+            None => None,
+            _ => panic!("invalid value passed for {}", key.yellow()),
+        }
+    }
+
+    pub fn get_list_or_panic(action_config: &HashMap<String, ActionValue>, key: &str) -> Vec<String> {
+        match action_config.get(key) {
+            Some(ActionValue::List(list)) => list.to_owned(),
+            _ => panic!("invalid value passed for {}", key.yellow()),
+        }
+    }
+
+    pub fn get_optional_trav(action_config: &HashMap<String, ActionValue>, key: &str) -> Option<Traversal> {
+        match action_config.get(key) {
+            Some(ActionValue::Traversal(t)) => Some(t.to_owned()),
+            None => None,
+            _ => panic!("invalid value passed for {}", key.yellow()),
+        }
+    }
+}
@@ -1,20 +1,66 @@
 use accessor::PostActionAccessor;
 use colored::Colorize;
-use hcl::{expr::Traversal, Expression};
+use hcl::{eval::Evaluate, expr::Traversal, Expression};
+use isahc::{config::Configurable, http::Uri, ReadResponseExt, Request};
 use log::error;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
 
-use crate::{parser::hcl_block::HclBlock, persist::PersisterProvider, record::CallRecord};
+use crate::util::exit;
+use crate::{
+    call::call_settings::CallSettings,
+    caller::{client::DropClient, network::Network},
+    cmd::{ctx::CmdContext, output::Output},
+    constants::*,
+    interpreter::{global_interpreter_context::GlobalInterpreterContext, scope::GlobalScopeProvider},
+    parser::hcl_block::HclBlock,
+    persist::PersisterProvider,
+    plugin::PluginRegistry,
+    record::{response_walker::ResponseWalker, CallRecord},
+    text_println,
+    util::redact::Redactor,
+};
 
 pub mod accessor;
+pub mod pre_action;
 
 pub type AfterActionConfig = HashMap<String, Vec<HashMap<String, ActionValue>>>;
 
+/// `on` values of an after action- each action
+/// type has its own default, `AfterAction::default_on`
+pub const AFTER_ON_VALUES: [&str; 3] = ["success", "failure", "always"];
+
+const HTTP_NOTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Clone)]
 pub enum ActionValue {
     String(String),
     Traversal(Traversal),
     Bool(bool),
+    List(Vec<String>),
+    /// a condition evaluated with the response, e.g.
+    /// `when = response.status == 200`
+    Expression(Expression),
+}
+
+/// result of an after action, reported in json output
+#[derive(Debug, Clone, Serialize)]
+pub struct AfterActionEffect {
+    #[serde(rename = "type")]
+    pub action_type: String,
+    pub success: bool,
+    /// why the action didn't run, when its condition didn't hold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+    pub details: serde_json::Map<String, serde_json::Value>,
 }
 
 pub struct PostAction {}
@@ -25,16 +71,36 @@ impl PostAction {
 
         let throw = || {
             error!("invalid after action objects");
-            std::process::exit(1);
+            exit::exit(1);
         };
 
         match exp {
             Expression::Object(obj) => {
                 for (key, value) in obj {
+                    if key.to_string() == AFTER_WHEN_PROP {
+                        map.insert(key.to_string(), ActionValue::Expression(value.to_owned()));
+                        continue;
+                    }
+
                     let value_to_insert = match value {
                         Expression::String(str) => ActionValue::String(str.to_string()),
                         Expression::Traversal(trav) => ActionValue::Traversal(*trav.clone()), // small clone
                         Expression::Bool(b) => ActionValue::Bool(*b),
+                        Expression::Array(items) => {
+                            let list = items
+                                .iter()
+                                .map(|item| match item {
+                                    Expression::String(str) => Some(str.to_string()),
+                                    Expression::Number(number) => Some(number.to_string()),
+                                    _ => None,
+                                })
+                                .collect::<Option<Vec<String>>>();
+
+                            match list {
+                                Some(list) => ActionValue::List(list),
+                                None => throw(),
+                            }
+                        }
                         &_ => throw(),
                     };
                     map.insert(key.to_string(), value_to_insert);
@@ -51,6 +117,11 @@ impl PostAction {
     pub fn run_post_action_callbacks(mut call_record: CallRecord) -> CallRecord {
         let after_action_config = call_record.after_action_config.take();
 
+        // before any output is printed or persisted
+        if let Some(user_post_action_configs) = &after_action_config {
+            PostAction::register_sensitive_outputs(&call_record, user_post_action_configs);
+        }
+
         // default post call action
         PostAction::post_action_persist()(&call_record);
         PostAction::post_action_print_outputs_to_console()(&call_record);
@@ -62,9 +133,32 @@ impl PostAction {
                         let action_config_id = action_config.get("type").unwrap();
 
                         if let ActionValue::String(value) = action_config_id {
-                            if value.as_str() == "set_secret" {
-                                PostAction::post_action_set_env_vars(&action_config)(&call_record);
+                            if let Some(reason) = PostAction::get_skip_reason(&action_config, &call_record) {
+                                text_println!(
+                                    "{} skipped: {reason}\n",
+                                    format!("post action {value}").blue()
+                                );
+
+                                call_record.after_action_effects.push(AfterActionEffect {
+                                    action_type: value.to_string(),
+                                    success: false,
+                                    skipped: Some(reason),
+                                    details: serde_json::Map::new(),
+                                });
+
+                                continue;
                             }
+
+                            // types are checked when the call block is parsed
+                            let plugin_registry = PluginRegistry::get();
+
+                            let Some(after_action) = plugin_registry.get_after_action(value) else {
+                                continue;
+                            };
+
+                            let effect = after_action.run(&action_config, &call_record);
+
+                            call_record.after_action_effects.push(effect);
                         }
                     }
                 }
@@ -75,9 +169,53 @@ impl PostAction {
             }
         }
 
+        Output::emit_document(&call_record.to_json());
+
         call_record
     }
 
+    /// outputs stored with `set_secret` or marked `sensitive = true`
+    /// are redacted like values from `secrets.*`
+    ///
+    /// `after = [{ type = "set_secret", input = response.body.token, ... }]`
+    /// `after = [{ type = "...", input = response.body.session, sensitive = true }]`
+    pub fn register_sensitive_outputs(
+        call_record: &CallRecord,
+        after_action_config: &AfterActionConfig,
+    ) {
+        for action_configs in after_action_config.values() {
+            for action_config in action_configs {
+                let is_set_secret = matches!(
+                    action_config.get("type"),
+                    Some(ActionValue::String(action_type)) if action_type == "set_secret"
+                );
+
+                let is_sensitive = matches!(
+                    action_config.get("sensitive"),
+                    Some(ActionValue::Bool(true))
+                );
+
+                if !(is_set_secret || is_sensitive) {
+                    continue;
+                }
+
+                let Some(ActionValue::Traversal(input)) = action_config.get("input") else {
+                    continue;
+                };
+
+                let input = HclBlock::traversal_to_string(input);
+
+                if let Some(outputs) = call_record.output_records() {
+                    for output_record in outputs {
+                        if *output_record.key() == input {
+                            Redactor::register(output_record.value());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     pub fn post_action_persist() -> impl FnMut(&CallRecord) {
         return |call_record| {
             
@@ -94,6 +232,10 @@ impl PostAction {
 
     pub fn post_action_print_outputs_to_console() -> impl Fn(&CallRecord) {
         return |call_record| {
+            if !Output::is_text() {
+                return;
+            }
+
             let drop_id = call_record.drop_id();
             let status = call_record.status_code().unwrap();
             println!("\n{drop_id:#?} result: {status:#?}\n");
@@ -107,58 +249,393 @@ impl PostAction {
         };
     }
 
-    pub fn post_action_set_env_vars(
+    /// `path`, relative to the working directory, with the `input`
+    /// output or the raw response body- parent directories are created
+    pub fn post_action_write_file(
         action_config: &HashMap<String, ActionValue>,
-    ) -> impl FnMut(&CallRecord) {
-        let input = PostActionAccessor::get_trav_or_panic(action_config, "input").to_owned();
-        let input = HclBlock::traversal_to_string(&input);
-        let key_to_set = PostActionAccessor::get_string_or_panic(action_config, "key").clone();
-        let overwrite =
-            PostActionAccessor::get_bool_or_panic(action_config, "overwrite").to_owned();
-        let env = PostActionAccessor::get_string_or_default(action_config, "env", "base").clone();
+    ) -> impl FnMut(&CallRecord) -> AfterActionEffect {
+        let path = PostActionAccessor::get_string_or_panic(action_config, "path");
+        let input = PostAction::get_input_key(action_config);
 
-        return move |call_record| {
+        move |call_record| {
+            let mut details = serde_json::Map::new();
+            details.insert("input".to_string(), input.clone().into());
+            details.insert("path".to_string(), path.clone().into());
 
-            let persister_lock = PersisterProvider::get_lock_to_persister();
+            let write_res = PostAction::get_input_value(call_record, &input).and_then(|value| {
+                let path = Path::new(&path);
 
-            if persister_lock.is_none() {
-                // warn 
-            } else {
-            
-                let persister = &mut persister_lock.unwrap();
+                if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+                }
 
-                let outputs = call_record.output_records();
+                fs::write(path, &value).map_err(|err| err.to_string())?;
 
-                // todo- refactor into Result<>
-                let mut matched = false;
+                details.insert("bytes".to_string(), value.len().into());
 
-                if let Some(outputs) = outputs {
-                    for output_record in outputs {
-                        let output_key = output_record.key();
-
-                        if *output_key == input {
-                            matched = true;
-                            let output_value = output_record.value();
-
-                            // todo
-                            log::trace!(
-                                "input {input} key_to_set {key_to_set} output_value {output_value}"
-                            );
-
-                            persister.insert_secret_into_env(
-                                &key_to_set,
-                                output_value,
-                                &env,
-                                overwrite,
-                            );
-                        }
-                    }
+                Ok(())
+            });
+
+            PostAction::to_effect(AFTER_WRITE_FILE_TYPE_VALUE, details, write_res)
+        }
+    }
+
+    /// a non-secret value, as `vars.[key]` in later calls-
+    /// stored for the selected environment unless `env` is set
+    pub fn post_action_set_variable(
+        action_config: &HashMap<String, ActionValue>,
+    ) -> impl FnMut(&CallRecord) -> AfterActionEffect {
+        let input = PostAction::get_input_key(action_config);
+        let key_to_set = PostActionAccessor::get_string_or_panic(action_config, "key");
+        let env = PostActionAccessor::get_optional_string(action_config, "env")
+            .unwrap_or_else(|| CmdContext::get_env().to_string());
+
+        move |call_record| {
+            let mut details = serde_json::Map::new();
+            details.insert("input".to_string(), input.clone().into());
+            details.insert("key".to_string(), key_to_set.clone().into());
+            details.insert("env".to_string(), env.clone().into());
+
+            let set_res = PostAction::get_input_value(call_record, &input).and_then(|value| {
+                let Some(mut persister) = PersisterProvider::get_lock_to_persister() else {
+                    return Err("error accessing the persister".to_string());
+                };
+
+                persister
+                    .set_variable_in_env(&key_to_set, &value, &env, &format!("after {}", call_record.drop_id()))
+                    .map_err(|err| err.to_string())?;
+
+                text_println!(
+                    "variable {} in environment {} set successfully.\n",
+                    key_to_set.yellow(),
+                    env.yellow()
+                );
+
+                Ok(())
+            });
+
+            PostAction::to_effect(AFTER_SET_VARIABLE_TYPE_VALUE, details, set_res)
+        }
+    }
+
+    /// a line with the `input` output, or the call's summary, appended
+    /// to `path`- output values that are json are written as json
+    pub fn post_action_append_jsonl(
+        action_config: &HashMap<String, ActionValue>,
+    ) -> impl FnMut(&CallRecord) -> AfterActionEffect {
+        let path = PostActionAccessor::get_string_or_panic(action_config, "path");
+        let input = PostActionAccessor::get_optional_trav(action_config, "input")
+            .map(|input| HclBlock::traversal_to_string(&input));
+
+        move |call_record| {
+            let mut details = serde_json::Map::new();
+            details.insert("input".to_string(), input.clone().into());
+            details.insert("path".to_string(), path.clone().into());
+
+            let line_res = match &input {
+                Some(input) => PostAction::get_input_value(call_record, input).map(|value| {
+                    serde_json::from_str::<serde_json::Value>(&value)
+                        .unwrap_or(serde_json::Value::String(value))
+                        .to_string()
+                }),
+                None => Ok(Redactor::redact(&PostAction::get_summary(call_record).to_string())),
+            };
+
+            let append_res = line_res.and_then(|line| {
+                let path = Path::new(&path);
+
+                if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent).map_err(|err| err.to_string())?;
                 }
 
-                if !matched {
-                    log::error!("{} error: {} not found in outputs", "post action set_secret".blue(), input.yellow());
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|err| err.to_string())?;
+
+                writeln!(file, "{line}").map_err(|err| err.to_string())
+            });
+
+            PostAction::to_effect(AFTER_APPEND_JSONL_TYPE_VALUE, details, append_res)
+        }
+    }
+
+    /// pipes the `input` output, or the raw response body, to
+    /// `command` and prints its stdout- the environment is passed
+    /// to the command as `DROP_ENV`
+    pub fn post_action_exec(
+        action_config: &HashMap<String, ActionValue>,
+    ) -> impl FnMut(&CallRecord) -> AfterActionEffect {
+        let command = PostActionAccessor::get_list_or_panic(action_config, "command");
+        let input = PostAction::get_input_key(action_config);
+
+        move |call_record| {
+            let mut details = serde_json::Map::new();
+            details.insert("input".to_string(), input.clone().into());
+            details.insert("command".to_string(), command.clone().into());
+
+            let exec_res = PostAction::get_input_value(call_record, &input).and_then(|value| {
+                let Some((program, args)) = command.split_first() else {
+                    return Err("command must not be empty".to_string());
+                };
+
+                let mut child = Command::new(program)
+                    .args(args)
+                    .env("DROP_ENV", CmdContext::get_env())
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .map_err(|err| format!("failed to run {program}: {err}"))?;
+
+                // written from another thread while the output is
+                // read, so a command that streams its input back
+                // can't fill the stdout pipe and block- one that
+                // exits without reading closes the pipe early
+                let stdin_writer = child.stdin.take().map(|mut stdin| {
+                    thread::spawn(move || {
+                        let _ = stdin.write_all(value.as_bytes());
+                    })
+                });
+
+                let output = child.wait_with_output().map_err(|err| err.to_string())?;
+
+                if let Some(stdin_writer) = stdin_writer {
+                    let _ = stdin_writer.join();
+                }
+
+                details.insert("exit_code".to_string(), output.status.code().into());
+
+                text_println!("{}", String::from_utf8_lossy(&output.stdout).trim_end());
+
+                if !output.status.success() {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    return Err(format!("{program} exited with {}: {}", output.status, stderr.trim()));
+                }
+
+                Ok(())
+            });
+
+            PostAction::to_effect(AFTER_EXEC_TYPE_VALUE, details, exec_res)
+        }
+    }
+
+    /// posts the call's summary to `url` as json, with
+    /// sensitive values redacted
+    pub fn post_action_http_notify(
+        action_config: &HashMap<String, ActionValue>,
+    ) -> impl FnMut(&CallRecord) -> AfterActionEffect {
+        let url = PostActionAccessor::get_string_or_panic(action_config, "url");
+
+        move |call_record| {
+            let mut details = serde_json::Map::new();
+            details.insert("url".to_string(), url.clone().into());
+
+            let summary = Redactor::redact(&PostAction::get_summary(call_record).to_string());
+
+            let notify_res = PostAction::send_http_notify(&url, summary, call_record).and_then(|mut response| {
+                let status = response.status();
+
+                details.insert("status".to_string(), status.as_u16().into());
+
+                if status.is_success() {
+                    Ok(())
+                } else {
+                    let body = response.text().unwrap_or_default();
+                    Err(format!("{url} responded with {status}: {}", body.trim()))
+                }
+            });
+
+            PostAction::to_effect(AFTER_HTTP_NOTIFY_TYPE_VALUE, details, notify_res)
+        }
+    }
+
+    /// sent with the run's client, and the environment's
+    /// proxy and dns settings, like a call
+    fn send_http_notify(
+        url: &str,
+        summary: String,
+        call_record: &CallRecord,
+    ) -> Result<isahc::Response<isahc::Body>, String> {
+        let client = match &call_record.client {
+            Some(client) => client.clone(),
+            None => DropClient::build().map_err(|err| format!("error building http client: {err}"))?,
+        };
+
+        // copied out, so the scope isn't held while sending
+        let settings = {
+            let env_var_scope = GlobalScopeProvider::get_mut().map_err(|err| err.to_string())?;
+
+            let mut settings = CallSettings::default();
+            settings.apply_scope_defaults(&env_var_scope, AFTER_HTTP_NOTIFY_TYPE_VALUE);
+            settings
+        };
+
+        let uri = url.parse::<Uri>().map_err(|err| format!("invalid url {url}: {err}"))?;
+
+        let request = Request::post(url)
+            .header("content-type", "application/json")
+            .timeout(HTTP_NOTIFY_TIMEOUT);
+
+        let request = Network::configure_request(&settings, request, &uri)
+            .body(summary)
+            .map_err(|err| err.to_string())?;
+
+        client.send(request).map_err(|err| err.to_string())
+    }
+
+    /// why the action shouldn't run for the call- its `on`,
+    /// `only_if_asserts_pass` and `when` conditions, in order
+    ///
+    /// `after = [{ type = "set_secret", ..., on = "success", when = response.status == 200 }]`
+    pub fn get_skip_reason(
+        action_config: &HashMap<String, ActionValue>,
+        call_record: &CallRecord,
+    ) -> Option<String> {
+        let status = call_record
+            .status_code()
+            .map(|status| status.as_u16().to_string())
+            .unwrap_or_default();
+
+        let default_on = match action_config.get(TYPE_PROP) {
+            Some(ActionValue::String(action_type)) => PluginRegistry::get()
+                .get_after_action(action_type)
+                .map_or("always".to_string(), |after_action| after_action.default_on().to_string()),
+            _ => "always".to_string(),
+        };
+
+        let on = PostActionAccessor::get_optional_string(action_config, AFTER_ON_PROP)
+            .unwrap_or(default_on);
+
+        match on.as_str() {
+            "success" if !call_record.is_successful_call => {
+                return Some(format!("runs on success, the call responded with {status}"))
+            }
+            "failure" if call_record.is_successful_call => {
+                return Some(format!("runs on failure, the call responded with {status}"))
+            }
+            _ => {}
+        }
+
+        let only_if_asserts_pass = matches!(
+            action_config.get(AFTER_ONLY_IF_ASSERTS_PASS_PROP),
+            Some(ActionValue::Bool(true))
+        );
+
+        let failed_asserts = call_record
+            .assert_results()
+            .iter()
+            .filter(|assert_result| assert_result["success"] != serde_json::Value::Bool(true))
+            .count();
+
+        if only_if_asserts_pass && failed_asserts > 0 {
+            return Some(format!("runs only if asserts pass, {failed_asserts} failed"));
+        }
+
+        if let Some(ActionValue::Expression(when)) = action_config.get(AFTER_WHEN_PROP) {
+            match PostAction::evaluate_when(when, call_record) {
+                Ok(true) => {}
+                Ok(false) => return Some(format!("when {when} is false")),
+                Err(err) => {
+                    log::error!("{} error: {err}", "post action when".blue());
+                    return Some(format!("when {when} could not be evaluated"));
                 }
             }
+        }
+
+        None
+    }
+
+    /// `when`, with `response.status`, `response.headers`
+    /// and `response.body` from the call
+    fn evaluate_when(when: &Expression, call_record: &CallRecord) -> Result<bool, String> {
+        let full_response = call_record.full_response().clone().unwrap_or_default();
+
+        let body = match ResponseWalker::deserialize_response_json(&full_response) {
+            Ok(body) => hcl::to_value(body).map_err(|err| err.to_string())?,
+            Err(_) => hcl::Value::String(full_response),
         };
+
+        let headers: hcl::Map<String, hcl::Value> = call_record
+            .response_headers()
+            .iter()
+            .map(|(key, value)| (key.to_string(), hcl::Value::String(value.to_string())))
+            .collect();
+
+        let mut response = hcl::Map::new();
+        response.insert(
+            "status".to_string(),
+            call_record.status_code().map_or(hcl::Value::Null, |status| status.as_u16().into()),
+        );
+        response.insert("headers".to_string(), hcl::Value::Object(headers));
+        response.insert(BODY_PROP.to_string(), body);
+
+        let mut ctx = GlobalInterpreterContext::create();
+        ctx.declare_var(RESPONSE_PROP, hcl::Value::Object(response));
+
+        match when.evaluate(&ctx) {
+            Ok(hcl::Value::Bool(holds)) => Ok(holds),
+            Ok(other) => Err(format!("when must be true or false, found {other}")),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    /// the call's result, for `http_notify` and
+    /// `append_jsonl` without an input
+    pub fn get_summary(call_record: &CallRecord) -> serde_json::Value {
+        serde_json::json!({
+            "drop_id": call_record.drop_id(),
+            "env": call_record.env(),
+            "method": call_record.method(),
+            "url": call_record.full_url(),
+            "status": call_record.status_code().map(|status| status.as_u16()),
+            "success": call_record.is_successful_call(),
+            "duration_ms": call_record.duration_ms(),
+            "asserts": call_record.assert_results(),
+        })
+    }
+
+    /// the `input` traversal as its output key- `response.body`
+    /// without one, for the raw response body
+    fn get_input_key(action_config: &HashMap<String, ActionValue>) -> String {
+        match PostActionAccessor::get_optional_trav(action_config, "input") {
+            Some(input) => HclBlock::traversal_to_string(&input),
+            None => format!("{RESPONSE_PROP}.{BODY_PROP}"),
+        }
+    }
+
+    fn get_input_value(call_record: &CallRecord, input: &str) -> Result<String, String> {
+        let output_value = call_record
+            .output_records()
+            .iter()
+            .flatten()
+            .find(|output_record| output_record.key() == input)
+            .map(|output_record| output_record.value().to_string());
+
+        match output_value {
+            Some(output_value) => Ok(output_value),
+            None if input == format!("{RESPONSE_PROP}.{BODY_PROP}") => {
+                Ok(call_record.full_response().clone().unwrap_or_default())
+            }
+            None => Err(format!("{input} not found in outputs")),
+        }
+    }
+
+    pub fn to_effect(
+        action_type: &str,
+        details: serde_json::Map<String, serde_json::Value>,
+        action_res: Result<(), String>,
+    ) -> AfterActionEffect {
+        if let Err(err) = &action_res {
+            log::error!("{} error: {err}", format!("post action {action_type}").blue());
+        }
+
+        AfterActionEffect {
+            action_type: action_type.to_string(),
+            success: action_res.is_ok(),
+            skipped: None,
+            details,
+        }
     }
 }
@@ -0,0 +1,311 @@
+use std::{process::Command, thread};
+
+use colored::Colorize;
+use hcl::{
+    eval::{Context, Evaluate},
+    Expression, Map, ObjectKey, Value,
+};
+use indexmap::IndexMap;
+use isahc::HttpClient;
+
+use crate::{
+    call::call_settings::CallSettings,
+    caller::{verbose::VerboseDump, Caller},
+    cmd::ctx::CmdContext,
+    constants::*,
+    interpreter::{diagnostics::EvalDiagnostics, evaluate::Evaluator, scope::Scope},
+    parser::{
+        drop_block::DropBlock,
+        hcl_block::{HclBlock, HclObject},
+        types::{DropBlockType, DropResourceType},
+    },
+    runner::drop_run::DropRun,
+    secret_provider::SecretProviderManager,
+    text_println,
+    util::jwt::Jwt,
+};
+
+use super::PostAction;
+
+/// `type` values of a before action, and the keys each must set
+pub const BEFORE_ACTIONS: [(&str, &[&str]); 4] = [
+    (BEFORE_ENSURE_SECRET_TYPE_VALUE, &["key", "run"]),
+    (BEFORE_SET_INPUT_TYPE_VALUE, &["key", "value"]),
+    (BEFORE_SLEEP_TYPE_VALUE, &["duration"]),
+    (BEFORE_EXEC_TYPE_VALUE, &["command", "key"]),
+];
+
+/// actions in a call's `before` array, run in order
+/// before the call block is evaluated
+///
+/// `before = [{ type = "ensure_secret", key = "auth_token", run = example.post.login }]`
+pub struct PreAction {}
+
+impl PreAction {
+    /// each action is evaluated after the ones before it,
+    /// so it can use the inputs and secrets they set
+    ///
+    /// `before_chain` is the calls whose `ensure_secret`
+    /// actions led to this one
+    pub fn run_pre_action_callbacks(
+        call_drop_container: &DropBlock,
+        env_var_scope: &mut Context<'static>,
+        input_index_map: &mut IndexMap<String, Value>,
+        client: &HttpClient,
+        before_chain: &[String],
+    ) -> Result<(), String> {
+        let DropBlockType::Call(call_block) = &call_drop_container.drop_block else {
+            return Ok(());
+        };
+
+        let Some(before_objects) = &call_block.before else {
+            return Ok(());
+        };
+
+        let drop_id = call_drop_container.drop_id.as_ref().unwrap().drop_id().unwrap();
+
+        for before_object in before_objects {
+            Scope::insert_object_into_hcl_context(env_var_scope, INPUT_OBJECT_VAR_PREFIX, input_index_map);
+
+            let action = PreAction::evaluate_action(before_object, env_var_scope)?;
+
+            let action_type = match action.get(TYPE_PROP) {
+                Some(Value::String(action_type)) => action_type.to_string(),
+                _ => return Err("before actions must set a type".to_string()),
+            };
+
+            let action_res = match action_type.as_str() {
+                BEFORE_ENSURE_SECRET_TYPE_VALUE => {
+                    PreAction::pre_action_ensure_secret(&action, &drop_id, env_var_scope, client, before_chain)
+                }
+                BEFORE_SET_INPUT_TYPE_VALUE => PreAction::pre_action_set_input(&action, input_index_map),
+                BEFORE_SLEEP_TYPE_VALUE => PreAction::pre_action_sleep(&action),
+                BEFORE_EXEC_TYPE_VALUE => PreAction::pre_action_exec(&action, input_index_map),
+                _ => Err(format!(
+                    "unknown type, expected one of {BEFORE_ENSURE_SECRET_TYPE_VALUE}, {BEFORE_SET_INPUT_TYPE_VALUE}, {BEFORE_SLEEP_TYPE_VALUE} or {BEFORE_EXEC_TYPE_VALUE}"
+                )),
+            };
+
+            action_res.map_err(|err| format!("before {action_type} error: {err}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// the action object with its expressions evaluated- `run`
+    /// is a drop id, like `hit` in a run block, so it isn't
+    fn evaluate_action(before_object: &HclObject, env_var_scope: &Context<'_>) -> Result<Map<String, Value>, String> {
+        let mut before_object = before_object.to_owned();
+
+        let run_key = ObjectKey::from("run");
+
+        if let Some(Expression::Traversal(run)) = before_object.get(&run_key) {
+            let run = HclBlock::traversal_to_string(run);
+            before_object.insert(run_key, Expression::String(run));
+        }
+
+        let mut expr = Expression::Object(before_object);
+
+        if let Err(errors) = expr.evaluate_in_place(env_var_scope) {
+            let hints = EvalDiagnostics::missing_secret_hints(&errors, &CmdContext::get_env());
+            return Err(format!("error evaluating before action: {errors}\n\n{hints}"));
+        }
+
+        match HclBlock::value_from_expr(expr) {
+            Value::Object(action) => Ok(action),
+            _ => Err("before actions must be objects".to_string()),
+        }
+    }
+
+    /// hit `run` when the secret is missing, empty or an expired
+    /// jwt- the call is expected to store it with `set_secret`
+    fn pre_action_ensure_secret(
+        action: &Map<String, Value>,
+        drop_id: &str,
+        env_var_scope: &mut Context<'static>,
+        client: &HttpClient,
+        before_chain: &[String],
+    ) -> Result<(), String> {
+        let key = PreAction::get_string(action, "key")?;
+        let run = PreAction::get_string(action, "run")?;
+
+        if !PreAction::is_secret_missing(env_var_scope, &key) {
+            VerboseDump::note(&format!("before ensure_secret {key} is set"));
+            return Ok(());
+        }
+
+        let mut before_chain = before_chain.to_vec();
+        before_chain.push(drop_id.to_string());
+
+        // e.g. a login call whose before actions ensure a
+        // secret with the call that needed the login
+        if before_chain.contains(&run) {
+            return Err(format!("before action cycle: {} -> {run}", before_chain.join(" -> ")));
+        }
+
+        text_println!(
+            "secret {} is missing or expired, hitting {}\n",
+            key.yellow(),
+            run.yellow()
+        );
+
+        let mut drop_run = PreAction::get_drop_run(&run)?;
+
+        drop_run
+            .try_run_nested_before_actions(client, &before_chain)
+            .map_err(|err| format!("{run}: {err}"))?;
+
+        let drop_call = drop_run.try_get_drop_call(IndexMap::new())?;
+
+        let caller = Caller { drop_call, client: client.clone() };
+
+        let call_record = caller.call().map_err(|err| format!("{run} failed: {err}"))?;
+
+        PostAction::run_post_action_callbacks(call_record);
+
+        // secrets.*, and the auth blocks, environment and
+        // module that reference them, with the new secret
+        *env_var_scope = PreAction::refresh_scope(drop_id)?;
+
+        if PreAction::is_secret_missing(env_var_scope, &key) {
+            return Err(format!("{run} did not set secret {key}"));
+        }
+
+        Ok(())
+    }
+
+    fn pre_action_set_input(
+        action: &Map<String, Value>,
+        input_index_map: &mut IndexMap<String, Value>,
+    ) -> Result<(), String> {
+        let key = PreAction::get_string(action, "key")?;
+        let value = action.get("value").ok_or("set_input must set value")?;
+
+        VerboseDump::note(&format!("before set_input {key}"));
+
+        input_index_map.insert(key, value.to_owned());
+
+        Ok(())
+    }
+
+    fn pre_action_sleep(action: &Map<String, Value>) -> Result<(), String> {
+        let duration = action.get("duration").ok_or("sleep must set duration")?;
+        let duration = CallSettings::parse_duration(duration)?;
+
+        VerboseDump::note(&format!("before sleep {duration:?}"));
+
+        thread::sleep(duration);
+
+        Ok(())
+    }
+
+    /// runs `command` and sets stdout, without its trailing
+    /// newline, as `inputs.[key]`- the environment is passed
+    /// to the command as `DROP_ENV`
+    fn pre_action_exec(
+        action: &Map<String, Value>,
+        input_index_map: &mut IndexMap<String, Value>,
+    ) -> Result<(), String> {
+        let key = PreAction::get_string(action, "key")?;
+
+        let command = match action.get("command") {
+            Some(Value::Array(command)) => command
+                .iter()
+                .map(|arg| match arg {
+                    Value::String(arg) => Some(arg.to_string()),
+                    Value::Number(arg) => Some(arg.to_string()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<String>>>(),
+            _ => None,
+        };
+
+        let command = command.ok_or("command must be an array, e.g. [\"git\", \"rev-parse\", \"HEAD\"]")?;
+
+        let Some((program, args)) = command.split_first() else {
+            return Err("command must not be empty".to_string());
+        };
+
+        VerboseDump::note(&format!("before exec {}", command.join(" ")));
+
+        let output = Command::new(program)
+            .args(args)
+            .env("DROP_ENV", &CmdContext::get_env())
+            .output()
+            .map_err(|err| format!("failed to run {program}: {err}"))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("{program} exited with {}: {}", output.status, stderr.trim()));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        input_index_map.insert(key, Value::String(stdout.trim_end_matches(['\r', '\n']).to_string()));
+
+        Ok(())
+    }
+
+    /// a run of the call, like `drop hit [run]`, with secrets
+    /// set by earlier before actions in its inputs
+    fn get_drop_run(run: &str) -> Result<DropRun, String> {
+        let call_drop_container = Evaluator::get_selected_container(run, DropResourceType::Call)
+            .map_err(|err| err.to_string())?;
+
+        let mut env_var_scope = PreAction::refresh_scope(run)?;
+
+        let mut input_index_map = IndexMap::<String, Value>::new();
+
+        if let DropBlockType::Call(call_block) = &call_drop_container.drop_block {
+            if let Some(inputs) = &call_block.inputs {
+                input_index_map = Evaluator::evaluate_input_block_and_create_index_map(
+                    inputs.clone(),
+                    &mut env_var_scope,
+                );
+            };
+        }
+
+        Ok(DropRun {
+            call_drop_container,
+            input_index_map,
+            env_var_scope,
+            depends_on: None,
+            call_block_overwrites: None,
+        })
+    }
+
+    fn is_secret_missing(env_var_scope: &Context<'_>, key: &str) -> bool {
+        match CallSettings::get_scope_value(env_var_scope, SECRET_OBJECT_VAR_PREFIX, key) {
+            Some(Value::String(secret)) => secret.is_empty() || Jwt::is_expired(&secret),
+            Some(Value::Null) | None => true,
+            Some(_) => false,
+        }
+    }
+
+    /// the variable scope for the call, with
+    /// the environment's current secrets
+    fn refresh_scope(drop_id: &str) -> Result<Context<'static>, String> {
+        let env = &CmdContext::get_env();
+
+        let secrets_hash_for_env =
+            SecretProviderManager::resolve_secrets_for_env(env).map_err(|err| err.to_string())?;
+
+        let mut env_var_scope =
+            Scope::evaluate_variable_scope(secrets_hash_for_env, env).map_err(|err| err.to_string())?;
+
+        let module_block = Evaluator::get_selected_module_block(drop_id).map_err(|err| err.to_string())?;
+
+        Evaluator::generate_module_context(&module_block, &mut env_var_scope);
+
+        Ok(env_var_scope)
+    }
+
+    fn get_string(action: &Map<String, Value>, key: &str) -> Result<String, String> {
+        match action.get(key) {
+            Some(Value::String(value)) => Ok(value.to_string()),
+            Some(Value::Number(value)) => Ok(value.to_string()),
+            Some(Value::Null) | None => Err(format!("{key} must be set")),
+            Some(other) => Err(format!("{key} must be a string, found {other}")),
+        }
+    }
+}
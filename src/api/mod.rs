@@ -0,0 +1,332 @@
+use std::{
+    any::Any,
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{Arc, Mutex, Once},
+};
+
+use indexmap::IndexMap;
+use isahc::HttpClient;
+use log::LevelFilter;
+use simplelog::{ColorChoice, ConfigBuilder, TermLogger, TerminalMode};
+use thiserror::Error;
+
+use crate::{
+    action::PostAction,
+    call::DropCall,
+    caller::{client::DropClient, Caller},
+    cmd::{
+        cli::{Cli, Command, LogLevelInput, OutputFormat},
+        commands::hit::HitCommand,
+        ctx::CmdContext,
+        output::Output,
+    },
+    interpreter::scope::{GlobalScopeProvider, Scope},
+    parser::{
+        drop_id::{CallType, DropId},
+        file_walker::FileWalker,
+        GlobalDropConfig, GlobalDropConfigProvider,
+    },
+    plugin::PluginRegistry,
+    record::CallRecord,
+    runner::drop_run::DropRun,
+    secret_provider::SecretProviderManager,
+    util::{
+        exit::{self, DropExit},
+        redact::{RedactingLogger, Redactor},
+    },
+};
+
+/// drop's state is global, so one session
+/// operation runs at a time per process
+static SESSION_LOCK: Mutex<()> = Mutex::new(());
+
+static EMBED_SETUP: Once = Once::new();
+
+thread_local! {
+    /// panics inside a session operation become
+    /// `DropError`s instead of printing
+    static IN_SESSION: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum DropError {
+    #[error("error loading {dir}: {msg}")]
+    Load { dir: String, msg: String },
+
+    #[error("{drop_id} is not a drop id- pass one of: {available}")]
+    InvalidDropId { drop_id: String, available: String },
+
+    #[error("{drop_id}: chains are not supported by the library")]
+    ChainUnsupported { drop_id: String },
+
+    #[error("{drop_id}: {msg}")]
+    Call { drop_id: String, msg: String },
+}
+
+/// a dropfile directory loaded for an environment, for
+/// drop embedded in tests and other programs
+///
+/// `let session = DropSession::load("./examples", "base")?;`
+/// `let record = session.hit("jsonplaceholder.get.posts", IndexMap::new())?;`
+///
+/// sessions share drop's global state- each operation
+/// restores its session's config and scope first, and
+/// records and variables are stored in `drop.db` in the
+/// working directory, like the cli
+///
+/// the http client is built once, from the environment's
+/// client settings, so calls in a session share connections
+pub struct DropSession {
+    dir: String,
+    env: String,
+    client: HttpClient,
+    cmd_ctx: Arc<CmdContext>,
+    plugin_registry: Arc<PluginRegistry>,
+    global_drop_config: Arc<GlobalDropConfig>,
+}
+
+impl DropSession {
+    pub fn load(dir: &str, env: &str) -> Result<DropSession, DropError> {
+        DropSession::setup_embedded();
+
+        let load_error = |msg: String| DropError::Load {
+            dir: dir.to_string(),
+            msg,
+        };
+
+        if !Path::new(dir).is_dir() {
+            return Err(load_error("not a directory".to_string()));
+        }
+
+        let _lock = SESSION_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+        DropSession::catch_exit(|| {
+            let cmd_ctx = CmdContext::set(Cli {
+                command: Command::hit {
+                    drop_id: String::new(),
+                    verbose: false,
+                    transcript: None,
+                },
+                level: LogLevelInput::Info,
+                env: env.to_string(),
+                dir: dir.to_string(),
+                output: OutputFormat::Silent,
+                non_interactive: true,
+                unsafe_show_secrets: false,
+            });
+
+            let plugin_registry = PluginRegistry::load(dir)
+                .map_err(|err| load_error(format!("error loading plugins: {err}")))?;
+            let plugin_registry = PluginRegistry::set(plugin_registry);
+
+            let drop_files = FileWalker::resolve_drop_files(dir)
+                .map_err(|err| load_error(format!("error reading drop files: {err}")))?;
+
+            let global_drop_config = GlobalDropConfig::from_drop_files(&drop_files)
+                .map_err(|err| load_error(format!("error resolving drop files: {err:?}")))?;
+            let global_drop_config = GlobalDropConfigProvider::set(global_drop_config);
+
+            DropSession::set_variable_scope(env).map_err(load_error)?;
+
+            let client = DropClient::build()
+                .map_err(|err| load_error(format!("error building http client: {err}")))?;
+
+            Ok(DropSession {
+                dir: dir.to_string(),
+                env: env.to_string(),
+                client,
+                cmd_ctx,
+                plugin_registry,
+                global_drop_config,
+            })
+        })
+        .map_err(load_error)?
+    }
+
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    pub fn env(&self) -> &str {
+        &self.env
+    }
+
+    /// the call or run evaluated in the session's environment,
+    /// without running its before actions- like `drop give`
+    pub fn evaluate(&self, drop_id: &str) -> Result<DropCall, DropError> {
+        self.with_drop_run(drop_id, IndexMap::new(), |mut drop_run| {
+            drop_run.try_get_drop_call(IndexMap::new())
+        })
+    }
+
+    /// sends the call or run, with `inputs` replacing the
+    /// block's inputs, and runs its after actions- like `drop hit`
+    ///
+    /// the record is returned for failed calls- an error is
+    /// returned when the call couldn't be made
+    pub fn hit(
+        &self,
+        drop_id: &str,
+        inputs: IndexMap<String, hcl::Value>,
+    ) -> Result<CallRecord, DropError> {
+        self.with_drop_run(drop_id, inputs, |mut drop_run| {
+            drop_run.try_run_before_actions(&self.client)?;
+
+            let drop_call = drop_run.try_get_drop_call(IndexMap::new())?;
+
+            let caller = Caller {
+                drop_call,
+                client: self.client.clone(),
+            };
+
+            let call_record = caller.call().map_err(|err| err.to_string())?;
+
+            Ok(PostAction::run_post_action_callbacks(call_record))
+        })
+    }
+
+    /// restores the session's globals, and builds the
+    /// drop run for the id as `drop hit` would
+    fn with_drop_run<T>(
+        &self,
+        drop_id: &str,
+        inputs: IndexMap<String, hcl::Value>,
+        operation: impl FnOnce(DropRun) -> Result<T, String>,
+    ) -> Result<T, DropError> {
+        let _lock = SESSION_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+        let call_error = |msg: String| DropError::Call {
+            drop_id: drop_id.to_string(),
+            msg,
+        };
+
+        DropSession::catch_exit(|| self.activate())
+            .and_then(|activated| activated)
+            .map_err(call_error)?;
+
+        if !DropId::is_drop_id(drop_id) {
+            let available = GlobalDropConfigProvider::get().get_drop_ids_in_module(drop_id);
+
+            if available.is_empty() {
+                return Err(call_error(format!("no calls found in module {drop_id}")));
+            }
+
+            return Err(DropError::InvalidDropId {
+                drop_id: drop_id.to_string(),
+                available: available.join(", "),
+            });
+        }
+
+        if let CallType::Chain = DropId::get_call_type_from_raw_drop_id(drop_id) {
+            return Err(DropError::ChainUnsupported {
+                drop_id: drop_id.to_string(),
+            });
+        }
+
+        DropSession::catch_exit(|| {
+            let hit_command = HitCommand {
+                input_drop_id_string: drop_id.to_string(),
+            };
+
+            let mut drop_runs = match DropId::get_call_type_from_raw_drop_id(drop_id) {
+                CallType::Run => hit_command.try_run_run(),
+                _ => hit_command.try_run_call(),
+            }
+            .map_err(|err| err.to_string())?;
+
+            let mut drop_run = drop_runs.remove(0);
+
+            drop_run.input_index_map.extend(inputs);
+
+            operation(drop_run)
+        })
+        .and_then(|res| res)
+        .map_err(call_error)
+    }
+
+    /// secrets and variables are resolved again, as
+    /// earlier calls in the session can set them
+    fn activate(&self) -> Result<(), String> {
+        CmdContext::set_arc(Arc::clone(&self.cmd_ctx));
+        PluginRegistry::set_arc(Arc::clone(&self.plugin_registry));
+        GlobalDropConfigProvider::set_arc(Arc::clone(&self.global_drop_config));
+
+        DropSession::set_variable_scope(&self.env)
+    }
+
+    fn set_variable_scope(env: &str) -> Result<(), String> {
+        let secrets_hash_for_env = SecretProviderManager::resolve_secrets_for_env(env)
+            .map_err(|err| format!("error resolving secrets: {err:?}"))?;
+
+        let scope = Scope::evaluate_variable_scope(secrets_hash_for_env, env)
+            .map_err(|err| format!("error resolving variable scope: {err:?}"))?;
+
+        GlobalScopeProvider::set(scope);
+
+        Ok(())
+    }
+
+    /// session operations return their errors- exits and
+    /// panics deeper in drop still unwind to here, with
+    /// the last error logged as the message
+    fn catch_exit<T>(operation: impl FnOnce() -> T) -> Result<T, String> {
+        exit::take_last_error();
+
+        IN_SESSION.with(|in_session| in_session.set(true));
+
+        let res = panic::catch_unwind(AssertUnwindSafe(operation));
+
+        IN_SESSION.with(|in_session| in_session.set(false));
+
+        res.map_err(|payload| DropSession::panic_message(payload.as_ref()))
+    }
+
+    fn panic_message(payload: &(dyn Any + Send)) -> String {
+        if let Some(DropExit { code }) = payload.downcast_ref::<DropExit>() {
+            return exit::take_last_error()
+                .unwrap_or_else(|| format!("drop exited with code {code}"));
+        }
+
+        let message = payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| payload.downcast_ref::<&str>().map(|msg| msg.to_string()))
+            .unwrap_or_else(|| "drop panicked".to_string());
+
+        Redactor::redact(&message)
+    }
+
+    /// once per process- silences output, logs warnings
+    /// to stderr unless the host set a logger, and
+    /// unwinds instead of exiting
+    fn setup_embedded() {
+        EMBED_SETUP.call_once(|| {
+            exit::set_embedded();
+
+            Output::set_format(OutputFormat::Silent);
+
+            colored::control::set_override(false);
+
+            let default_hook = panic::take_hook();
+
+            panic::set_hook(Box::new(move |info| {
+                if !IN_SESSION.with(Cell::get) {
+                    default_hook(info);
+                }
+            }));
+
+            let term_logger = TermLogger::new(
+                LevelFilter::Warn,
+                ConfigBuilder::new().build(),
+                TerminalMode::Stderr,
+                ColorChoice::Never,
+            );
+
+            if log::set_boxed_logger(Box::new(RedactingLogger { inner: term_logger })).is_ok() {
+                log::set_max_level(LevelFilter::Warn);
+            }
+        });
+    }
+}
@@ -2,7 +2,10 @@ use colored::Colorize;
 use isahc::http::HeaderMap;
 
 use crate::{
+    cmd::output::Output,
     parser::hcl_block::HclBlock,
+    text_println,
+    util::jwt::Jwt,
     record::response_walker::{JsonWalkError, OutputType, ResponseWalker},
 };
 
@@ -23,8 +26,8 @@ impl CallAssertion {
         asserts: Vec<Assert>,
         response_string: &str,
         response_headers: &HeaderMap,
-    ) {
-        println!("\n{drop_id} {}", "assertions".yellow());
+    ) -> Vec<serde_json::Value> {
+        text_println!("\n{drop_id} {}", "assertions".yellow());
 
         let mut assert_results = Vec::<AssertResult>::new();
         for assert in asserts {
@@ -39,9 +42,13 @@ impl CallAssertion {
             });
         }
 
-        if !assert_results.is_empty() {
+        let assert_results_json = assert_results.iter().map(AssertResult::to_json).collect();
+
+        if !assert_results.is_empty() && Output::is_text() {
             AssertResult::report_results(assert_results);
         }
+
+        assert_results_json
     }
 
     #[log_attributes::log(trace, "exit {fn} drop_id: {drop_id:?} assert:{assert:?} response_string: {response_string} response_headers: {response_headers:?}")]
@@ -96,13 +103,13 @@ impl CallAssertion {
                     Ok(response_walker_result) => {
                         let actual_result_value = response_walker_result.result_value;
 
-                        CallAssertion::run_assertion_value(actual_result_value, assert)
+                        CallAssertion::run_assertion_target_value(actual_result_value, assert)
                     }
                     Err(walk_err) => CallAssertion::handle_walk_err_assertions(assert, &walk_err),
                 }
             }
             Err(err) => {
-                CallAssertion::run_assertion_value(serde_json::Value::String(response_string.to_string()), assert)
+                CallAssertion::run_assertion_target_value(serde_json::Value::String(response_string.to_string()), assert)
             }
         }
     }
@@ -121,12 +128,61 @@ impl CallAssertion {
 
         match walk_result {
             Ok(header_result_value) => {
-                CallAssertion::run_assertion_value(header_result_value, assert)
+                CallAssertion::run_assertion_target_value(header_result_value, assert)
             }
             Err(walk_err) => CallAssertion::handle_walk_err_assertions(assert, &walk_err),
         }
     }
 
+    /// the response value, or for `jwt(response.body.token).claims.sub`,
+    /// the claim from the decoded token
+    fn run_assertion_target_value(
+        result_value: serde_json::Value,
+        assert: &Assert,
+    ) -> AssertResultOutcome {
+        let Some(jwt_operators) = &assert.jwt_operators else {
+            return CallAssertion::run_assertion_value(result_value, assert);
+        };
+
+        let decoded = match result_value.as_str().map(Jwt::decode) {
+            Some(Ok(decoded)) => decoded,
+            Some(Err(err)) => {
+                return AssertResultOutcome::FailureOnError(AssertionError::DeserializationError { msg: err })
+            }
+            None => {
+                return AssertResultOutcome::FailureOnError(AssertionError::DeserializationError {
+                    msg: format!("jwt assertion expected a string token, found {result_value}"),
+                })
+            }
+        };
+
+        let mut jwt_level = &decoded;
+
+        for operator in jwt_operators {
+            let next_level = match operator {
+                hcl::TraversalOperator::GetAttr(key) => jwt_level.get(key.as_str()),
+                hcl::TraversalOperator::Index(hcl::Expression::String(key)) => jwt_level.get(key),
+                hcl::TraversalOperator::Index(hcl::Expression::Number(index)) => {
+                    index.as_u64().and_then(|index| jwt_level.get(index as usize))
+                }
+                _ => None,
+            };
+
+            match next_level {
+                Some(next_level) => jwt_level = next_level,
+                None => {
+                    let walk_err = JsonWalkError::InvalidFinalValue {
+                        final_key_to_resolve: assert.display_name.to_string(),
+                    };
+
+                    return CallAssertion::handle_walk_err_assertions(assert, &walk_err);
+                }
+            }
+        }
+
+        CallAssertion::run_assertion_value(jwt_level.to_owned(), assert)
+    }
+
     fn run_assertion_value(
         result_value: serde_json::Value,
         assert: &Assert,
@@ -3,6 +3,8 @@ use colored::Colorize;
 use serde_json::Value;
 use thiserror::Error;
 
+use crate::util::redact::Redactor;
+
 #[derive(Debug)]
 pub enum AssertExpectedValueObjectOperator {
     Exist,
@@ -87,6 +89,9 @@ pub struct Assert {
     pub traversal_key: hcl::Traversal,
     pub display_name: String,
     pub expected_value: AssertExpectedValue,
+    /// `.claims.sub` of `jwt(response.body.token).claims.sub`-
+    /// the response value is decoded as a jwt and walked
+    pub jwt_operators: Option<Vec<hcl::TraversalOperator>>,
 }
 
 #[derive(Debug)]
@@ -154,14 +159,35 @@ pub struct AssertResult {
 }
 
 impl AssertResult {
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut result = serde_json::json!({
+            "name": self.assert.display_name,
+            "operation": self.assert.expected_value.to_string(),
+            "success": matches!(self.outcome, AssertResultOutcome::Success),
+        });
+
+        match &self.outcome {
+            AssertResultOutcome::Success => {}
+            AssertResultOutcome::TestFailure(failure) => {
+                result["expected"] = serde_json::Value::String(failure.expected_value.to_string());
+                result["actual"] = serde_json::Value::String(failure.actual_result_value.to_string());
+            }
+            AssertResultOutcome::FailureOnError(err) => {
+                result["error"] = serde_json::Value::String(err.to_string());
+            }
+        }
+
+        result
+    }
+
     pub fn report_results(assert_results: Vec<AssertResult>) {
         let mut table = Vec::<Vec<CellStruct>>::new();
 
         for each in assert_results {
             let display_name = &each.assert.display_name;
             let display_name = display_name.cell();
-            let operation = each.assert.expected_value.to_string();
-            let result_printout = each.outcome.report().cell();
+            let operation = Redactor::redact(&each.assert.expected_value.to_string());
+            let result_printout = Redactor::redact(&each.outcome.report()).cell();
             let row = vec![display_name, operation.cell(), result_printout];
             table.push(row);
         }
@@ -0,0 +1,210 @@
+//! tls mock server for the `tls` example environment
+//!
+//! generates a certificate authority, a server certificate for
+//! localhost and a client certificate, writes the ca and client
+//! certificate to the directory passed in (`./tls` by default),
+//! and serves https on two ports- the second requires the client
+//! certificate (mtls)
+//!
+//! `cargo run --bin tls_mock -- examples/tls`
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    path::Path,
+    sync::Arc,
+    thread,
+};
+
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    ssl::{SslAcceptor, SslMethod, SslStream, SslVerifyMode},
+    x509::{
+        extension::{BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName},
+        X509NameBuilder, X509,
+    },
+};
+
+const TLS_PORT: u16 = 57443;
+const MTLS_PORT: u16 = 57444;
+
+fn main() {
+    let cert_dir = std::env::args().nth(1).unwrap_or("tls".to_string());
+    let cert_dir = Path::new(&cert_dir);
+
+    fs::create_dir_all(cert_dir).unwrap();
+
+    let ca_key = generate_key();
+    let ca_cert = generate_cert("drop mock ca", &ca_key, None, CertUse::Ca);
+
+    let server_key = generate_key();
+    let server_cert = generate_cert("localhost", &server_key, Some((&ca_cert, &ca_key)), CertUse::Server);
+
+    let client_key = generate_key();
+    let client_cert = generate_cert("drop client", &client_key, Some((&ca_cert, &ca_key)), CertUse::Client);
+
+    fs::write(cert_dir.join("ca.pem"), ca_cert.to_pem().unwrap()).unwrap();
+    fs::write(cert_dir.join("client.pem"), client_cert.to_pem().unwrap()).unwrap();
+    fs::write(
+        cert_dir.join("client.key.pem"),
+        client_key.private_key_to_pem_pkcs8().unwrap(),
+    )
+    .unwrap();
+
+    let tls_acceptor = build_acceptor(&server_cert, &server_key, None);
+    let mtls_acceptor = build_acceptor(&server_cert, &server_key, Some(&ca_cert));
+
+    thread::spawn(move || serve(TLS_PORT, tls_acceptor));
+    thread::spawn(move || serve(MTLS_PORT, mtls_acceptor));
+
+    println!("certificates written to: {}", cert_dir.display());
+    println!("tls port: {TLS_PORT:?}");
+    println!("mtls port: {MTLS_PORT:?}");
+
+    thread::park();
+}
+
+enum CertUse {
+    Ca,
+    Server,
+    Client,
+}
+
+fn generate_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+fn generate_cert(
+    common_name: &str,
+    key: &PKey<Private>,
+    issuer: Option<(&X509, &PKey<Private>)>,
+    cert_use: CertUse,
+) -> X509 {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, common_name).unwrap();
+    let name = name.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(128, MsbOption::MAYBE_ZERO, false).unwrap();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&serial.to_asn1_integer().unwrap()).unwrap();
+    cert.set_subject_name(&name).unwrap();
+    cert.set_pubkey(key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(30).unwrap()).unwrap();
+
+    match issuer {
+        Some((issuer_cert, _)) => cert.set_issuer_name(issuer_cert.subject_name()).unwrap(),
+        None => cert.set_issuer_name(&name).unwrap(),
+    }
+
+    match cert_use {
+        CertUse::Ca => {
+            cert.append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+                .unwrap();
+            cert.append_extension(KeyUsage::new().critical().key_cert_sign().crl_sign().build().unwrap())
+                .unwrap();
+        }
+        CertUse::Server => {
+            let san = SubjectAlternativeName::new()
+                .dns("localhost")
+                .ip("127.0.0.1")
+                .build(&cert.x509v3_context(issuer.map(|(issuer_cert, _)| &**issuer_cert), None))
+                .unwrap();
+
+            cert.append_extension(san).unwrap();
+            cert.append_extension(ExtendedKeyUsage::new().server_auth().build().unwrap())
+                .unwrap();
+        }
+        CertUse::Client => {
+            cert.append_extension(ExtendedKeyUsage::new().client_auth().build().unwrap())
+                .unwrap();
+        }
+    }
+
+    let signing_key = issuer.map(|(_, issuer_key)| issuer_key).unwrap_or(key);
+    cert.sign(signing_key, MessageDigest::sha256()).unwrap();
+
+    cert.build()
+}
+
+/// with a client ca, connections
+/// without a client certificate fail
+fn build_acceptor(
+    server_cert: &X509,
+    server_key: &PKey<Private>,
+    client_ca: Option<&X509>,
+) -> Arc<SslAcceptor> {
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server()).unwrap();
+    acceptor.set_certificate(server_cert).unwrap();
+    acceptor.set_private_key(server_key).unwrap();
+
+    if let Some(client_ca) = client_ca {
+        acceptor.cert_store_mut().add_cert(client_ca.clone()).unwrap();
+        acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    }
+
+    Arc::new(acceptor.build())
+}
+
+fn serve(port: u16, acceptor: Arc<SslAcceptor>) {
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+
+    for stream in listener.incoming().flatten() {
+        let acceptor = Arc::clone(&acceptor);
+
+        thread::spawn(move || match acceptor.accept(stream) {
+            Ok(mut tls_stream) => respond(&mut tls_stream),
+            Err(err) => println!("tls handshake failed on port {port}: {err}"),
+        });
+    }
+}
+
+/// `{ "path": "/status", "tls_version": "TLSv1.3", "client_cert": "drop client" }`
+fn respond(tls_stream: &mut SslStream<std::net::TcpStream>) {
+    let mut request_line = String::new();
+
+    {
+        let mut reader = BufReader::new(&mut *tls_stream);
+        reader.read_line(&mut request_line).unwrap_or_default();
+
+        // skip the headers
+        let mut header_line = String::new();
+        while reader.read_line(&mut header_line).unwrap_or_default() > 2 {
+            header_line.clear();
+        }
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let client_cert = tls_stream.ssl().peer_certificate().and_then(|cert| {
+        cert.subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .and_then(|entry| entry.data().as_utf8().ok())
+            .map(|common_name| common_name.to_string())
+    });
+
+    let body = serde_json::json!({
+        "path": path,
+        "tls_version": tls_stream.ssl().version_str(),
+        "client_cert": client_cert,
+    })
+    .to_string();
+
+    let response = format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    let _ = tls_stream.write_all(response.as_bytes());
+    let _ = tls_stream.shutdown();
+}
@@ -1,6 +1,7 @@
 use hcl::{Attribute, Expression};
 use log::error;
 
+use crate::util::exit;
 use crate::{action::PostAction, constants::*};
 
 use super::DropCall;
@@ -20,7 +21,7 @@ impl DropCall {
             }
         } else {
             error!("{drop_id:#?}: after must be an array of after action objects");
-            std::process::exit(1)
+            exit::exit(1)
         }
     }
 
@@ -34,7 +35,7 @@ impl DropCall {
                 }
 
                 DropCall::after_throw(drop_id);
-                std::process::exit(1)
+                exit::exit(1)
             };
 
             let _action_id = get_action_id();
@@ -45,7 +46,7 @@ impl DropCall {
             self.after_action_config_push(drop_id, action_hash);
         } else {
             DropCall::after_throw(drop_id);
-            std::process::exit(1)
+            exit::exit(1)
         }
     }
 
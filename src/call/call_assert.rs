@@ -15,13 +15,14 @@ impl DropCall {
         if let hcl::Expression::Object(assert_block) = attr.expr() {
             for entry in assert_block {
                 let (key, value) = entry;
-                let traversal_key = DropCall::process_assert_key(key, drop_id);
+                let (traversal_key, jwt_operators) = DropCall::process_assert_key(key, drop_id);
                 let expected_value = DropCall::process_assert_block_value(value, drop_id, key);
 
                 self.asserts.push(Assert {
                     traversal_key,
                     display_name: key.to_string(),
                     expected_value,
+                    jwt_operators,
                 });
             }
         } else {
@@ -30,16 +31,22 @@ impl DropCall {
         }
     }
 
-    pub fn process_assert_key(key: &hcl::ObjectKey, drop_id: &str) -> hcl::Traversal {
+    /// the response traversal, and for `jwt(response.body.token).claims.sub`,
+    /// the path into the decoded token
+    pub fn process_assert_key(key: &hcl::ObjectKey, drop_id: &str) -> (hcl::Traversal, Option<Vec<hcl::TraversalOperator>>) {
         let throw_msg = format!("error in assert block for {drop_id}, {key} must be a property of- response.body, response.header");
         match key {
             hcl::ObjectKey::Expression(expr) => {
                 if let hcl::Expression::Traversal(trav) = expr {
+                    if let hcl::Expression::FuncCall(func_call) = &trav.expr {
+                        return DropCall::process_assert_jwt_key(func_call, &trav.operators, drop_id);
+                    }
+
                     if let OutputType::InvalidOutput(err) = ResponseWalker::get_output_variant(trav)
                     {
                         panic!("error in assert block for {drop_id}: {err}")
                     }
-                    *trav.to_owned()
+                    (*trav.to_owned(), None)
                 } else {
                     panic!("{throw_msg}");
                 }
@@ -51,6 +58,27 @@ impl DropCall {
         }
     }
 
+    /// `jwt(response.body.token).claims.sub`
+    fn process_assert_jwt_key(
+        func_call: &hcl::FuncCall,
+        jwt_operators: &[hcl::TraversalOperator],
+        drop_id: &str,
+    ) -> (hcl::Traversal, Option<Vec<hcl::TraversalOperator>>) {
+        let throw_msg = format!("error in assert block for {drop_id}, {} must be e.g. jwt(response.body.token).claims.sub", func_call.name);
+
+        assert!(func_call.name.as_str() == "jwt" && func_call.args.len() == 1, "{throw_msg}");
+
+        let hcl::Expression::Traversal(trav) = &func_call.args[0] else {
+            panic!("{throw_msg}")
+        };
+
+        if let OutputType::InvalidOutput(err) = ResponseWalker::get_output_variant(trav) {
+            panic!("error in assert block for {drop_id}: {err}")
+        }
+
+        (*trav.to_owned(), Some(jwt_operators.to_vec()))
+    }
+
     pub fn process_assert_block_value(
         assert_block_expected_value: &hcl::Expression,
         drop_id: &str,
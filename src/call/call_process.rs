@@ -4,6 +4,7 @@ use hcl::{Attribute, Block, Expression, ObjectKey};
 use isahc::http::{HeaderName, HeaderValue};
 use serde_json::Value;
 
+use crate::util::exit;
 use crate::{constants::CALL_BODY, parser::hcl_block::HclBlock};
 use log::{error, warn};
 
@@ -56,13 +57,13 @@ impl DropCall {
                 } else {
                     let drop_id = self.drop_id.drop_id().unwrap();
                     error!("{drop_id:#?}: each output must be a variable ");
-                    std::process::exit(1)
+                    exit::exit(1)
                 }
             }
         } else {
             let drop_id = self.drop_id.drop_id().unwrap();
             error!("{drop_id:#?}: outputs must be an array of variables");
-            std::process::exit(1)
+            exit::exit(1)
         }
     }
 
@@ -82,7 +83,7 @@ impl DropCall {
         if hcl_as_serde_value.is_err() {
             let msg = hcl_as_serde_value.err();
             error!("{resource_name:#?}: request body must be a json object {msg:#?}");
-            std::process::exit(1)
+            exit::exit(1)
         }
 
         let valid_hcl_as_serde_value = hcl_as_serde_value.unwrap();
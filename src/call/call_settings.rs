@@ -0,0 +1,530 @@
+use std::{
+    net::IpAddr,
+    time::{Duration, SystemTime},
+};
+
+use hcl::{
+    eval::{Context, Evaluate},
+    Expression, Traversal, Value, Variable,
+};
+use indexmap::IndexMap;
+use isahc::http::{HeaderMap, StatusCode, Uri};
+use rand::Rng;
+
+use crate::util::exit;
+use crate::{caller::{auth::CallAuth, verbose::VerboseDump}, constants::*, script::DEFAULT_SCRIPT_TIMEOUT};
+
+use super::DropCall;
+
+/// request timeout without a `timeout` setting
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// settings read from the module and environment scope
+/// for calls that don't set them
+const CALL_SETTING_KEYS: [&str; 14] = [
+    CALL_TIMEOUT,
+    CALL_RETRIES,
+    CALL_RETRY_ON,
+    CALL_BACKOFF,
+    CALL_PROXY,
+    CALL_NO_PROXY,
+    CALL_RESOLVE,
+    CALL_FOLLOW_REDIRECTS,
+    CALL_MAX_REDIRECTS,
+    CALL_COOKIES,
+    CALL_AUTH,
+    CALL_SCRIPT_BEFORE,
+    CALL_SCRIPT_AFTER,
+    CALL_SCRIPT_TIMEOUT,
+];
+
+/// connection and retry settings for a call
+///
+/// set on the call block, or in the module or environment
+/// block for all of its calls- the call overrides the
+/// module, which overrides the environment
+///
+/// `timeout = "30s"`
+/// `retries = 3`
+/// `retry_on = [502, 503, "connect_error"]`
+/// `backoff = { initial = "200ms", max = "5s", jitter = true }`
+/// `proxy = "http://proxy.internal:3128"`
+/// `no_proxy = ["localhost", ".internal"]`
+/// `resolve = { "api.internal" = "127.0.0.1" }`
+/// `follow_redirects = true`
+/// `max_redirects = 5`
+/// `cookies = true`
+/// `auth = auth.api_user`
+/// `script_before = "scripts/nonce.rhai"`
+/// `script_after = "scripts/cursor.rhai"`
+/// `script_timeout = "500ms"`
+#[derive(Debug, Clone, Default)]
+pub struct CallSettings {
+    pub timeout: Option<Duration>,
+    pub retries: Option<u32>,
+    pub retry_on: Option<Vec<RetryOn>>,
+    pub backoff: Option<Backoff>,
+    /// an empty string turns off the proxy,
+    /// including from `HTTPS_PROXY`
+    pub proxy: Option<String>,
+    pub no_proxy: Option<Vec<String>>,
+    /// host to `ip` or `ip:port`
+    pub resolve: Option<IndexMap<String, String>>,
+    pub follow_redirects: Option<bool>,
+    pub max_redirects: Option<u32>,
+    /// send and store cookies from the
+    /// environment's cookie jar
+    pub cookies: Option<bool>,
+    /// `auth = null` turns off the
+    /// module's or environment's auth
+    pub auth: Option<CallAuth>,
+    /// rhai script paths, relative
+    /// to the dropfile directory
+    pub script_before: Option<String>,
+    pub script_after: Option<String>,
+    pub script_timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RetryOn {
+    Status(u16),
+    ConnectError,
+    Timeout,
+}
+
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub jitter: bool,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(200),
+            max: Duration::from_secs(5),
+            jitter: true,
+        }
+    }
+}
+
+impl DropCall {
+    pub fn process_setting(&mut self, attr: &hcl::Attribute) {
+        let value = Value::from(attr.expr().to_owned());
+
+        if let Err(err) = self.settings.set(attr.key(), &value) {
+            let drop_id = self.drop_id.drop_id().unwrap();
+            log::error!("{drop_id:#?}: invalid {}: {err}", attr.key());
+            exit::exit(1)
+        }
+    }
+}
+
+impl CallSettings {
+    pub fn set(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match key {
+            CALL_TIMEOUT => self.timeout = Some(CallSettings::parse_duration(value)?),
+            CALL_RETRIES => {
+                let retries = value
+                    .as_number()
+                    .and_then(|number| number.as_u64())
+                    .ok_or("retries must be a whole number")?;
+
+                self.retries = Some(retries as u32);
+            }
+            CALL_RETRY_ON => self.retry_on = Some(RetryOn::from_value(value)?),
+            CALL_BACKOFF => self.backoff = Some(Backoff::from_value(value)?),
+            CALL_PROXY => {
+                let Value::String(proxy) = value else {
+                    return Err("proxy must be a url, e.g. \"http://proxy.internal:3128\"".to_string());
+                };
+
+                if !proxy.is_empty() {
+                    proxy
+                        .parse::<Uri>()
+                        .map_err(|err| format!("invalid proxy url {proxy}: {err}"))?;
+                }
+
+                self.proxy = Some(proxy.to_string());
+            }
+            CALL_NO_PROXY => {
+                let hosts = match value {
+                    Value::Array(hosts) => hosts
+                        .iter()
+                        .map(|host| host.as_str().map(str::to_string))
+                        .collect::<Option<Vec<String>>>(),
+                    // "localhost,.internal", like NO_PROXY
+                    Value::String(hosts) => Some(
+                        hosts
+                            .split(',')
+                            .map(|host| host.trim().to_string())
+                            .filter(|host| !host.is_empty())
+                            .collect(),
+                    ),
+                    _ => None,
+                };
+
+                self.no_proxy = Some(hosts.ok_or("no_proxy must be an array of hosts")?);
+            }
+            CALL_RESOLVE => {
+                let Value::Object(overrides) = value else {
+                    return Err("resolve must be an object, e.g. { \"api.internal\" = \"127.0.0.1\" }".to_string());
+                };
+
+                let mut resolve = IndexMap::new();
+
+                for (host, address) in overrides {
+                    let Value::String(address) = address else {
+                        return Err(format!("resolve {host} must be an ip address"));
+                    };
+
+                    let is_valid = address.parse::<IpAddr>().is_ok()
+                        || address.parse::<std::net::SocketAddr>().is_ok();
+
+                    if !is_valid {
+                        return Err(format!("resolve {host} {address} must be an ip or ip:port"));
+                    }
+
+                    resolve.insert(host.to_lowercase(), address.to_string());
+                }
+
+                self.resolve = Some(resolve);
+            }
+            CALL_FOLLOW_REDIRECTS => {
+                self.follow_redirects =
+                    Some(value.as_bool().ok_or("follow_redirects must be true or false")?);
+            }
+            CALL_MAX_REDIRECTS => {
+                let max_redirects = value
+                    .as_number()
+                    .and_then(|number| number.as_u64())
+                    .ok_or("max_redirects must be a whole number")?;
+
+                self.max_redirects = Some(max_redirects as u32);
+            }
+            CALL_COOKIES => {
+                self.cookies = Some(value.as_bool().ok_or("cookies must be true or false")?);
+            }
+            CALL_AUTH => self.auth = Some(CallAuth::from_value(value)?),
+            CALL_SCRIPT_BEFORE | CALL_SCRIPT_AFTER => {
+                let Value::String(path) = value else {
+                    return Err(format!("{key} must be the path of a rhai script, e.g. \"scripts/nonce.rhai\""));
+                };
+
+                if key == CALL_SCRIPT_BEFORE {
+                    self.script_before = Some(path.to_string());
+                } else {
+                    self.script_after = Some(path.to_string());
+                }
+            }
+            CALL_SCRIPT_TIMEOUT => self.script_timeout = Some(CallSettings::parse_duration(value)?),
+            _ => return Err(format!("unknown call setting {key}")),
+        }
+
+        Ok(())
+    }
+
+    fn is_set(&self, key: &str) -> bool {
+        match key {
+            CALL_TIMEOUT => self.timeout.is_some(),
+            CALL_RETRIES => self.retries.is_some(),
+            CALL_RETRY_ON => self.retry_on.is_some(),
+            CALL_BACKOFF => self.backoff.is_some(),
+            CALL_PROXY => self.proxy.is_some(),
+            CALL_NO_PROXY => self.no_proxy.is_some(),
+            CALL_RESOLVE => self.resolve.is_some(),
+            CALL_FOLLOW_REDIRECTS => self.follow_redirects.is_some(),
+            CALL_MAX_REDIRECTS => self.max_redirects.is_some(),
+            CALL_COOKIES => self.cookies.is_some(),
+            CALL_AUTH => self.auth.is_some(),
+            CALL_SCRIPT_BEFORE => self.script_before.is_some(),
+            CALL_SCRIPT_AFTER => self.script_after.is_some(),
+            CALL_SCRIPT_TIMEOUT => self.script_timeout.is_some(),
+            _ => false,
+        }
+    }
+
+    /// fill settings the call doesn't set from
+    /// `mod.[setting]`, then `env.[setting]`
+    pub fn apply_scope_defaults(&mut self, env_var_scope: &Context<'_>, drop_id: &str) {
+        for prefix in [MOD_OBJECT_VAR_PREFIX, ENV_OBJECT_VAR_PREFIX] {
+            for key in CALL_SETTING_KEYS {
+                if self.is_set(key) {
+                    continue;
+                }
+
+                let Some(value) = CallSettings::get_scope_value(env_var_scope, prefix, key) else {
+                    continue;
+                };
+
+                if let Err(err) = self.set(key, &value) {
+                    log::error!("{drop_id:#?}: invalid {prefix}.{key}: {err}");
+                    exit::exit(1)
+                }
+            }
+        }
+    }
+
+    /// `env.[key]` or `mod.[key]`, if declared
+    pub fn get_scope_value(env_var_scope: &Context<'_>, prefix: &str, key: &str) -> Option<Value> {
+        let traversal = Traversal::builder(Variable::unchecked(prefix))
+            .attr(key)
+            .build();
+
+        Expression::from(traversal).evaluate(env_var_scope).ok()
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout.unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    pub fn get_script_timeout(&self) -> Duration {
+        self.script_timeout.unwrap_or(DEFAULT_SCRIPT_TIMEOUT)
+    }
+
+    pub fn get_retries(&self) -> u32 {
+        self.retries.unwrap_or(0)
+    }
+
+    pub fn is_cookie_jar_enabled(&self) -> bool {
+        self.cookies.unwrap_or(false)
+    }
+
+    pub fn get_backoff(&self) -> Backoff {
+        self.backoff.clone().unwrap_or_default()
+    }
+
+    /// `[429, 502, 503, 504, "connect_error", "timeout"]` when unset
+    pub fn get_retry_on(&self) -> Vec<RetryOn> {
+        match &self.retry_on {
+            Some(retry_on) => retry_on.clone(),
+            None => vec![
+                RetryOn::Status(429),
+                RetryOn::Status(502),
+                RetryOn::Status(503),
+                RetryOn::Status(504),
+                RetryOn::ConnectError,
+                RetryOn::Timeout,
+            ],
+        }
+    }
+
+    pub fn should_retry_status(&self, status: StatusCode) -> bool {
+        self.get_retry_on()
+            .contains(&RetryOn::Status(status.as_u16()))
+    }
+
+    pub fn should_retry_error(&self, err: &isahc::Error) -> bool {
+        let retry_on = self.get_retry_on();
+
+        (err.is_timeout() && retry_on.contains(&RetryOn::Timeout))
+            || (err.is_network() && retry_on.contains(&RetryOn::ConnectError))
+    }
+
+    /// delay before the next attempt- the response's
+    /// `Retry-After`, in seconds or as a date, or the backoff
+    ///
+    /// `Retry-After` is capped at the backoff's `max`,
+    /// so a server can't hold the call for hours
+    pub fn get_retry_delay(&self, attempt: u32, response_headers: Option<&HeaderMap>) -> Duration {
+        let backoff = self.get_backoff();
+
+        let retry_after = response_headers
+            .and_then(|headers| headers.get("retry-after"))
+            .and_then(|value| value.to_str().ok())
+            .and_then(CallSettings::parse_retry_after);
+
+        match retry_after {
+            Some(retry_after) if retry_after > backoff.max => {
+                VerboseDump::note(&format!(
+                    "retry-after of {}s is longer than the backoff max, waiting {}ms",
+                    retry_after.as_secs(),
+                    backoff.max.as_millis()
+                ));
+
+                backoff.max
+            }
+            Some(retry_after) => retry_after,
+            None => backoff.delay(attempt),
+        }
+    }
+
+    fn parse_retry_after(value: &str) -> Option<Duration> {
+        let value = value.trim();
+
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+
+        let date = httpdate::parse_http_date(value).ok()?;
+
+        Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+    }
+
+    /// `"200ms"`, `"5s"`, `"2m"`, or a number of seconds
+    pub fn parse_duration(value: &Value) -> Result<Duration, String> {
+        let invalid = || format!("invalid duration {value}, e.g. \"200ms\", \"5s\" or \"1m\"");
+
+        match value {
+            // negative, NaN and overflowing values are rejected
+            Value::Number(number) => number
+                .as_f64()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .ok_or_else(invalid),
+            Value::String(duration) => {
+                let duration = duration.trim();
+                let unit_start = duration
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .unwrap_or(duration.len());
+
+                let (amount, unit) = duration.split_at(unit_start);
+                let amount: f64 = amount.parse().map_err(|_| invalid())?;
+
+                let seconds = match unit.trim() {
+                    "ms" => amount / 1000.0,
+                    "" | "s" => amount,
+                    "m" => amount * 60.0,
+                    "h" => amount * 3600.0,
+                    _ => return Err(invalid()),
+                };
+
+                Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl RetryOn {
+    /// `[502, 503, "connect_error", "timeout"]`
+    pub fn from_value(value: &Value) -> Result<Vec<RetryOn>, String> {
+        let Value::Array(conditions) = value else {
+            return Err("retry_on must be an array of status codes, \"connect_error\" or \"timeout\"".to_string());
+        };
+
+        conditions
+            .iter()
+            .map(|condition| match condition {
+                Value::Number(number) => number
+                    .as_u64()
+                    .and_then(|status| u16::try_from(status).ok())
+                    .map(RetryOn::Status)
+                    .ok_or_else(|| format!("invalid status code {number}")),
+                Value::String(condition) => match condition.as_str() {
+                    "connect_error" => Ok(RetryOn::ConnectError),
+                    "timeout" => Ok(RetryOn::Timeout),
+                    status => status
+                        .parse::<u16>()
+                        .map(RetryOn::Status)
+                        .map_err(|_| format!("invalid retry_on condition {status}")),
+                },
+                other => Err(format!("invalid retry_on condition {other}")),
+            })
+            .collect()
+    }
+}
+
+impl Backoff {
+    /// `{ initial = "200ms", max = "5s", jitter = true }`
+    pub fn from_value(value: &Value) -> Result<Backoff, String> {
+        let Value::Object(object) = value else {
+            return Err("backoff must be an object, e.g. { initial = \"200ms\", max = \"5s\" }".to_string());
+        };
+
+        let mut backoff = Backoff::default();
+
+        for (key, value) in object {
+            match key.as_str() {
+                "initial" => backoff.initial = CallSettings::parse_duration(value)?,
+                "max" => backoff.max = CallSettings::parse_duration(value)?,
+                "jitter" => {
+                    backoff.jitter = value.as_bool().ok_or("backoff jitter must be true or false")?;
+                }
+                other => return Err(format!("unknown backoff setting {other}")),
+            }
+        }
+
+        Ok(backoff)
+    }
+
+    /// doubles from `initial` for each attempt up to `max`- jitter
+    /// picks a random delay between half and the full delay
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.initial.saturating_mul(factor).min(self.max);
+
+        if self.jitter && !delay.is_zero() {
+            let millis = delay.as_millis() as u64;
+            Duration::from_millis(rand::thread_rng().gen_range(millis / 2..=millis))
+        } else {
+            delay
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use hcl::Value;
+    use isahc::http::{HeaderMap, HeaderValue};
+
+    use super::{Backoff, CallSettings};
+
+    fn retry_after(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn settings(max: Duration) -> CallSettings {
+        CallSettings {
+            backoff: Some(Backoff {
+                initial: Duration::from_millis(100),
+                max,
+                jitter: false,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn retry_after_seconds_are_used_up_to_the_backoff_max() {
+        let settings = settings(Duration::from_secs(10));
+
+        assert_eq!(settings.get_retry_delay(1, Some(&retry_after("3"))), Duration::from_secs(3));
+        assert_eq!(settings.get_retry_delay(1, Some(&retry_after("86400"))), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn retry_after_date_is_capped_at_the_backoff_max() {
+        let settings = settings(Duration::from_secs(2));
+
+        let next_week = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(7 * 24 * 3600));
+
+        assert_eq!(settings.get_retry_delay(1, Some(&retry_after(&next_week))), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn backoff_is_used_without_retry_after() {
+        let settings = settings(Duration::from_secs(2));
+
+        assert_eq!(settings.get_retry_delay(1, None), Duration::from_millis(100));
+        assert_eq!(settings.get_retry_delay(1, Some(&retry_after("soon"))), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn durations_are_parsed_with_units() {
+        assert_eq!(CallSettings::parse_duration(&Value::from("200ms")), Ok(Duration::from_millis(200)));
+        assert_eq!(CallSettings::parse_duration(&Value::from("2m")), Ok(Duration::from_secs(120)));
+        assert_eq!(CallSettings::parse_duration(&Value::from(1.5)), Ok(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn overflowing_durations_are_invalid() {
+        assert!(CallSettings::parse_duration(&Value::from("1e300h")).is_err());
+        assert!(CallSettings::parse_duration(&Value::from("99999999999999999999999h")).is_err());
+        assert!(CallSettings::parse_duration(&Value::from(-1.0)).is_err());
+    }
+}
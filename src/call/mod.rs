@@ -14,11 +14,14 @@ use crate::parser::drop_id::DropId;
 use crate::parser::hcl_block::HclBlock;
 use crate::runner::drop_run::CallBlockOverWrites;
 
+use call_settings::CallSettings;
+
 pub mod call_after;
 pub mod call_assert;
 pub mod call_auth;
 pub mod call_inputs;
 pub mod call_process;
+pub mod call_settings;
 
 /// main structure to manage api call
 ///
@@ -36,11 +39,12 @@ pub struct DropCall {
     pub outputs: Option<Vec<Traversal>>,
     pub after_action_config: AfterActionConfig,
     pub asserts: Vec<Assert>,
+    pub settings: CallSettings,
 }
 
 impl DropCall {
     pub fn full_url(&self) -> String {
-        self.base_url.to_owned() + &self.path
+        self.base_url.to_owned() + self.path.as_str()
     }
 
     pub fn set_body(&mut self, body: &serde_json::Value) {
@@ -63,6 +67,7 @@ impl DropCall {
             inputs: None,
             after_action_config: HashMap::new(),
             asserts: Vec::<Assert>::new(),
+            settings: CallSettings::default(),
         }
     }
 
@@ -83,6 +88,12 @@ impl DropCall {
                 CALL_BODY => call.process_body(block, block_type),
                 CALL_ASSERT => call.process_assert_block(attr),
                 CALL_INPUTS => call.process_input_block(attr),
+                CALL_TIMEOUT | CALL_RETRIES | CALL_RETRY_ON | CALL_BACKOFF | CALL_PROXY
+                | CALL_NO_PROXY | CALL_RESOLVE | CALL_FOLLOW_REDIRECTS | CALL_MAX_REDIRECTS
+                | CALL_COOKIES | CALL_AUTH | CALL_SCRIPT_BEFORE | CALL_SCRIPT_AFTER
+                | CALL_SCRIPT_TIMEOUT => {
+                    call.process_setting(attr)
+                }
                 _ => {
                     log::warn!("invalid attribute found on call block {:?}", attr.key())
                 }
@@ -0,0 +1,223 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use hcl::{Map, Value};
+use isahc::{
+    auth::{Authentication, Credentials},
+    config::Configurable,
+    http::{request::Builder, HeaderMap},
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::{parser::block_type::auth::AUTH_TYPES, util::redact::Redactor};
+
+use super::{
+    oauth2::OAuth2Settings,
+    signing::{AwsSigV4, HmacSigner},
+    verbose::VerboseDump,
+};
+
+/// everything but the unreserved characters
+pub(super) const QUERY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// auth from an `auth` block, added to the request by the caller
+///
+/// `type = "bearer"`, `token`
+/// `type = "basic"`, `username` and `password`
+/// `type = "api_key"`, `name`, `value` and `in = "header"` or `"query"`
+/// `type = "digest"`, `username` and `password`
+/// `type = "oauth2"`- see `OAuth2Settings`
+/// `type = "aws_sigv4"`- see `AwsSigV4`
+/// `type = "hmac"`- see `HmacSigner`
+///
+/// signing auth is added last, to the final request
+#[derive(Debug, Clone)]
+pub enum CallAuth {
+    /// `auth = null`
+    None,
+    Bearer { token: String },
+    Basic { username: String, password: String },
+    ApiKey { name: String, value: String, in_query: bool },
+    Digest { username: String, password: String },
+    OAuth2(OAuth2Settings),
+    AwsSigV4(AwsSigV4),
+    Hmac(HmacSigner),
+}
+
+impl CallAuth {
+    pub fn from_value(value: &Value) -> Result<CallAuth, String> {
+        let object = match value {
+            Value::Null => return Ok(CallAuth::None),
+            Value::Object(object) => object,
+            _ => return Err("auth must be an auth block, e.g. auth = auth.api_user".to_string()),
+        };
+
+        let get = |key: &str| CallAuth::get_string(object, key);
+
+        let auth = match get("type")?.as_str() {
+            "bearer" => CallAuth::Bearer { token: get("token")? },
+            "basic" => CallAuth::Basic {
+                username: get("username")?,
+                password: get("password")?,
+            },
+            "api_key" => {
+                let in_query = match object.get("in").and_then(Value::as_str) {
+                    None | Some("header") => false,
+                    Some("query") => true,
+                    Some(other) => return Err(format!("invalid api_key in {other}, expected \"header\" or \"query\"")),
+                };
+
+                CallAuth::ApiKey {
+                    name: get("name")?,
+                    value: get("value")?,
+                    in_query,
+                }
+            }
+            "digest" => CallAuth::Digest {
+                username: get("username")?,
+                password: get("password")?,
+            },
+            "oauth2" => CallAuth::OAuth2(OAuth2Settings::from_object(object)?),
+            "aws_sigv4" => CallAuth::AwsSigV4(AwsSigV4::from_object(object)?),
+            "hmac" => CallAuth::Hmac(HmacSigner::from_object(object)?),
+            other => return Err(format!("invalid auth type {other}, expected one of {}", AUTH_TYPES.join(", "))),
+        };
+
+        auth.register_sensitive_values();
+
+        Ok(auth)
+    }
+
+    /// values from outside `secrets.*`, and the
+    /// encoded basic credentials, are masked too
+    fn register_sensitive_values(&self) {
+        match self {
+            CallAuth::None => {}
+            CallAuth::Bearer { token } => Redactor::register(token),
+            CallAuth::Basic { username, password } => {
+                Redactor::register(password);
+                Redactor::register(&CallAuth::encode_basic(username, password));
+            }
+            CallAuth::ApiKey { value, .. } => {
+                Redactor::register(value);
+                Redactor::register(&utf8_percent_encode(value, QUERY_ENCODE_SET).to_string());
+            }
+            CallAuth::Digest { password, .. } => Redactor::register(password),
+            CallAuth::OAuth2(oauth2) => oauth2.register_sensitive_values(),
+            CallAuth::AwsSigV4(aws_sigv4) => aws_sigv4.register_sensitive_values(),
+            CallAuth::Hmac(hmac) => hmac.register_sensitive_values(),
+        }
+    }
+
+    /// adds an `in = "query"` api key to the url
+    pub fn apply_to_url(&self, url: String) -> String {
+        let CallAuth::ApiKey { name, value, in_query: true } = self else {
+            return url;
+        };
+
+        let separator = if url.contains('?') { '&' } else { '?' };
+
+        format!(
+            "{url}{separator}{}={}",
+            utf8_percent_encode(name, QUERY_ENCODE_SET),
+            utf8_percent_encode(value, QUERY_ENCODE_SET)
+        )
+    }
+
+    /// adds the auth header- a header set
+    /// on the call is sent instead
+    pub fn apply_to_request(&self, mut request: Builder, call_headers: &HeaderMap) -> Builder {
+        let header = match self {
+            CallAuth::None => return request,
+            CallAuth::Bearer { token } => Some(("authorization".to_string(), format!("Bearer {token}"))),
+            CallAuth::Basic { username, password } => Some((
+                "authorization".to_string(),
+                format!("Basic {}", CallAuth::encode_basic(username, password)),
+            )),
+            CallAuth::ApiKey { name, value, in_query: false } => Some((name.to_lowercase(), value.to_string())),
+            CallAuth::ApiKey { in_query: true, .. } => None,
+            CallAuth::Digest { username, password } => {
+                // the client answers the server's challenge
+                request = request
+                    .authentication(Authentication::digest())
+                    .credentials(Credentials::new(username.as_str(), password.as_str()));
+
+                None
+            }
+            // the token is fetched by the caller before the request
+            CallAuth::OAuth2(oauth2) => oauth2
+                .token
+                .as_ref()
+                .map(|token| ("authorization".to_string(), token.get_authorization())),
+            // signed once the request is complete
+            CallAuth::AwsSigV4(_) | CallAuth::Hmac(_) => None,
+        };
+
+        VerboseDump::note(&format!("auth {}", self.get_type()));
+
+        match header {
+            Some((name, _)) if call_headers.contains_key(name.as_str()) => {
+                VerboseDump::note(&format!("auth {name} header set by the call"));
+                request
+            }
+            Some((name, value)) => request.header(name, value),
+            None => request,
+        }
+    }
+
+    pub fn get_type(&self) -> &'static str {
+        match self {
+            CallAuth::None => "none",
+            CallAuth::Bearer { .. } => "bearer",
+            CallAuth::Basic { .. } => "basic",
+            CallAuth::ApiKey { .. } => "api_key",
+            CallAuth::Digest { .. } => "digest",
+            CallAuth::OAuth2(_) => "oauth2",
+            CallAuth::AwsSigV4(_) => "aws_sigv4",
+            CallAuth::Hmac(_) => "hmac",
+        }
+    }
+
+    /// signs the final request, with its serialized body-
+    /// a signature header set on the call is sent instead
+    pub fn sign_request(&self, request: Builder, body: Option<&[u8]>) -> Result<Builder, String> {
+        let signature_header = match self {
+            CallAuth::AwsSigV4(_) => "authorization",
+            CallAuth::Hmac(hmac) => hmac.header.as_str(),
+            _ => return Ok(request),
+        };
+
+        if request.headers_ref().is_some_and(|headers| headers.contains_key(signature_header)) {
+            VerboseDump::note(&format!("auth {signature_header} header set by the call"));
+            return Ok(request);
+        }
+
+        match self {
+            CallAuth::AwsSigV4(aws_sigv4) => aws_sigv4.sign(request, body),
+            CallAuth::Hmac(hmac) => hmac.sign(request, body),
+            _ => Ok(request),
+        }
+    }
+
+    /// a string, or number, from the evaluated auth block
+    pub fn get_string(object: &Map<String, Value>, key: &str) -> Result<String, String> {
+        match CallAuth::get_optional_string(object, key)? {
+            Some(value) => Ok(value),
+            None => Err(format!(
+                "auth of type {} must set {key}",
+                object.get("type").unwrap_or(&Value::Null)
+            )),
+        }
+    }
+
+    pub fn get_optional_string(object: &Map<String, Value>, key: &str) -> Result<Option<String>, String> {
+        match object.get(key) {
+            Some(Value::String(value)) => Ok(Some(value.to_string())),
+            Some(Value::Number(value)) => Ok(Some(value.to_string())),
+            Some(Value::Null) | None => Ok(None),
+            Some(other) => Err(format!("auth {key} must be a string, found {other}")),
+        }
+    }
+
+    fn encode_basic(username: &str, password: &str) -> String {
+        BASE64_STANDARD.encode(format!("{username}:{password}"))
+    }
+}
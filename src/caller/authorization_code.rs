@@ -0,0 +1,398 @@
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::{TcpListener, TcpStream},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::{cmd::ctx::CmdContext, prompt_println, util::redact::Redactor};
+
+use super::{auth::QUERY_ENCODE_SET, verbose::VerboseDump};
+
+/// how long the listener waits for the
+/// authorization server's redirect
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+const REDIRECT_PATH: &str = "/callback";
+
+/// the authorization code grant with pkce, for user tokens-
+/// the authorize url is printed, and opened in the browser,
+/// and a short-lived listener on 127.0.0.1 takes the redirect
+/// with the code
+///
+/// `$BROWSER`, when set, is run with the url instead of the
+/// system's browser
+pub struct AuthorizationCode {
+    pub code: String,
+    pub code_verifier: String,
+    pub redirect_uri: String,
+}
+
+impl AuthorizationCode {
+    /// `redirect_port = 0` listens on any free port
+    ///
+    /// the login needs a user, so it fails when drop is
+    /// non-interactive (`--yes`, CI, embedded) rather
+    /// than waiting for a redirect that won't come
+    pub fn authorize(
+        authorize_url: &str,
+        client_id: &str,
+        scope: Option<&str>,
+        redirect_port: u16,
+        open_browser: bool,
+    ) -> Result<AuthorizationCode, anyhow::Error> {
+        if !CmdContext::is_interactive() {
+            return Err(anyhow!(
+                "oauth2 authorization_code needs a login in the browser, and drop is not interactive- run the call once from a terminal without --yes to store a refresh token"
+            ));
+        }
+
+        AuthorizationCode::authorize_in_browser(authorize_url, client_id, scope, redirect_port, open_browser)
+    }
+
+    fn authorize_in_browser(
+        authorize_url: &str,
+        client_id: &str,
+        scope: Option<&str>,
+        redirect_port: u16,
+        open_browser: bool,
+    ) -> Result<AuthorizationCode, anyhow::Error> {
+        let listener = TcpListener::bind(("127.0.0.1", redirect_port))
+            .map_err(|err| anyhow!("error listening for the oauth2 redirect on 127.0.0.1:{redirect_port}: {err}"))?;
+
+        let redirect_uri = format!("http://127.0.0.1:{}{REDIRECT_PATH}", listener.local_addr()?.port());
+
+        let code_verifier = AuthorizationCode::random_string(64);
+        let code_challenge = BASE64_URL_SAFE_NO_PAD.encode(openssl::sha::sha256(code_verifier.as_bytes()));
+        let state = AuthorizationCode::random_string(32);
+
+        let mut query: Vec<(&str, &str)> = vec![
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", &redirect_uri),
+            ("code_challenge", &code_challenge),
+            ("code_challenge_method", "S256"),
+            ("state", &state),
+        ];
+
+        if let Some(scope) = scope {
+            query.push(("scope", scope));
+        }
+
+        let query = query
+            .iter()
+            .map(|(key, value)| format!("{key}={}", utf8_percent_encode(value, QUERY_ENCODE_SET)))
+            .collect::<Vec<String>>()
+            .join("&");
+
+        let separator = if authorize_url.contains('?') { '&' } else { '?' };
+        let url = format!("{authorize_url}{separator}{query}");
+
+        prompt_println!("\nauthorize drop in your browser: {url}\n");
+
+        if open_browser {
+            AuthorizationCode::open_browser(&url);
+        }
+
+        let code = AuthorizationCode::wait_for_redirect(&listener, &state)?;
+
+        Redactor::register(&code);
+
+        Ok(AuthorizationCode {
+            code,
+            code_verifier,
+            redirect_uri,
+        })
+    }
+
+    fn open_browser(url: &str) {
+        let mut command = match std::env::var("BROWSER") {
+            Ok(browser) if !browser.trim().is_empty() => {
+                let mut browser = browser.split_whitespace();
+                let mut command = Command::new(browser.next().unwrap_or_default());
+                command.args(browser);
+                command
+            }
+            _ if cfg!(target_os = "macos") => Command::new("open"),
+            _ if cfg!(target_os = "windows") => {
+                let mut command = Command::new("rundll32");
+                command.arg("url.dll,FileProtocolHandler");
+                command
+            }
+            _ => Command::new("xdg-open"),
+        };
+
+        let spawn_res = command
+            .arg(url)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        if let Err(err) = spawn_res {
+            VerboseDump::note(&format!("oauth2 couldn't open a browser ({err}), open the url above"));
+        }
+    }
+
+    /// requests to other paths, e.g. the
+    /// browser's favicon, are answered with 404
+    fn wait_for_redirect(listener: &TcpListener, state: &str) -> Result<String, anyhow::Error> {
+        listener.set_nonblocking(true)?;
+
+        let started = Instant::now();
+
+        loop {
+            let mut stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    if started.elapsed() > LOGIN_TIMEOUT {
+                        return Err(anyhow!(
+                            "oauth2 authorization timed out after {}s waiting for the redirect",
+                            LOGIN_TIMEOUT.as_secs()
+                        ));
+                    }
+
+                    std::thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+                Err(err) => return Err(anyhow!("error accepting the oauth2 redirect: {err}")),
+            };
+
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+            let target = AuthorizationCode::read_request_target(&mut stream);
+
+            let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+
+            if path != REDIRECT_PATH {
+                AuthorizationCode::respond(&mut stream, "404 Not Found", "not found");
+                continue;
+            }
+
+            let params: Vec<(String, String)> = query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.to_string(), AuthorizationCode::decode(value)))
+                .collect();
+
+            let get = |key: &str| {
+                params
+                    .iter()
+                    .find(|(param, _)| param == key)
+                    .map(|(_, value)| value.to_string())
+            };
+
+            if get("state").as_deref() != Some(state) {
+                AuthorizationCode::respond(&mut stream, "400 Bad Request", "drop: authorization failed, state does not match");
+                return Err(anyhow!("oauth2 redirect state does not match the authorize request"));
+            }
+
+            if let Some(error) = get("error") {
+                let description = get("error_description").map(|description| format!(", {description}")).unwrap_or_default();
+
+                AuthorizationCode::respond(&mut stream, "200 OK", "drop: authorization failed, you can close this window");
+                return Err(anyhow!("oauth2 authorization failed: {error}{description}"));
+            }
+
+            let Some(code) = get("code") else {
+                AuthorizationCode::respond(&mut stream, "400 Bad Request", "drop: authorization failed, no code");
+                return Err(anyhow!("oauth2 redirect has no code"));
+            };
+
+            AuthorizationCode::respond(&mut stream, "200 OK", "drop: authorization complete, you can close this window");
+
+            return Ok(code);
+        }
+    }
+
+    /// `/callback?code=...` from `GET /callback?code=... HTTP/1.1`
+    fn read_request_target(stream: &mut TcpStream) -> String {
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap_or_default();
+
+        // skip the headers
+        let mut header_line = String::new();
+        while reader.read_line(&mut header_line).unwrap_or_default() > 2 {
+            header_line.clear();
+        }
+
+        request_line.split_whitespace().nth(1).unwrap_or("/").to_string()
+    }
+
+    fn respond(stream: &mut TcpStream, status: &str, message: &str) {
+        let body = format!("<html><body><p>{message}</p></body></html>");
+
+        let response = format!(
+            "HTTP/1.1 {status}\r\ncontent-type: text/html\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+
+        if let Err(err) = stream.write_all(response.as_bytes()) {
+            log::debug!("error responding to the oauth2 redirect: {err}");
+        }
+    }
+
+    fn decode(value: &str) -> String {
+        percent_decode_str(&value.replace('+', " "))
+            .decode_utf8_lossy()
+            .to_string()
+    }
+
+    /// from the pkce verifier's unreserved characters
+    fn random_string(len: usize) -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(len)
+            .map(char::from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, path::PathBuf, sync::Mutex};
+
+    use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+
+    use crate::cmd::{
+        cli::{Cli, Command, LogLevelInput, OutputFormat},
+        ctx::CmdContext,
+    };
+
+    use super::AuthorizationCode;
+
+    /// `$BROWSER` is process-wide
+    static BROWSER_LOCK: Mutex<()> = Mutex::new(());
+
+    fn free_port() -> u16 {
+        TcpListener::bind(("127.0.0.1", 0)).unwrap().local_addr().unwrap().port()
+    }
+
+    /// a `$BROWSER` that saves the authorize url, asks for
+    /// a favicon like a browser would, then follows the
+    /// redirect with `callback_query`- `$state` is the
+    /// authorize request's state
+    fn scripted_browser(name: &str, port: u16, callback_query: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("drop-browser-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let url_file = dir.join("url");
+        let script = dir.join("browser.sh");
+
+        std::fs::write(
+            &script,
+            format!(
+                r#"
+echo "$1" > {url_file}
+state=$(echo "$1" | sed 's/.*[?&]state=\([^&]*\).*/\1/')
+curl -s -o /dev/null "http://127.0.0.1:{port}/favicon.ico"
+curl -s -o /dev/null "http://127.0.0.1:{port}/callback?{callback_query}"
+"#,
+                url_file = url_file.display()
+            ),
+        )
+        .unwrap();
+
+        (script, url_file)
+    }
+
+    fn authorize_with_browser(name: &str, callback_query: &str) -> (Result<AuthorizationCode, anyhow::Error>, String) {
+        let _lock = BROWSER_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+        let port = free_port();
+        let (script, url_file) = scripted_browser(name, port, callback_query);
+
+        std::env::set_var("BROWSER", format!("sh {}", script.display()));
+
+        let res = AuthorizationCode::authorize_in_browser(
+            "https://auth.example.com/authorize?audience=api",
+            "drop client",
+            Some("read write"),
+            port,
+            true,
+        );
+
+        let url = std::fs::read_to_string(url_file).unwrap_or_default();
+
+        (res, url.trim().to_string())
+    }
+
+    fn query_param(url: &str, key: &str) -> Option<String> {
+        let (_, query) = url.split_once('?')?;
+
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(param, _)| *param == key)
+            .map(|(_, value)| AuthorizationCode::decode(value))
+    }
+
+    #[test]
+    fn authorize_takes_the_code_from_the_redirect() {
+        let (res, url) = authorize_with_browser("code", "code=scripted%20code&state=$state");
+
+        let authorization = res.unwrap();
+
+        assert_eq!(authorization.code, "scripted code");
+
+        assert!(url.starts_with("https://auth.example.com/authorize?audience=api&response_type=code&"));
+        assert_eq!(query_param(&url, "client_id").as_deref(), Some("drop client"));
+        assert_eq!(query_param(&url, "scope").as_deref(), Some("read write"));
+        assert_eq!(query_param(&url, "redirect_uri"), Some(authorization.redirect_uri.clone()));
+        assert!(authorization.redirect_uri.starts_with("http://127.0.0.1:"));
+        assert!(authorization.redirect_uri.ends_with("/callback"));
+
+        let code_challenge = BASE64_URL_SAFE_NO_PAD.encode(openssl::sha::sha256(authorization.code_verifier.as_bytes()));
+
+        assert_eq!(query_param(&url, "code_challenge"), Some(code_challenge));
+        assert_eq!(query_param(&url, "code_challenge_method").as_deref(), Some("S256"));
+    }
+
+    #[test]
+    fn authorize_rejects_a_redirect_with_another_state() {
+        let (res, _) = authorize_with_browser("state", "code=scripted&state=other");
+
+        let err = res.err().unwrap().to_string();
+
+        assert!(err.contains("state does not match"), "{err}");
+    }
+
+    #[test]
+    fn authorize_reports_the_authorization_error() {
+        let (res, _) = authorize_with_browser("error", "error=access_denied&error_description=user%20denied&state=$state");
+
+        let err = res.err().unwrap().to_string();
+
+        assert_eq!(err, "oauth2 authorization failed: access_denied, user denied");
+    }
+
+    #[test]
+    fn authorize_fails_when_not_interactive() {
+        CmdContext::set(Cli {
+            command: Command::hit {
+                drop_id: String::new(),
+                verbose: false,
+                transcript: None,
+            },
+            level: LogLevelInput::Info,
+            env: "base".to_string(),
+            dir: ".".to_string(),
+            output: OutputFormat::Silent,
+            non_interactive: true,
+            unsafe_show_secrets: false,
+        });
+
+        let res = AuthorizationCode::authorize("https://auth.example.com/authorize", "drop", None, 0, false);
+
+        let err = res.err().unwrap().to_string();
+
+        assert!(err.contains("not interactive"), "{err}");
+    }
+}
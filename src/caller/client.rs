@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use hcl::Value;
+use isahc::{
+    config::{Configurable, VersionNegotiation},
+    HttpClient,
+};
+
+use crate::{
+    call::call_settings::CallSettings, constants::*, interpreter::scope::GlobalScopeProvider,
+};
+
+use super::tls::TlsSettings;
+
+/// keep-alive probes on pooled connections
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// builds the http client shared by every call in a run,
+/// so connections are pooled and kept alive between calls
+///
+/// configured from the selected environment block
+///
+/// `http_version = "auto"` http/2 when the server offers it over tls, the default
+/// `http_version = "2"` http/2 only, including plain http
+/// `http_version = "1.1"` http/1.1 only
+/// `max_connections = 20`
+/// `max_connections_per_host = 6`
+/// `tls { ca_file = "ca.pem" }`- see `TlsSettings`
+pub struct DropClient {}
+
+impl DropClient {
+    pub fn build() -> Result<HttpClient, anyhow::Error> {
+        let env_var_scope = GlobalScopeProvider::get_mut()?;
+
+        let get_env_setting = |key: &str| {
+            CallSettings::get_scope_value(&env_var_scope, ENV_OBJECT_VAR_PREFIX, key)
+        };
+
+        let mut client_builder = HttpClient::builder()
+            .tcp_keepalive(TCP_KEEPALIVE)
+            .metrics(true);
+
+        if let Some(http_version) = get_env_setting(CLIENT_HTTP_VERSION) {
+            client_builder =
+                client_builder.version_negotiation(DropClient::parse_http_version(&http_version)?);
+        }
+
+        if let Some(max_connections) = get_env_setting(CLIENT_MAX_CONNECTIONS) {
+            client_builder = client_builder
+                .max_connections(DropClient::parse_count(CLIENT_MAX_CONNECTIONS, &max_connections)?);
+        }
+
+        if let Some(max_connections_per_host) = get_env_setting(CLIENT_MAX_CONNECTIONS_PER_HOST) {
+            client_builder = client_builder.max_connections_per_host(DropClient::parse_count(
+                CLIENT_MAX_CONNECTIONS_PER_HOST,
+                &max_connections_per_host,
+            )?);
+        }
+
+        if let Some(tls) = get_env_setting(CLIENT_TLS) {
+            client_builder = TlsSettings::from_value(&tls)?.apply(client_builder)?;
+        }
+
+        Ok(client_builder.build()?)
+    }
+
+    fn parse_http_version(value: &Value) -> Result<VersionNegotiation, anyhow::Error> {
+        let http_version = match value {
+            Value::String(http_version) => http_version.to_string(),
+            Value::Number(http_version) => http_version.to_string(),
+            _ => String::new(),
+        };
+
+        match http_version.as_str() {
+            "auto" => Ok(VersionNegotiation::latest_compatible()),
+            "2" | "2.0" => Ok(VersionNegotiation::http2()),
+            "1.1" => Ok(VersionNegotiation::http11()),
+            _ => Err(anyhow!(
+                "invalid env.{CLIENT_HTTP_VERSION} {value}, expected \"auto\", \"2\" or \"1.1\""
+            )),
+        }
+    }
+
+    fn parse_count(key: &str, value: &Value) -> Result<usize, anyhow::Error> {
+        value
+            .as_number()
+            .and_then(|number| number.as_u64())
+            .map(|count| count as usize)
+            .ok_or_else(|| anyhow!("invalid env.{key} {value}, expected a whole number"))
+    }
+}
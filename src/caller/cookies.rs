@@ -0,0 +1,392 @@
+use cookie::Cookie;
+use hcl::Value;
+use indexmap::IndexMap;
+use isahc::http::{HeaderMap, Uri};
+
+use crate::util::exit;
+use crate::{
+    cmd::ctx::CmdContext,
+    persist::{PersisterProvider, StoredCookie},
+    util::{redact::Redactor, unix_now},
+};
+
+use super::verbose::VerboseDump;
+
+/// the selected environment's cookie jar, for calls
+/// with `cookies = true` on the call, module or environment
+///
+/// cookies from `Set-Cookie` response headers are stored per
+/// environment and domain, and sent with later calls that match
+/// their domain, path and `Secure` attribute until they expire
+pub struct CookieJar {}
+
+impl CookieJar {
+    /// the request's `Cookie` header- cookies set on the call
+    /// are kept, and jar cookies with the same name are not sent
+    pub fn get_cookie_header(uri: &Uri, call_cookie: Option<&str>) -> Option<String> {
+        let host = uri.host()?.to_lowercase();
+        let path = if uri.path().is_empty() { "/" } else { uri.path() };
+        let is_secure = uri.scheme_str() == Some("https");
+
+        let call_cookie_names: Vec<&str> = call_cookie
+            .map(|call_cookie| {
+                call_cookie
+                    .split(';')
+                    .filter_map(|pair| pair.split('=').next())
+                    .map(str::trim)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut matching: Vec<StoredCookie> = CookieJar::get_cookies(&CmdContext::get_env())
+            .into_iter()
+            .filter(|cookie| {
+                CookieJar::domain_matches(cookie, &host)
+                    && CookieJar::path_matches(&cookie.path, path)
+                    && (is_secure || !cookie.secure)
+                    && !call_cookie_names.contains(&cookie.name.as_str())
+            })
+            .collect();
+
+        if matching.is_empty() {
+            return call_cookie.map(str::to_string);
+        }
+
+        // cookies with longer paths are sent first
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+
+        let names: Vec<&str> = matching.iter().map(|cookie| cookie.name.as_str()).collect();
+        VerboseDump::note(&format!("sending cookies {} from the jar", names.join(", ")));
+
+        let jar_cookies = matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<String>>()
+            .join("; ");
+
+        match call_cookie {
+            Some(call_cookie) => Some(format!("{call_cookie}; {jar_cookies}")),
+            None => Some(jar_cookies),
+        }
+    }
+
+    /// cookies from the response's `Set-Cookie` headers- values
+    /// are registered as sensitive before the response is printed
+    pub fn from_response(uri: &Uri, headers: &HeaderMap) -> Vec<StoredCookie> {
+        let Some(host) = uri.host().map(str::to_lowercase) else {
+            return Vec::new();
+        };
+
+        headers
+            .get_all("set-cookie")
+            .iter()
+            .filter_map(|set_cookie| set_cookie.to_str().ok())
+            .filter_map(|set_cookie| match CookieJar::parse_set_cookie(set_cookie, &host, uri.path()) {
+                Ok(cookie) => {
+                    Redactor::register(&cookie.value);
+                    Some(cookie)
+                }
+                Err(err) => {
+                    log::warn!("ignoring cookie from {host}: {err}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// store the cookies in the selected environment's jar-
+    /// cookies set with a past expiry are removed
+    pub fn store(cookies: &[StoredCookie]) {
+        if cookies.is_empty() {
+            return;
+        }
+
+        let env = &CmdContext::get_env();
+
+        let Some(mut persister) = PersisterProvider::get_lock_to_persister() else {
+            log::error!("error storing cookies in env {env}");
+            return;
+        };
+
+        for cookie in cookies {
+            let is_expired = cookie
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= unix_now());
+
+            let store_res = if is_expired {
+                VerboseDump::note(&format!("removing cookie {} for {}", cookie.name, cookie.domain));
+                persister.delete_cookie_in_env(cookie, env)
+            } else {
+                VerboseDump::note(&format!(
+                    "storing cookie {} for {}{}",
+                    cookie.name, cookie.domain, cookie.path
+                ));
+                persister.set_cookie_in_env(cookie, env)
+            };
+
+            if let Err(err) = store_res {
+                log::error!("error storing cookie {} for {} in env {env}: {err}", cookie.name, cookie.domain);
+            }
+        }
+    }
+
+    /// cookies by name, as `cookies.[name]` in expressions- when
+    /// several domains or paths set a name, the latest stored is used
+    pub fn get_values_for_env(env: &str) -> IndexMap<String, Value> {
+        let mut cookies = CookieJar::get_cookies(env);
+
+        cookies.sort_by(|a, b| a.updated_at.cmp(&b.updated_at));
+
+        cookies
+            .into_iter()
+            .map(|cookie| {
+                Redactor::register(&cookie.value);
+                (cookie.name, Value::String(cookie.value))
+            })
+            .collect()
+    }
+
+    fn get_cookies(env: &str) -> Vec<StoredCookie> {
+        let cookies = match PersisterProvider::get_lock_to_persister() {
+            Some(mut persister) => persister.get_cookies_for_env(env),
+            None => Ok(Vec::new()),
+        };
+
+        match cookies {
+            Ok(cookies) => cookies,
+            Err(err) => {
+                log::error!("error reading cookies for env {env}: {err}");
+                exit::exit(1)
+            }
+        }
+    }
+
+    fn parse_set_cookie(set_cookie: &str, host: &str, request_path: &str) -> Result<StoredCookie, String> {
+        let cookie = Cookie::parse(set_cookie).map_err(|err| format!("invalid Set-Cookie: {err}"))?;
+
+        let (domain, host_only) = match cookie.domain() {
+            Some(domain) if !domain.is_empty() => {
+                let domain = domain.trim_start_matches('.').to_lowercase();
+
+                let is_host_or_parent = host == domain || host.ends_with(&format!(".{domain}"));
+
+                // a rough public suffix check- `Domain=com` is rejected
+                if !is_host_or_parent || (!domain.contains('.') && domain != host) {
+                    return Err(format!("{} set Domain={domain}", cookie.name()));
+                }
+
+                (domain, false)
+            }
+            _ => (host.to_string(), true),
+        };
+
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_string(),
+            _ => CookieJar::default_path(request_path),
+        };
+
+        // Max-Age takes precedence over Expires
+        let expires_at = match (cookie.max_age(), cookie.expires_datetime()) {
+            (Some(max_age), _) => Some(unix_now() + max_age.whole_seconds()),
+            (None, Some(expires)) => Some(expires.unix_timestamp()),
+            (None, None) => None,
+        };
+
+        Ok(StoredCookie {
+            domain,
+            path,
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            expires_at,
+            secure: cookie.secure().unwrap_or(false),
+            http_only: cookie.http_only().unwrap_or(false),
+            host_only,
+            updated_at: None,
+        })
+    }
+
+    /// the request path up to its last `/`-
+    /// `/session/login` sets cookies for `/session`
+    fn default_path(request_path: &str) -> String {
+        match request_path.rfind('/') {
+            Some(0) | None => "/".to_string(),
+            Some(last_slash) => request_path[..last_slash].to_string(),
+        }
+    }
+
+    fn domain_matches(cookie: &StoredCookie, host: &str) -> bool {
+        if cookie.host_only {
+            return host == cookie.domain;
+        }
+
+        host == cookie.domain || host.ends_with(&format!(".{}", cookie.domain))
+    }
+
+    /// `/session` matches `/session` and `/session/profile`, not `/sessions`
+    fn path_matches(cookie_path: &str, request_path: &str) -> bool {
+        if cookie_path == request_path {
+            return true;
+        }
+
+        request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use isahc::HttpClient;
+
+    use crate::{
+        call::DropCall,
+        caller::Caller,
+        parser::drop_id::DropId,
+        persist::{secret_cipher::SecretCipher, StoredCookie},
+        util::unix_now,
+    };
+
+    use super::CookieJar;
+
+    fn cookie(domain: &str, host_only: bool) -> StoredCookie {
+        StoredCookie {
+            domain: domain.to_string(),
+            path: "/".to_string(),
+            name: "session".to_string(),
+            value: "abc".to_string(),
+            expires_at: None,
+            secure: false,
+            http_only: false,
+            host_only,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn paths_match_on_segments() {
+        assert!(CookieJar::path_matches("/session", "/session"));
+        assert!(CookieJar::path_matches("/session", "/session/profile"));
+        assert!(CookieJar::path_matches("/session/", "/session/profile"));
+        assert!(!CookieJar::path_matches("/session", "/sessions"));
+        assert!(!CookieJar::path_matches("/session", "/"));
+        assert!(CookieJar::path_matches("/", "/"));
+        assert!(CookieJar::path_matches("/", "/session/profile"));
+    }
+
+    #[test]
+    fn host_only_cookies_match_their_host() {
+        let cookie = cookie("example.com", true);
+
+        assert!(CookieJar::domain_matches(&cookie, "example.com"));
+        assert!(!CookieJar::domain_matches(&cookie, "api.example.com"));
+    }
+
+    #[test]
+    fn domain_cookies_match_subdomains() {
+        let cookie = cookie("example.com", false);
+
+        assert!(CookieJar::domain_matches(&cookie, "example.com"));
+        assert!(CookieJar::domain_matches(&cookie, "api.example.com"));
+        assert!(!CookieJar::domain_matches(&cookie, "notexample.com"));
+        assert!(!CookieJar::domain_matches(&cookie, "example.org"));
+    }
+
+    #[test]
+    fn set_cookie_is_parsed() {
+        let cookie = CookieJar::parse_set_cookie(
+            "session=abc; Domain=.Example.com; Path=/app; Secure; HttpOnly",
+            "api.example.com",
+            "/login",
+        )
+        .unwrap();
+
+        assert_eq!(cookie.name, "session");
+        assert_eq!(cookie.value, "abc");
+        assert_eq!(cookie.domain, "example.com");
+        assert!(!cookie.host_only);
+        assert_eq!(cookie.path, "/app");
+        assert!(cookie.secure);
+        assert!(cookie.http_only);
+        assert_eq!(cookie.expires_at, None);
+
+        let cookie = CookieJar::parse_set_cookie("session=abc", "api.example.com", "/session/login").unwrap();
+
+        assert_eq!(cookie.domain, "api.example.com");
+        assert!(cookie.host_only);
+        assert_eq!(cookie.path, "/session");
+        assert!(!cookie.secure);
+        assert!(!cookie.http_only);
+
+        let cookie = CookieJar::parse_set_cookie("session=abc", "api.example.com", "/login").unwrap();
+
+        assert_eq!(cookie.path, "/");
+    }
+
+    #[test]
+    fn set_cookie_for_another_domain_is_rejected() {
+        assert!(CookieJar::parse_set_cookie("session=abc; Domain=example.org", "example.com", "/").is_err());
+        assert!(CookieJar::parse_set_cookie("session=abc; Domain=api.example.com", "example.com", "/").is_err());
+        assert!(CookieJar::parse_set_cookie("session=abc; Domain=com", "example.com", "/").is_err());
+    }
+
+    #[test]
+    fn max_age_takes_precedence_over_expires() {
+        let cookie = CookieJar::parse_set_cookie(
+            "session=abc; Max-Age=60; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            "example.com",
+            "/",
+        )
+        .unwrap();
+
+        let expires_at = cookie.expires_at.unwrap();
+        assert!(expires_at > unix_now() && expires_at <= unix_now() + 60);
+
+        let cookie = CookieJar::parse_set_cookie("session=abc; Expires=Wed, 21 Oct 2015 07:28:00 GMT", "example.com", "/")
+            .unwrap();
+
+        assert_eq!(cookie.expires_at, Some(1445412480));
+    }
+
+    /// only the final response's cookies are stored
+    /// when redirects are followed
+    #[test]
+    fn redirect_hop_cookies_are_not_stored() {
+        SecretCipher::set_test_key();
+
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(GET).path("/login");
+            then.status(302)
+                .header("location", "/home")
+                .header("set-cookie", "redirect_hop=1; Path=/");
+        });
+
+        server.mock(|when, then| {
+            when.method(GET).path("/home");
+            then.status(200)
+                .header("set-cookie", "redirect_final=1; Path=/")
+                .body("home");
+        });
+
+        let mut drop_call = DropCall::default(DropId::get_call_drop_id("get", "cookies", "login"), "get");
+        drop_call.base_url = server.base_url();
+        drop_call.path = "/login".to_string();
+        drop_call.settings.follow_redirects = Some(true);
+        drop_call.settings.cookies = Some(true);
+
+        let call_record = Caller {
+            drop_call,
+            client: HttpClient::new().unwrap(),
+        }
+        .call()
+        .unwrap();
+
+        assert_eq!(call_record.status_code.map(|status| status.as_u16()), Some(200));
+
+        let cookies = CookieJar::get_values_for_env("base");
+
+        assert!(cookies.contains_key("redirect_final"));
+        assert!(!cookies.contains_key("redirect_hop"));
+    }
+}
@@ -1,72 +1,303 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use anyhow::anyhow;
-use isahc::{config::Configurable, http::{request::Builder, Error, HeaderMap}, HttpClient, ReadResponseExt, Request};
-
-use crate::{assert::{assertion::CallAssertion, types::Assert}, call::DropCall, record::CallRecord};
+use isahc::{config::Configurable, http::{request::Builder, Error, HeaderMap, StatusCode, Uri}, HttpClient, ReadResponseExt, Request, ResponseExt};
+
+use auth::CallAuth;
+use cookies::CookieJar;
+use network::Network;
+use verbose::VerboseDump;
+
+use crate::util::exit;
+use crate::{assert::{assertion::CallAssertion, types::Assert}, call::DropCall, cmd::output::Output, record::{CallAttempt, CallRecord, CallTimings}, script::DropScript, text_println, util::redact::Redactor};
+
+pub mod auth;
+pub mod authorization_code;
+pub mod client;
+pub mod cookies;
+pub mod network;
+pub mod oauth2;
+pub mod signing;
+pub mod tls;
+pub mod verbose;
 
 /// http transaction manager
 #[derive(Debug)]
 pub struct Caller {
-    pub drop_call: DropCall
+    pub drop_call: DropCall,
+    /// shared by the calls in a run
+    pub client: HttpClient,
 }
 
 impl Caller {
-    pub fn call(self) -> Result<CallRecord, anyhow::Error> {
+    pub fn call(mut self) -> Result<CallRecord, anyhow::Error> {
 
         log::debug!("Caller call: {:#?}", self);
 
-        let client = HttpClient::builder()
-            .timeout(Duration::from_secs(5))
-            .build()?;
+        if let Some(script_before) = self.drop_call.settings.script_before.clone() {
+            if let Err(err) = DropScript::run_before(&script_before, &mut self.drop_call) {
+                log::error!("script_before error: {err}");
+                return Err(anyhow!("script_before error: {err}"));
+            }
+        }
+
+        let settings = self.drop_call.settings.clone();
+
+        let body = match &self.drop_call.body {
+            Some(body) => Some(serde_json::to_vec(body)?),
+            None => None,
+        };
+
+        let mut max_attempts = settings.get_retries() + 1;
+        let mut attempts: Vec<CallAttempt> = Vec::new();
+        let mut attempt = 1;
+        let mut is_token_renewed = false;
+
+        self.resolve_auth_token(false)?;
+
+        loop {
+            let started = Instant::now();
+
+            let mut response_result = self.send(body.as_deref(), attempt);
+
+            let duration = started.elapsed();
+
+            let mut call_attempt = CallAttempt {
+                attempt,
+                status: response_result.as_ref().ok().map(|response| response.status().as_u16()),
+                error: response_result.as_ref().err().map(|err| err.to_string()),
+                duration_ms: duration.as_millis(),
+                timings: None,
+                retry_in_ms: None,
+            };
+
+            // a rejected oauth2 token is replaced, and the
+            // call sent again once, outside of its retries
+            let is_token_rejected = !is_token_renewed
+                && matches!(settings.auth, Some(CallAuth::OAuth2(_)))
+                && response_result
+                    .as_ref()
+                    .is_ok_and(|response| response.status() == StatusCode::UNAUTHORIZED);
+
+            if is_token_rejected {
+                Caller::dump_failed_attempt(&mut response_result, &mut call_attempt);
+
+                VerboseDump::note("oauth2 token rejected (status 401), requesting a new token");
+                attempts.push(call_attempt);
+
+                self.resolve_auth_token(true)?;
+
+                is_token_renewed = true;
+                max_attempts += 1;
+                attempt += 1;
+                continue;
+            }
+
+            let should_retry = attempt < max_attempts
+                && match &response_result {
+                    Ok(response) => settings.should_retry_status(response.status()),
+                    Err(err) => settings.should_retry_error(err),
+                };
+
+            if !should_retry {
+                attempts.push(call_attempt);
+
+                return match response_result {
+                    Ok(response) => self.handle_request_success(response, duration, attempts),
+                    Err(err) => self.handle_request_error(err, &attempts),
+                };
+            }
+
+            let delay = settings.get_retry_delay(
+                attempt,
+                response_result.as_ref().ok().map(|response| response.headers()),
+            );
+
+            Caller::dump_failed_attempt(&mut response_result, &mut call_attempt);
+
+            let reason = match (&call_attempt.status, &call_attempt.error) {
+                (Some(status), _) => format!("status {status}"),
+                (_, Some(err)) => err.to_string(),
+                _ => String::new(),
+            };
+
+            log::debug!("Caller attempt {attempt} of {max_attempts} failed ({reason}), retrying in {delay:?}");
+            VerboseDump::note(&format!(
+                "attempt {attempt} of {max_attempts} failed ({reason}), retrying in {}ms",
+                delay.as_millis()
+            ));
+
+            call_attempt.retry_in_ms = Some(delay.as_millis());
+            attempts.push(call_attempt);
+
+            std::thread::sleep(delay);
+
+            attempt += 1;
+        }
+    }
+
+    /// fetches, or reuses, the access token of an oauth2 auth
+    fn resolve_auth_token(&mut self, force_new: bool) -> Result<(), anyhow::Error> {
+        let settings = self.drop_call.settings.clone();
+
+        let Some(CallAuth::OAuth2(oauth2)) = &mut self.drop_call.settings.auth else {
+            return Ok(());
+        };
+
+        if let Err(err) = oauth2.resolve_token(&self.client, &settings, force_new) {
+            log::error!("{err}");
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// the verbose dump of an attempt that isn't the last
+    fn dump_failed_attempt(response_result: &mut Result<isahc::Response<isahc::Body>, isahc::Error>, call_attempt: &mut CallAttempt) {
+        if let Ok(response) = response_result {
+            let response_text = response.text().ok();
+
+            VerboseDump::response(
+                response.version(),
+                response.status(),
+                response.headers(),
+                response_text.as_deref(),
+            );
+
+            call_attempt.timings = CallTimings::from_response(response);
+            VerboseDump::timings(call_attempt.timings.as_ref());
+        }
+    }
 
-        let request_builder = self.generate_request_from_call();
+    fn send(&self, body: Option<&[u8]>, attempt: u32) -> Result<isahc::Response<isahc::Body>, isahc::Error> {
+        let request_builder = self.generate_request_from_call(body);
 
-        let response_result = if let Some(body) = &self.drop_call.body {
-            let request = request_builder.body(serde_json::to_vec(body)?).unwrap();
+        if let Some(body) = body {
+            let request = request_builder.body(body.to_vec()).unwrap();
             log::debug!("Caller request: {request:?}");
-            println!("\ncalling {}", request.uri());
-            client.send(request)
-        
+            if attempt == 1 {
+                text_println!("\ncalling {}", Redactor::redact(&request.uri().to_string()));
+            }
+            VerboseDump::request(request.method(), request.uri(), request.headers(), Some(request.body()));
+            self.client.send(request)
+
         } else {
             let request = request_builder.body(()).unwrap();
             log::debug!("Caller request: {request:?}");
-            println!("calling {}", request.uri());
-            client.send(request)
-        };
-
-        if response_result.is_err() {
-            return self.handle_request_error(response_result.unwrap_err());
-        } else {
-            return self.handle_request_success(response_result.unwrap());
+            if attempt == 1 {
+                text_println!("calling {}", Redactor::redact(&request.uri().to_string()));
+            }
+            VerboseDump::request(request.method(), request.uri(), request.headers(), None);
+            self.client.send(request)
         }
     }
 
-    pub fn generate_request_from_call(&self) -> Builder {
+    /// `body` is the serialized body, for request signing
+    pub fn generate_request_from_call(&self, body: Option<&[u8]>) -> Builder {
     
         let headers = &self.drop_call.headers;
-        let full_url = self.drop_call.base_url.to_string() + &self.drop_call.path;
-        let mut request = Request::builder().method(&self.drop_call.method).uri(full_url);
+        let full_url = self.get_request_url();
+        let mut request = Request::builder()
+            .method(&self.drop_call.method)
+            .uri(&full_url)
+            .timeout(self.drop_call.settings.get_timeout());
+
+        let uri = full_url.parse::<Uri>().ok();
+
+        if let Some(uri) = &uri {
+            request = Network::configure_request(&self.drop_call.settings, request, uri);
+        }
+
+        let cookie_header = match &uri {
+            Some(uri) if self.drop_call.settings.is_cookie_jar_enabled() => {
+                let call_cookie = headers.get("cookie").and_then(|cookie| cookie.to_str().ok());
+                CookieJar::get_cookie_header(uri, call_cookie)
+            }
+            _ => None,
+        };
 
         for (k,v) in headers {
+            // merged with the jar's cookies
+            if cookie_header.is_some() && k == "cookie" {
+                continue;
+            }
+
             request = request.header(k,v);
         }
 
+        if let Some(cookie_header) = cookie_header {
+            request = request.header("cookie", cookie_header);
+        }
+
+        if let Some(auth) = &self.drop_call.settings.auth {
+            request = auth.apply_to_request(request, headers);
+
+            request = match auth.sign_request(request, body) {
+                Ok(request) => request,
+                Err(err) => {
+                    log::error!("error signing request for {}: {err}", self.drop_call.drop_id.drop_id().unwrap_or_default());
+                    exit::exit(1)
+                }
+            };
+        }
+
         request
     }
 
-    pub fn handle_request_error(&self, response_result: isahc::Error)-> Result<CallRecord, anyhow::Error> {
+    /// the call's url, with an api key
+    /// from its auth in the query
+    fn get_request_url(&self) -> String {
+        let full_url = self.drop_call.full_url();
+
+        match &self.drop_call.settings.auth {
+            Some(auth) => auth.apply_to_url(full_url),
+            None => full_url,
+        }
+    }
+
+    pub fn handle_request_error(&self, response_result: isahc::Error, attempts: &[CallAttempt])-> Result<CallRecord, anyhow::Error> {
+        let after_attempts = if attempts.len() > 1 {
+            format!(" after {} attempts", attempts.len())
+        } else {
+            String::new()
+        };
+
         // print error
-        log::error!("error completing request {response_result}");
+        log::error!("error completing request{after_attempts} {response_result}");
 
-        Err(anyhow!("error completing request"))
+        Err(anyhow!("error completing request{after_attempts}: {response_result}"))
     }
 
-    pub fn handle_request_success(mut self, mut response: isahc::Response<isahc::Body>) -> Result<CallRecord, anyhow::Error> {
+    pub fn handle_request_success(mut self, mut response: isahc::Response<isahc::Body>, duration: Duration, attempts: Vec<CallAttempt>) -> Result<CallRecord, anyhow::Error> {
 
         log::debug!("response {response:?}");
 
         let response_string = &response.text();
 
+        let response_cookies = match response.effective_uri().cloned().or_else(|| self.drop_call.full_url().parse().ok()) {
+            Some(uri) if self.drop_call.settings.is_cookie_jar_enabled() => {
+                CookieJar::from_response(&uri, response.headers())
+            }
+            _ => Vec::new(),
+        };
+
+        VerboseDump::response(
+            response.version(),
+            response.status(),
+            response.headers(),
+            response_string.as_deref().ok(),
+        );
+
+        if let Some(effective_uri) = response.effective_uri() {
+            if effective_uri.to_string() != self.get_request_url() {
+                VerboseDump::note(&format!("redirected to {effective_uri}"));
+            }
+        }
+
+        CookieJar::store(&response_cookies);
+
+        let timings = CallTimings::from_response(&response);
+        VerboseDump::timings(timings.as_ref());
+
         let drop_id_string = &self.drop_call.drop_id.drop_id().unwrap();
         let outputs = &self.drop_call.outputs.take();
         let asserts: Vec<Assert> = self.drop_call.asserts.drain(..).collect();
@@ -81,9 +312,22 @@ impl Caller {
             self.handle_failed_status_code(response_string);
         }
 
+        let script_after = self.drop_call.settings.script_after.clone();
+        let script_timeout = self.drop_call.settings.get_script_timeout();
+
         let mut record = CallRecord::init(self.drop_call, is_successful_call);
 
+        record.client = Some(self.client.clone());
+
         record.set_status_code(response_status);
+        record.duration_ms = Some(duration.as_millis());
+        record.attempts = attempts;
+        record.timings = timings.clone();
+
+        if let Some(attempt) = record.attempts.last_mut() {
+            attempt.timings = timings;
+        }
+        record.response_headers = CallRecord::headers_to_map(response_headers);
 
         match response_string {
             Ok(response_string) => {
@@ -98,7 +342,7 @@ impl Caller {
                 );
 
                 if !asserts.is_empty() {
-                    CallAssertion::run_assertions(
+                    record.assert_results = CallAssertion::run_assertions(
                         drop_id_string,
                         asserts,
                         &response_string,
@@ -112,6 +356,13 @@ impl Caller {
             },
         }
 
+        if let Some(script_after) = script_after {
+            if let Err(err) = DropScript::run_after(&script_after, script_timeout, &mut record) {
+                log::error!("script_after error: {err}");
+                return Err(anyhow!("script_after error: {err}"));
+            }
+        }
+
         Ok(record)
     }
 
@@ -120,24 +371,24 @@ impl Caller {
             Ok(msg) => {
                 if msg.is_empty() {
                     log::trace!("raw response_string {response_string:#?}");
-                    println!("\ncall failed (no response message)");
+                    text_println!("\ncall failed (no response message)");
                 } else {
                     let try_deserialize: Result<hcl::Value, serde_json::Error> =
                         serde_json::from_str(msg);
 
                     match try_deserialize {
                         Ok(ser) => {
-                            println!("\ncall failed: {ser:?}");
+                            text_println!("\ncall failed: {}", Redactor::redact(&format!("{ser:?}")));
                         }
                         _ => {
-                            println!("\ncall failed: {msg:?}");
+                            text_println!("\ncall failed: {}", Redactor::redact(&format!("{msg:?}")));
                         }
                     }
                 }
             }
             Err(err) => {
                 log::trace!("response_string unwrap err {err:#?}");
-                println!("\ncall failed");
+                text_println!("\ncall failed");
             }
         }
     }
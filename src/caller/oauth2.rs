@@ -0,0 +1,752 @@
+use anyhow::anyhow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use hcl::{Map, Value};
+use isahc::{config::Configurable, http::Uri, HttpClient, ReadResponseExt, Request};
+use percent_encoding::utf8_percent_encode;
+
+use crate::{
+    call::call_settings::CallSettings,
+    cmd::ctx::CmdContext,
+    persist::{PersisterProvider, StoredToken},
+    util::{jwt::Jwt, redact::Redactor, unix_now},
+};
+
+use super::{
+    auth::{CallAuth, QUERY_ENCODE_SET},
+    authorization_code::AuthorizationCode,
+    network::Network,
+    verbose::VerboseDump,
+};
+
+/// tokens expiring sooner than this are
+/// refreshed before the call is sent
+const EXPIRY_MARGIN_SECS: i64 = 30;
+
+/// secrets the authorization code grant stores its tokens in
+const DEFAULT_ACCESS_TOKEN_SECRET: &str = "oauth2_access_token";
+const DEFAULT_REFRESH_TOKEN_SECRET: &str = "oauth2_refresh_token";
+
+/// `grant` of an oauth2 auth block
+#[derive(Debug, Clone)]
+pub enum OAuth2Grant {
+    ClientCredentials,
+    Password { username: String, password: String },
+    RefreshToken { refresh_token: String },
+    AuthorizationCode {
+        authorize_url: String,
+        redirect_port: u16,
+        open_browser: bool,
+        access_token_secret: String,
+        refresh_token_secret: String,
+    },
+}
+
+/// an oauth2 auth block- the access token is fetched from
+/// `token_url`, and cached per environment until it expires
+///
+/// `grant = "client_credentials"`, the default
+/// `grant = "password"`, `username` and `password`
+/// `grant = "refresh_token"`, `refresh_token`
+/// `grant = "authorization_code"`, `authorize_url`- see `AuthorizationCode`
+///
+/// `token_url` and `client_id` are required, `client_secret` and
+/// `scope`, a string or a list, are optional- the client credentials
+/// are sent as basic auth, or in the form with `client_auth = "body"`
+#[derive(Debug, Clone)]
+pub struct OAuth2Settings {
+    pub grant: OAuth2Grant,
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+    pub client_auth_in_body: bool,
+    /// set by the caller before the request is sent
+    pub token: Option<StoredToken>,
+}
+
+impl OAuth2Settings {
+    pub fn from_object(object: &Map<String, Value>) -> Result<OAuth2Settings, String> {
+        let get = |key: &str| CallAuth::get_string(object, key);
+
+        let grant = match CallAuth::get_optional_string(object, "grant")?.as_deref() {
+            None | Some("client_credentials") => OAuth2Grant::ClientCredentials,
+            Some("password") => OAuth2Grant::Password {
+                username: get("username")?,
+                password: get("password")?,
+            },
+            Some("refresh_token") => OAuth2Grant::RefreshToken {
+                refresh_token: get("refresh_token")?,
+            },
+            Some("authorization_code") => OAuth2Grant::AuthorizationCode {
+                authorize_url: get("authorize_url")?,
+                redirect_port: match object.get("redirect_port") {
+                    None | Some(Value::Null) => 0,
+                    Some(Value::Number(port)) if port.as_u64().is_some_and(|port| port <= u16::MAX as u64) => {
+                        port.as_u64().unwrap_or_default() as u16
+                    }
+                    Some(other) => return Err(format!("invalid oauth2 redirect_port {other}, expected a port number")),
+                },
+                open_browser: object.get("open_browser").and_then(Value::as_bool).unwrap_or(true),
+                access_token_secret: CallAuth::get_optional_string(object, "access_token_secret")?
+                    .unwrap_or(DEFAULT_ACCESS_TOKEN_SECRET.to_string()),
+                refresh_token_secret: CallAuth::get_optional_string(object, "refresh_token_secret")?
+                    .unwrap_or(DEFAULT_REFRESH_TOKEN_SECRET.to_string()),
+            },
+            Some(other) => {
+                return Err(format!(
+                    "invalid oauth2 grant {other}, expected one of client_credentials, password, refresh_token, authorization_code"
+                ))
+            }
+        };
+
+        let token_url = get("token_url")?;
+
+        if token_url.parse::<Uri>().is_err() {
+            return Err(format!("invalid oauth2 token_url {token_url}"));
+        }
+
+        let scope = match object.get("scope") {
+            Some(Value::Array(scopes)) => Some(
+                scopes
+                    .iter()
+                    .map(|scope| match scope {
+                        Value::String(scope) => Ok(scope.to_string()),
+                        other => Err(format!("auth scope must be a string or list of strings, found {other}")),
+                    })
+                    .collect::<Result<Vec<String>, String>>()?
+                    .join(" "),
+            ),
+            _ => CallAuth::get_optional_string(object, "scope")?,
+        };
+
+        let client_auth_in_body = match CallAuth::get_optional_string(object, "client_auth")?.as_deref() {
+            None | Some("basic") => false,
+            Some("body") => true,
+            Some(other) => return Err(format!("invalid oauth2 client_auth {other}, expected \"basic\" or \"body\"")),
+        };
+
+        Ok(OAuth2Settings {
+            grant,
+            token_url,
+            client_id: get("client_id")?,
+            client_secret: CallAuth::get_optional_string(object, "client_secret")?,
+            scope,
+            client_auth_in_body,
+            token: None,
+        })
+    }
+
+    pub fn register_sensitive_values(&self) {
+        if let Some(client_secret) = &self.client_secret {
+            Redactor::register(client_secret);
+        }
+
+        match &self.grant {
+            OAuth2Grant::ClientCredentials | OAuth2Grant::AuthorizationCode { .. } => {}
+            OAuth2Grant::Password { password, .. } => Redactor::register(password),
+            OAuth2Grant::RefreshToken { refresh_token } => Redactor::register(refresh_token),
+        }
+    }
+
+    /// the cached token- refreshed with its refresh token when it is
+    /// about to expire, and fetched with the grant when there is none
+    ///
+    /// `force_new` after the server rejects the cached token
+    pub fn resolve_token(&mut self, client: &HttpClient, settings: &CallSettings, force_new: bool) -> Result<(), anyhow::Error> {
+        let env = &CmdContext::get_env();
+        let cache_key = self.get_cache_key();
+
+        let cached = match OAuth2Settings::get_cached_token(&cache_key, env)? {
+            Some(cached) => Some(cached),
+            None => self.get_token_from_secrets(env),
+        };
+
+        if force_new {
+            OAuth2Settings::delete_cached_token(&cache_key, env);
+        }
+
+        let is_expiring = |token: &StoredToken| {
+            token
+                .expires_at
+                .is_some_and(|expires_at| expires_at - EXPIRY_MARGIN_SECS <= unix_now())
+        };
+
+        let token = match cached {
+            Some(cached) if !force_new && !is_expiring(&cached) => {
+                VerboseDump::note(&format!("oauth2 using cached token from {}", self.token_url));
+                OAuth2Settings::register_token(&cached);
+                self.token = Some(cached);
+                return Ok(());
+            }
+            Some(StoredToken { refresh_token: Some(refresh_token), .. }) => {
+                VerboseDump::note(&format!("oauth2 refreshing token from {}", self.token_url));
+
+                let grant = OAuth2Grant::RefreshToken { refresh_token: refresh_token.clone() };
+
+                match self.request_token(client, settings, &grant) {
+                    // the refresh token is kept when no new one is sent
+                    Ok(mut token) => {
+                        token.refresh_token = token.refresh_token.or(Some(refresh_token));
+                        token
+                    }
+                    // the refresh token may have expired too
+                    Err(err) => {
+                        VerboseDump::note(&format!("oauth2 refresh failed ({err}), requesting a new token"));
+                        self.request_token(client, settings, &self.grant)?
+                    }
+                }
+            }
+            _ => {
+                VerboseDump::note(&format!("oauth2 requesting token from {}", self.token_url));
+                self.request_token(client, settings, &self.grant)?
+            }
+        };
+
+        OAuth2Settings::set_cached_token(&cache_key, &token, env);
+
+        OAuth2Settings::register_token(&token);
+
+        self.set_token_secrets(&token, env);
+
+        self.token = Some(token);
+
+        Ok(())
+    }
+
+    /// user tokens stored as secrets, e.g. by another run or by
+    /// `drop secret set`- the access token is used until its `exp`
+    /// claim, and refreshed first when it isn't a jwt
+    fn get_token_from_secrets(&self, env: &str) -> Option<StoredToken> {
+        let OAuth2Grant::AuthorizationCode { access_token_secret, refresh_token_secret, .. } = &self.grant else {
+            return None;
+        };
+
+        let secrets = PersisterProvider::get_lock_to_persister()?.get_secrets_for_env(env).ok()?;
+
+        let get = |key: &str| match secrets.get(key) {
+            Some(Value::String(value)) if !value.is_empty() => Some(value.to_string()),
+            _ => None,
+        };
+
+        let refresh_token = get(refresh_token_secret)?;
+        let access_token = get(access_token_secret).unwrap_or_default();
+
+        Some(StoredToken {
+            expires_at: Some(Jwt::get_expiry(&access_token).unwrap_or_default()),
+            access_token,
+            token_type: "Bearer".to_string(),
+            refresh_token: Some(refresh_token),
+        })
+    }
+
+    /// user tokens are also stored as secrets for the
+    /// env, as `secrets.[access_token_secret]`
+    fn set_token_secrets(&self, token: &StoredToken, env: &str) {
+        let OAuth2Grant::AuthorizationCode { access_token_secret, refresh_token_secret, .. } = &self.grant else {
+            return;
+        };
+
+        let Some(mut persister) = PersisterProvider::get_lock_to_persister() else {
+            log::error!("error storing oauth2 tokens as secrets in env {env}");
+            return;
+        };
+
+        let mut secrets = vec![(access_token_secret, &token.access_token)];
+
+        if let Some(refresh_token) = &token.refresh_token {
+            secrets.push((refresh_token_secret, refresh_token));
+        }
+
+        for (key, value) in secrets {
+            match persister.set_secret_in_env(key, value, env, "oauth2 authorization_code") {
+                Ok(()) => VerboseDump::note(&format!("oauth2 stored secrets.{key}")),
+                Err(err) => log::error!("error storing oauth2 token as secret {key} in env {env}: {err}"),
+            }
+        }
+    }
+
+    /// form post to the token endpoint, with
+    /// the network settings of the call
+    fn request_token(&self, client: &HttpClient, settings: &CallSettings, grant: &OAuth2Grant) -> Result<StoredToken, anyhow::Error> {
+        let mut form: Vec<(&str, &str)> = Vec::new();
+        let authorization: AuthorizationCode;
+
+        match grant {
+            OAuth2Grant::ClientCredentials => form.push(("grant_type", "client_credentials")),
+            OAuth2Grant::Password { username, password } => {
+                form.push(("grant_type", "password"));
+                form.push(("username", username));
+                form.push(("password", password));
+            }
+            OAuth2Grant::RefreshToken { refresh_token } => {
+                form.push(("grant_type", "refresh_token"));
+                form.push(("refresh_token", refresh_token));
+            }
+            OAuth2Grant::AuthorizationCode { authorize_url, redirect_port, open_browser, .. } => {
+                VerboseDump::note(&format!("oauth2 authorizing with {authorize_url}"));
+
+                authorization = AuthorizationCode::authorize(
+                    authorize_url,
+                    &self.client_id,
+                    self.scope.as_deref(),
+                    *redirect_port,
+                    *open_browser,
+                )?;
+
+                form.push(("grant_type", "authorization_code"));
+                form.push(("code", &authorization.code));
+                form.push(("redirect_uri", &authorization.redirect_uri));
+                form.push(("code_verifier", &authorization.code_verifier));
+            }
+        }
+
+        // the authorize request set the scope of the code
+        let is_code_exchange = matches!(grant, OAuth2Grant::AuthorizationCode { .. });
+
+        if let Some(scope) = self.scope.as_deref().filter(|_| !is_code_exchange) {
+            form.push(("scope", scope));
+        }
+
+        let mut request = Request::post(&self.token_url)
+            .timeout(settings.get_timeout())
+            .header("content-type", "application/x-www-form-urlencoded")
+            .header("accept", "application/json");
+
+        // public clients, without a secret, only send their id
+        if self.client_auth_in_body || self.client_secret.is_none() {
+            form.push(("client_id", &self.client_id));
+
+            if let Some(client_secret) = &self.client_secret {
+                form.push(("client_secret", client_secret));
+            }
+        } else {
+            // the client credentials are form encoded before basic encoding
+            let credentials = format!(
+                "{}:{}",
+                utf8_percent_encode(&self.client_id, QUERY_ENCODE_SET),
+                utf8_percent_encode(self.client_secret.as_deref().unwrap_or_default(), QUERY_ENCODE_SET)
+            );
+
+            request = request.header("authorization", format!("Basic {}", BASE64_STANDARD.encode(credentials)));
+        }
+
+        if let Ok(uri) = self.token_url.parse::<Uri>() {
+            request = Network::configure_request(settings, request, &uri);
+        }
+
+        let body = form
+            .iter()
+            .map(|(key, value)| format!("{key}={}", utf8_percent_encode(value, QUERY_ENCODE_SET)))
+            .collect::<Vec<String>>()
+            .join("&");
+
+        let mut response = client.send(request.body(body)?)?;
+
+        let status = response.status();
+        let response_text = response.text().unwrap_or_default();
+        let response_json: serde_json::Value = serde_json::from_str(&response_text).unwrap_or_default();
+
+        let get = |key: &str| response_json.get(key).and_then(serde_json::Value::as_str).map(str::to_string);
+
+        if !status.is_success() {
+            let error = match (get("error"), get("error_description")) {
+                (Some(error), Some(description)) => format!(": {error}, {description}"),
+                (Some(error), None) => format!(": {error}"),
+                _ => String::new(),
+            };
+
+            return Err(anyhow!("oauth2 token request to {} failed with status {}{error}", self.token_url, status.as_u16()));
+        }
+
+        let Some(access_token) = get("access_token") else {
+            return Err(anyhow!("oauth2 token response from {} has no access_token", self.token_url));
+        };
+
+        let expires_at = response_json
+            .get("expires_in")
+            .and_then(|expires_in| expires_in.as_i64().or_else(|| expires_in.as_str()?.parse().ok()))
+            .map(|expires_in| unix_now() + expires_in)
+            // a jwt access token's own expiry
+            .or_else(|| Jwt::get_expiry(&access_token));
+
+        Ok(StoredToken {
+            access_token,
+            token_type: get("token_type").unwrap_or("Bearer".to_string()),
+            expires_at,
+            refresh_token: get("refresh_token"),
+        })
+    }
+
+    /// tokens are shared by auth blocks requesting the same
+    /// client, user and scope from the same endpoint
+    fn get_cache_key(&self) -> String {
+        let grant = match &self.grant {
+            OAuth2Grant::ClientCredentials => "client_credentials".to_string(),
+            OAuth2Grant::Password { username, .. } => format!("password {username}"),
+            // the refresh token itself isn't stored in the key
+            OAuth2Grant::RefreshToken { .. } => "refresh_token".to_string(),
+            OAuth2Grant::AuthorizationCode { access_token_secret, .. } => format!("authorization_code {access_token_secret}"),
+        };
+
+        format!(
+            "{} {} {grant} {}",
+            self.token_url,
+            self.client_id,
+            self.scope.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn get_cached_token(cache_key: &str, env: &str) -> Result<Option<StoredToken>, anyhow::Error> {
+        match PersisterProvider::get_lock_to_persister() {
+            Some(mut persister) => persister
+                .get_token_in_env(cache_key, env)
+                .map_err(|err| anyhow!("error reading oauth2 token for env {env}: {err}")),
+            None => Ok(None),
+        }
+    }
+
+    fn set_cached_token(cache_key: &str, token: &StoredToken, env: &str) {
+        let Some(mut persister) = PersisterProvider::get_lock_to_persister() else {
+            log::error!("error storing oauth2 token in env {env}");
+            return;
+        };
+
+        if let Err(err) = persister.set_token_in_env(cache_key, token, env) {
+            log::error!("error storing oauth2 token in env {env}: {err}");
+        }
+    }
+
+    fn delete_cached_token(cache_key: &str, env: &str) {
+        let Some(mut persister) = PersisterProvider::get_lock_to_persister() else {
+            return;
+        };
+
+        if let Err(err) = persister.delete_token_in_env(cache_key, env) {
+            log::error!("error removing oauth2 token in env {env}: {err}");
+        }
+    }
+
+    fn register_token(token: &StoredToken) {
+        Redactor::register(&token.access_token);
+
+        if let Some(refresh_token) = &token.refresh_token {
+            Redactor::register(refresh_token);
+        }
+    }
+}
+
+impl StoredToken {
+    /// `Bearer [access_token]`- token types
+    /// are case insensitive, and some servers
+    /// send `bearer`
+    pub fn get_authorization(&self) -> String {
+        let token_type = if self.token_type.eq_ignore_ascii_case("bearer") {
+            "Bearer"
+        } else {
+            self.token_type.as_str()
+        };
+
+        format!("{token_type} {}", self.access_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use httpmock::prelude::*;
+    use isahc::HttpClient;
+    use serde_json::json;
+
+    use crate::{
+        call::{call_settings::CallSettings, DropCall},
+        caller::{auth::CallAuth, Caller},
+        parser::drop_id::DropId,
+        persist::{secret_cipher::SecretCipher, PersisterProvider, StoredToken},
+        util::unix_now,
+    };
+
+    use super::{OAuth2Grant, OAuth2Settings, EXPIRY_MARGIN_SECS};
+
+    /// tokens are cached encrypted, with the tests' key
+    fn oauth2(server: &MockServer, grant: OAuth2Grant) -> OAuth2Settings {
+        SecretCipher::set_test_key();
+
+        OAuth2Settings {
+            grant,
+            token_url: server.url("/token"),
+            client_id: "drop client".to_string(),
+            client_secret: Some("client secret".to_string()),
+            scope: Some("read write".to_string()),
+            client_auth_in_body: false,
+            token: None,
+        }
+    }
+
+    fn resolve(oauth2: &mut OAuth2Settings) -> StoredToken {
+        oauth2
+            .resolve_token(&HttpClient::new().unwrap(), &CallSettings::default(), false)
+            .unwrap();
+
+        oauth2.token.clone().unwrap()
+    }
+
+    #[test]
+    fn client_credentials_grant() {
+        let server = MockServer::start();
+
+        let token_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/token")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .header(
+                    "authorization",
+                    format!("Basic {}", BASE64_STANDARD.encode("drop%20client:client%20secret")),
+                )
+                .x_www_form_urlencoded_tuple("grant_type", "client_credentials")
+                .x_www_form_urlencoded_tuple("scope", "read write");
+            then.status(200)
+                .json_body(json!({ "access_token": "cc token", "token_type": "bearer", "expires_in": 3600 }));
+        });
+
+        let mut oauth2 = oauth2(&server, OAuth2Grant::ClientCredentials);
+
+        let token = resolve(&mut oauth2);
+
+        assert_eq!(token.get_authorization(), "Bearer cc token");
+        assert!(token.expires_at.is_some_and(|expires_at| expires_at > unix_now() + 3500));
+
+        // cached until it expires
+        let mut oauth2 = oauth2.clone();
+        oauth2.token = None;
+
+        assert_eq!(resolve(&mut oauth2).access_token, "cc token");
+
+        token_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn password_grant_with_client_auth_in_body() {
+        let server = MockServer::start();
+
+        let token_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/token")
+                .x_www_form_urlencoded_tuple("grant_type", "password")
+                .x_www_form_urlencoded_tuple("username", "ada")
+                .x_www_form_urlencoded_tuple("password", "p&ss word")
+                .x_www_form_urlencoded_tuple("client_id", "drop client")
+                .x_www_form_urlencoded_tuple("client_secret", "client secret");
+            then.status(200)
+                .json_body(json!({ "access_token": "password token", "refresh_token": "password refresh" }));
+        });
+
+        let mut oauth2 = oauth2(
+            &server,
+            OAuth2Grant::Password {
+                username: "ada".to_string(),
+                password: "p&ss word".to_string(),
+            },
+        );
+        oauth2.client_auth_in_body = true;
+
+        let token = resolve(&mut oauth2);
+
+        assert_eq!(token.access_token, "password token");
+        assert_eq!(token.refresh_token.as_deref(), Some("password refresh"));
+        assert_eq!(token.expires_at, None);
+
+        token_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn refresh_token_grant() {
+        let server = MockServer::start();
+
+        let token_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/token")
+                .x_www_form_urlencoded_tuple("grant_type", "refresh_token")
+                .x_www_form_urlencoded_tuple("refresh_token", "configured refresh");
+            then.status(200)
+                .json_body(json!({ "access_token": "refreshed token", "expires_in": "600" }));
+        });
+
+        let mut oauth2 = oauth2(
+            &server,
+            OAuth2Grant::RefreshToken {
+                refresh_token: "configured refresh".to_string(),
+            },
+        );
+
+        let token = resolve(&mut oauth2);
+
+        assert_eq!(token.access_token, "refreshed token");
+        assert!(token.expires_at.is_some_and(|expires_at| expires_at > unix_now() + 500));
+
+        token_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn token_expiring_within_the_margin_is_refreshed() {
+        let server = MockServer::start();
+
+        let client_credentials_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/token")
+                .x_www_form_urlencoded_tuple("grant_type", "client_credentials");
+            then.status(200).json_body(json!({
+                "access_token": "expiring token",
+                "refresh_token": "first refresh",
+                "expires_in": EXPIRY_MARGIN_SECS - 5,
+            }));
+        });
+
+        // no new refresh token is sent
+        let refresh_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/token")
+                .x_www_form_urlencoded_tuple("grant_type", "refresh_token")
+                .x_www_form_urlencoded_tuple("refresh_token", "first refresh");
+            then.status(200)
+                .json_body(json!({ "access_token": "refreshed token", "expires_in": 3600 }));
+        });
+
+        let mut oauth2 = oauth2(&server, OAuth2Grant::ClientCredentials);
+
+        assert_eq!(resolve(&mut oauth2).access_token, "expiring token");
+
+        let token = resolve(&mut oauth2);
+
+        assert_eq!(token.access_token, "refreshed token");
+        assert_eq!(token.refresh_token.as_deref(), Some("first refresh"));
+
+        // the refreshed token is cached
+        assert_eq!(resolve(&mut oauth2).access_token, "refreshed token");
+
+        client_credentials_mock.assert_hits(1);
+        refresh_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn token_request_error_is_reported() {
+        let server = MockServer::start();
+
+        server.mock(|when, then| {
+            when.method(POST).path("/token");
+            then.status(400)
+                .json_body(json!({ "error": "invalid_client", "error_description": "unknown client" }));
+        });
+
+        let mut oauth2 = oauth2(&server, OAuth2Grant::ClientCredentials);
+
+        let err = oauth2
+            .resolve_token(&HttpClient::new().unwrap(), &CallSettings::default(), false)
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            err,
+            format!(
+                "oauth2 token request to {} failed with status 400: invalid_client, unknown client",
+                server.url("/token")
+            )
+        );
+    }
+
+    #[test]
+    fn rejected_token_is_replaced_and_the_call_sent_again() {
+        let server = MockServer::start();
+
+        let token_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/token")
+                .x_www_form_urlencoded_tuple("grant_type", "client_credentials");
+            then.status(200)
+                .json_body(json!({ "access_token": "new token", "expires_in": 3600 }));
+        });
+
+        let rejected_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/protected")
+                .header("authorization", "Bearer revoked token");
+            then.status(401);
+        });
+
+        let accepted_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/protected")
+                .header("authorization", "Bearer new token");
+            then.status(200).body("ok");
+        });
+
+        // a cached token the server has since revoked
+        let oauth2 = oauth2(&server, OAuth2Grant::ClientCredentials);
+
+        OAuth2Settings::set_cached_token(
+            &oauth2.get_cache_key(),
+            &StoredToken {
+                access_token: "revoked token".to_string(),
+                token_type: "Bearer".to_string(),
+                expires_at: Some(unix_now() + 3600),
+                refresh_token: None,
+            },
+            "base",
+        );
+
+        let mut drop_call = DropCall::default(DropId::get_call_drop_id("get", "oauth2", "protected"), "get");
+        drop_call.base_url = server.base_url();
+        drop_call.path = "/protected".to_string();
+        drop_call.settings.auth = Some(CallAuth::OAuth2(oauth2));
+
+        let call_record = Caller {
+            drop_call,
+            client: HttpClient::new().unwrap(),
+        }
+        .call()
+        .unwrap();
+
+        assert_eq!(call_record.status_code.map(|status| status.as_u16()), Some(200));
+        assert_eq!(
+            call_record.attempts.iter().map(|attempt| attempt.status).collect::<Vec<_>>(),
+            vec![Some(401), Some(200)]
+        );
+
+        token_mock.assert_hits(1);
+        rejected_mock.assert_hits(1);
+        accepted_mock.assert_hits(1);
+    }
+
+    #[test]
+    fn authorization_code_tokens_are_stored_as_secrets() {
+        let server = MockServer::start();
+
+        let oauth2 = oauth2(
+            &server,
+            OAuth2Grant::AuthorizationCode {
+                authorize_url: server.url("/authorize"),
+                redirect_port: 0,
+                open_browser: false,
+                access_token_secret: "stored_access_token".to_string(),
+                refresh_token_secret: "stored_refresh_token".to_string(),
+            },
+        );
+
+        let token = StoredToken {
+            access_token: "ac token".to_string(),
+            token_type: "bearer".to_string(),
+            expires_at: None,
+            refresh_token: Some("ac refresh".to_string()),
+        };
+
+        oauth2.set_token_secrets(&token, "oauth2_secrets");
+
+        let secrets = PersisterProvider::get_lock_to_persister()
+            .unwrap()
+            .get_secrets_for_env("oauth2_secrets")
+            .unwrap();
+
+        assert_eq!(secrets.get("stored_access_token"), Some(&hcl::Value::from("ac token")));
+        assert_eq!(secrets.get("stored_refresh_token"), Some(&hcl::Value::from("ac refresh")));
+    }
+}
@@ -0,0 +1,712 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use hcl::{Map, Value};
+use isahc::http::{request::Builder, HeaderMap, Method, Uri};
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::util::redact::Redactor;
+
+use super::{auth::CallAuth, verbose::VerboseDump};
+
+/// unreserved characters are left as they are, and `/`
+/// is kept in paths- see the aws uri encoding rules
+const AWS_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+const AWS_PATH_ENCODE_SET: &AsciiSet = &AWS_ENCODE_SET.remove(b'/');
+
+/// headers proxies and clients change, which aren't signed
+const UNSIGNED_HEADERS: [&str; 5] = ["authorization", "user-agent", "expect", "connection", "x-amzn-trace-id"];
+
+const DEFAULT_HMAC_TEMPLATE: &str = "{method}\n{path}\n{timestamp}\n{body_sha256}";
+
+/// aws signature version 4, for api gateway iam auth and other aws apis
+///
+/// `region` and `service` are required- credentials default to the
+/// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+/// environment variables
+///
+/// an `x-amz-date` header set on the call is used as the signing time
+#[derive(Debug, Clone)]
+pub struct AwsSigV4 {
+    pub region: String,
+    pub service: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+/// hmac signature over a canonical string of the request,
+/// sent in a header
+///
+/// `template` placeholders- `{method}`, `{host}`, `{path}`, `{query}`,
+/// `{timestamp}`, `{body}`, `{body_sha256}` and `{header:[name]}`
+///
+/// `algorithm = "sha256"`, the default, `"sha1"` or `"sha512"`
+/// `encoding = "hex"`, the default, or `"base64"`
+/// `timestamp_format = "unix"`, the default, `"unix_ms"` or `"iso8601"`
+///
+/// a timestamp header set on the call is used as the signing time
+#[derive(Debug, Clone)]
+pub struct HmacSigner {
+    pub secret: String,
+    pub algorithm: String,
+    pub template: String,
+    pub header: String,
+    /// e.g. `sha256=`
+    pub prefix: String,
+    pub encoding: String,
+    pub timestamp_header: Option<String>,
+    pub timestamp_format: String,
+}
+
+impl AwsSigV4 {
+    pub fn from_object(object: &Map<String, Value>) -> Result<AwsSigV4, String> {
+        let get_credential = |key: &str, env_var: &str| -> Result<Option<String>, String> {
+            match CallAuth::get_optional_string(object, key)? {
+                Some(value) => Ok(Some(value)),
+                None => Ok(std::env::var(env_var).ok().filter(|value| !value.is_empty())),
+            }
+        };
+
+        let access_key_id = get_credential("access_key_id", "AWS_ACCESS_KEY_ID")?
+            .ok_or("auth of type \"aws_sigv4\" must set access_key_id, or AWS_ACCESS_KEY_ID must be set")?;
+
+        let secret_access_key = get_credential("secret_access_key", "AWS_SECRET_ACCESS_KEY")?
+            .ok_or("auth of type \"aws_sigv4\" must set secret_access_key, or AWS_SECRET_ACCESS_KEY must be set")?;
+
+        Ok(AwsSigV4 {
+            region: CallAuth::get_string(object, "region")?,
+            service: CallAuth::get_string(object, "service")?,
+            access_key_id,
+            secret_access_key,
+            session_token: get_credential("session_token", "AWS_SESSION_TOKEN")?,
+        })
+    }
+
+    pub fn register_sensitive_values(&self) {
+        Redactor::register(&self.secret_access_key);
+
+        if let Some(session_token) = &self.session_token {
+            Redactor::register(session_token);
+        }
+    }
+
+    /// adds `x-amz-date`, the session token and the
+    /// `authorization` header to the final request
+    pub fn sign(&self, mut request: Builder, body: Option<&[u8]>) -> Result<Builder, String> {
+        let (method, uri, mut headers) = get_request_parts(&request)?;
+
+        let amz_date = match headers.get("x-amz-date").and_then(|amz_date| amz_date.to_str().ok()) {
+            Some(amz_date) => amz_date.to_string(),
+            None => {
+                let amz_date = AwsSigV4::get_amz_date();
+                request = request.header("x-amz-date", &amz_date);
+                headers.insert("x-amz-date", amz_date.parse().map_err(|_| "invalid x-amz-date")?);
+                amz_date
+            }
+        };
+
+        if amz_date.len() < 8 {
+            return Err(format!("invalid x-amz-date {amz_date}, expected e.g. 20150830T123600Z"));
+        }
+
+        if let Some(session_token) = &self.session_token {
+            request = request.header("x-amz-security-token", session_token);
+            headers.insert("x-amz-security-token", session_token.parse().map_err(|_| "invalid session_token")?);
+        }
+
+        let payload_hash = to_hex(&openssl::sha::sha256(body.unwrap_or_default()));
+
+        // s3 requires the payload hash as a header
+        if self.service == "s3" && !headers.contains_key("x-amz-content-sha256") {
+            request = request.header("x-amz-content-sha256", &payload_hash);
+            headers.insert("x-amz-content-sha256", payload_hash.parse().map_err(|_| "invalid payload hash")?);
+        }
+
+        let (canonical_request, signed_headers) =
+            self.get_canonical_request(&method, uri.path(), &uri, &headers, &payload_hash);
+
+        log::debug!("aws_sigv4 canonical request:\n{canonical_request}");
+
+        let scope = self.get_scope(&amz_date);
+        let string_to_sign = AwsSigV4::get_string_to_sign(&amz_date, &scope, &canonical_request);
+        let signature = self.get_signature(&amz_date, &string_to_sign)?;
+
+        VerboseDump::note(&format!("aws_sigv4 signed {signed_headers} for {scope}"));
+
+        Ok(request.header(
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                self.access_key_id
+            ),
+        ))
+    }
+
+    /// the request's lines, and the `;` separated signed header names-
+    /// `path` is the path as sent, apart from the uri so paths the
+    /// uri can't hold can be checked against aws's test suite
+    fn get_canonical_request(
+        &self,
+        method: &Method,
+        path: &str,
+        uri: &Uri,
+        headers: &HeaderMap,
+        payload_hash: &str,
+    ) -> (String, String) {
+        let (canonical_headers, signed_headers) = AwsSigV4::get_canonical_headers(uri, headers);
+
+        let canonical_request = [
+            method.as_str(),
+            &self.get_canonical_path(path),
+            &AwsSigV4::get_canonical_query(uri),
+            &canonical_headers,
+            &signed_headers,
+            payload_hash,
+        ]
+        .join("\n");
+
+        (canonical_request, signed_headers)
+    }
+
+    /// `20150830/us-east-1/service/aws4_request`
+    fn get_scope(&self, amz_date: &str) -> String {
+        format!("{}/{}/{}/aws4_request", &amz_date[..8], self.region, self.service)
+    }
+
+    fn get_string_to_sign(amz_date: &str, scope: &str, canonical_request: &str) -> String {
+        format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            to_hex(&openssl::sha::sha256(canonical_request.as_bytes()))
+        )
+    }
+
+    /// signed with the key derived for the date, region and service
+    fn get_signature(&self, amz_date: &str, string_to_sign: &str) -> Result<String, String> {
+        let mut signing_key = format!("AWS4{}", self.secret_access_key).into_bytes();
+
+        for part in [&amz_date[..8], &self.region, &self.service, "aws4_request"] {
+            signing_key = hmac(MessageDigest::sha256(), &signing_key, part.as_bytes())?;
+        }
+
+        Ok(to_hex(&hmac(MessageDigest::sha256(), &signing_key, string_to_sign.as_bytes())?))
+    }
+
+    /// `20150830T123600Z`
+    fn get_amz_date() -> String {
+        let format = time::format_description::parse("[year][month][day]T[hour][minute][second]Z").unwrap_or_default();
+        OffsetDateTime::now_utc().format(&format).unwrap_or_default()
+    }
+
+    /// `name:value` lines sorted by name, and the `;` separated names-
+    /// repeated headers are joined with `,` and spaces in values collapsed
+    fn get_canonical_headers(uri: &Uri, headers: &HeaderMap) -> (String, String) {
+        let mut canonical: Vec<(String, String)> = Vec::new();
+
+        if !headers.contains_key("host") {
+            if let Some(authority) = uri.authority() {
+                canonical.push(("host".to_string(), authority.to_string()));
+            }
+        }
+
+        for name in headers.keys() {
+            if UNSIGNED_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+
+            let values: Vec<String> = headers
+                .get_all(name)
+                .iter()
+                .map(|value| {
+                    String::from_utf8_lossy(value.as_bytes())
+                        .split_whitespace()
+                        .collect::<Vec<&str>>()
+                        .join(" ")
+                })
+                .collect();
+
+            canonical.push((name.as_str().to_string(), values.join(",")));
+        }
+
+        canonical.sort();
+
+        let canonical_headers: String = canonical
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect();
+
+        let signed_headers = canonical
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<&str>>()
+            .join(";");
+
+        (canonical_headers, signed_headers)
+    }
+
+    /// services other than s3 encode the path twice
+    fn get_canonical_path(&self, path: &str) -> String {
+        let path = if path.is_empty() { "/" } else { path };
+
+        if self.service == "s3" {
+            return path.to_string();
+        }
+
+        utf8_percent_encode(path, AWS_PATH_ENCODE_SET).to_string()
+    }
+
+    /// parameters encoded and sorted by name, then value
+    fn get_canonical_query(uri: &Uri) -> String {
+        let mut params: Vec<(String, String)> = uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (aws_encode(name), aws_encode(value))
+            })
+            .collect();
+
+        params.sort();
+
+        params
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+}
+
+impl HmacSigner {
+    pub fn from_object(object: &Map<String, Value>) -> Result<HmacSigner, String> {
+        let get_or = |key: &str, default: &str| -> Result<String, String> {
+            Ok(CallAuth::get_optional_string(object, key)?.unwrap_or(default.to_string()))
+        };
+
+        let algorithm = get_or("algorithm", "sha256")?.to_lowercase();
+
+        if !["sha1", "sha256", "sha512"].contains(&algorithm.as_str()) {
+            return Err(format!("invalid hmac algorithm {algorithm}, expected \"sha256\", \"sha1\" or \"sha512\""));
+        }
+
+        let encoding = get_or("encoding", "hex")?;
+
+        if !["hex", "base64"].contains(&encoding.as_str()) {
+            return Err(format!("invalid hmac encoding {encoding}, expected \"hex\" or \"base64\""));
+        }
+
+        let timestamp_format = get_or("timestamp_format", "unix")?;
+
+        if !["unix", "unix_ms", "iso8601"].contains(&timestamp_format.as_str()) {
+            return Err(format!(
+                "invalid hmac timestamp_format {timestamp_format}, expected \"unix\", \"unix_ms\" or \"iso8601\""
+            ));
+        }
+
+        // `timestamp_header = null` sends none
+        let timestamp_header = match object.get("timestamp_header") {
+            Some(Value::Null) => None,
+            _ => Some(get_or("timestamp_header", "x-timestamp")?.to_lowercase()),
+        };
+
+        Ok(HmacSigner {
+            secret: CallAuth::get_string(object, "secret")?,
+            algorithm,
+            template: get_or("template", DEFAULT_HMAC_TEMPLATE)?,
+            header: get_or("header", "x-signature")?.to_lowercase(),
+            prefix: get_or("prefix", "")?,
+            encoding,
+            timestamp_header,
+            timestamp_format,
+        })
+    }
+
+    pub fn register_sensitive_values(&self) {
+        Redactor::register(&self.secret);
+    }
+
+    /// adds the timestamp and signature headers to the final request
+    pub fn sign(&self, mut request: Builder, body: Option<&[u8]>) -> Result<Builder, String> {
+        let (method, uri, headers) = get_request_parts(&request)?;
+
+        let call_timestamp = self
+            .timestamp_header
+            .as_ref()
+            .and_then(|timestamp_header| headers.get(timestamp_header))
+            .and_then(|timestamp| timestamp.to_str().ok())
+            .map(str::to_string);
+
+        let timestamp = match call_timestamp {
+            Some(timestamp) => timestamp,
+            None => {
+                let timestamp = self.get_timestamp();
+
+                if let Some(timestamp_header) = &self.timestamp_header {
+                    request = request.header(timestamp_header, &timestamp);
+                }
+
+                timestamp
+            }
+        };
+
+        let canonical = self.get_canonical_string(&method, &uri, &headers, &timestamp, body.unwrap_or_default());
+
+        log::debug!("hmac canonical string:\n{canonical}");
+
+        let digest = match self.algorithm.as_str() {
+            "sha1" => MessageDigest::sha1(),
+            "sha512" => MessageDigest::sha512(),
+            _ => MessageDigest::sha256(),
+        };
+
+        let signature = hmac(digest, self.secret.as_bytes(), canonical.as_bytes())?;
+
+        let signature = match self.encoding.as_str() {
+            "base64" => BASE64_STANDARD.encode(signature),
+            _ => to_hex(&signature),
+        };
+
+        VerboseDump::note(&format!("hmac {} signed in {}", self.algorithm, self.header));
+
+        Ok(request.header(&self.header, format!("{}{signature}", self.prefix)))
+    }
+
+    /// the template with its placeholders replaced
+    fn get_canonical_string(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        timestamp: &str,
+        body: &[u8],
+    ) -> String {
+        let mut canonical = self
+            .template
+            .replace("{method}", method.as_str())
+            .replace("{host}", uri.authority().map(|authority| authority.as_str()).unwrap_or_default())
+            .replace("{path}", if uri.path().is_empty() { "/" } else { uri.path() })
+            .replace("{query}", uri.query().unwrap_or_default())
+            .replace("{timestamp}", timestamp)
+            .replace("{body_sha256}", &to_hex(&openssl::sha::sha256(body)))
+            .replace("{body}", &String::from_utf8_lossy(body));
+
+        while let Some(start) = canonical.find("{header:") {
+            let Some(len) = canonical[start..].find('}') else {
+                break;
+            };
+
+            let name = canonical[start + 8..start + len].to_lowercase();
+            let value = headers
+                .get(&name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+
+            canonical.replace_range(start..start + len + 1, &value);
+        }
+
+        canonical
+    }
+
+    fn get_timestamp(&self) -> String {
+        let now = OffsetDateTime::now_utc();
+
+        match self.timestamp_format.as_str() {
+            "unix_ms" => (now.unix_timestamp_nanos() / 1_000_000).to_string(),
+            "iso8601" => now.replace_nanosecond(0).unwrap_or(now).format(&Rfc3339).unwrap_or_default(),
+            _ => now.unix_timestamp().to_string(),
+        }
+    }
+}
+
+fn get_request_parts(request: &Builder) -> Result<(Method, Uri, HeaderMap), String> {
+    let method = request.method_ref().cloned().unwrap_or_default();
+    let uri = request.uri_ref().cloned().ok_or("request signing requires a valid url")?;
+    let headers = request.headers_ref().cloned().unwrap_or_default();
+
+    Ok((method, uri, headers))
+}
+
+fn hmac(digest: MessageDigest, key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+    let sign = || -> Result<Vec<u8>, openssl::error::ErrorStack> {
+        let key = PKey::hmac(key)?;
+        let mut signer = Signer::new(digest, &key)?;
+        signer.update(data)?;
+        signer.sign_to_vec()
+    };
+
+    sign().map_err(|err| format!("error signing request: {err}"))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// decoded first, so a parameter is encoded once
+fn aws_encode(value: &str) -> String {
+    let decoded = percent_decode_str(value).decode_utf8_lossy();
+    utf8_percent_encode(&decoded, AWS_ENCODE_SET).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use isahc::{
+        http::{HeaderName, HeaderValue},
+        Request,
+    };
+
+    use super::*;
+
+    /// aws signature version 4 test suite- the credentials,
+    /// scope and time every vector is signed with
+    const AMZ_DATE: &str = "20150830T123600Z";
+    const SCOPE: &str = "20150830/us-east-1/service/aws4_request";
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn suite_signer() -> AwsSigV4 {
+        AwsSigV4 {
+            region: "us-east-1".to_string(),
+            service: "service".to_string(),
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        }
+    }
+
+    fn suite_headers(extra: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("example.amazonaws.com"));
+        headers.insert("x-amz-date", HeaderValue::from_static(AMZ_DATE));
+
+        for (name, value) in extra {
+            headers.insert(HeaderName::from_bytes(name.as_bytes()).unwrap(), HeaderValue::from_str(value).unwrap());
+        }
+
+        headers
+    }
+
+    /// checks the canonical request, string to sign and signature
+    /// of a vector- `path` is the request line's path
+    fn assert_vector(
+        method: &str,
+        path: &str,
+        query: &str,
+        extra_headers: &[(&str, &str)],
+        body: &[u8],
+        expected_canonical_request: &str,
+        expected_string_to_sign_hash: &str,
+        expected_signature: &str,
+    ) {
+        let signer = suite_signer();
+        let method: Method = method.parse().unwrap();
+        let uri: Uri = format!("https://example.amazonaws.com/{query}").parse().unwrap();
+        let headers = suite_headers(extra_headers);
+        let payload_hash = to_hex(&openssl::sha::sha256(body));
+
+        let (canonical_request, _) = signer.get_canonical_request(&method, path, &uri, &headers, &payload_hash);
+        assert_eq!(canonical_request, expected_canonical_request);
+
+        let string_to_sign = AwsSigV4::get_string_to_sign(AMZ_DATE, &signer.get_scope(AMZ_DATE), &canonical_request);
+        assert_eq!(
+            string_to_sign,
+            format!("AWS4-HMAC-SHA256\n{AMZ_DATE}\n{SCOPE}\n{expected_string_to_sign_hash}")
+        );
+
+        assert_eq!(signer.get_signature(AMZ_DATE, &string_to_sign).unwrap(), expected_signature);
+    }
+
+    #[test]
+    fn aws_get_vanilla() {
+        assert_vector(
+            "GET",
+            "/",
+            "",
+            &[],
+            b"",
+            &format!("GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:{AMZ_DATE}\n\nhost;x-amz-date\n{EMPTY_SHA256}"),
+            "bb579772317eb040ac9ed261061d46c1f17a8133879d6129b6e1c25292927e63",
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31",
+        );
+    }
+
+    #[test]
+    fn aws_get_vanilla_query_order_key_case() {
+        assert_vector(
+            "GET",
+            "/",
+            "?Param2=value2&Param1=value1",
+            &[],
+            b"",
+            &format!("GET\n/\nParam1=value1&Param2=value2\nhost:example.amazonaws.com\nx-amz-date:{AMZ_DATE}\n\nhost;x-amz-date\n{EMPTY_SHA256}"),
+            "816cd5b414d056048ba4f7c5386d6e0533120fb1fcfa93762cf0fc39e2cf19e0",
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500",
+        );
+    }
+
+    #[test]
+    fn aws_get_vanilla_query_order_key() {
+        assert_vector(
+            "GET",
+            "/",
+            "?Param1=value2&Param1=value1",
+            &[],
+            b"",
+            &format!("GET\n/\nParam1=value1&Param1=value2\nhost:example.amazonaws.com\nx-amz-date:{AMZ_DATE}\n\nhost;x-amz-date\n{EMPTY_SHA256}"),
+            "c968629d70850097a2d8781c9bf7edcb988b04cac14cca9be4acc3595f884606",
+            "5772eed61e12b33fae39ee5e7012498b51d56abc0abb7c60486157bd471c4694",
+        );
+    }
+
+    #[test]
+    fn aws_get_vanilla_query_order_value() {
+        assert_vector(
+            "GET",
+            "/",
+            "?Param1=value2&Param1=Value1",
+            &[],
+            b"",
+            &format!("GET\n/\nParam1=Value1&Param1=value2\nhost:example.amazonaws.com\nx-amz-date:{AMZ_DATE}\n\nhost;x-amz-date\n{EMPTY_SHA256}"),
+            "704b4cef673542d84cdff252633f065e8daeba5f168b77116f8b1bcaf3d38f89",
+            "eedbc4e291e521cf13422ffca22be7d2eb8146eecf653089df300a15b2382bd1",
+        );
+    }
+
+    #[test]
+    fn aws_get_space() {
+        assert_vector(
+            "GET",
+            "/example space/",
+            "",
+            &[],
+            b"",
+            &format!("GET\n/example%20space/\n\nhost:example.amazonaws.com\nx-amz-date:{AMZ_DATE}\n\nhost;x-amz-date\n{EMPTY_SHA256}"),
+            "63ee75631ed7234ae61b5f736dfc7754cdccfedbff4b5128a915706ee9390d86",
+            "652487583200325589f1fba4c7e578f72c47cb61beeca81406b39ddec1366741",
+        );
+    }
+
+    #[test]
+    fn aws_get_utf8() {
+        assert_vector(
+            "GET",
+            "/ሴ",
+            "",
+            &[],
+            b"",
+            &format!("GET\n/%E1%88%B4\n\nhost:example.amazonaws.com\nx-amz-date:{AMZ_DATE}\n\nhost;x-amz-date\n{EMPTY_SHA256}"),
+            "2a0a97d02205e45ce2e994789806b19270cfbbb0921b278ccf58f5249ac42102",
+            "8318018e0b0f223aa2bbf98705b62bb787dc9c0e678f255a891fd03141be5d85",
+        );
+    }
+
+    #[test]
+    fn aws_post_vanilla() {
+        assert_vector(
+            "POST",
+            "/",
+            "",
+            &[],
+            b"",
+            &format!("POST\n/\n\nhost:example.amazonaws.com\nx-amz-date:{AMZ_DATE}\n\nhost;x-amz-date\n{EMPTY_SHA256}"),
+            "553f88c9e4d10fc9e109e2aeb65f030801b70c2f6468faca261d401ae622fc87",
+            "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b",
+        );
+    }
+
+    #[test]
+    fn aws_post_x_www_form_urlencoded() {
+        assert_vector(
+            "POST",
+            "/",
+            "",
+            &[("content-type", "application/x-www-form-urlencoded")],
+            b"Param1=value1",
+            &format!(
+                "POST\n/\n\ncontent-type:application/x-www-form-urlencoded\nhost:example.amazonaws.com\nx-amz-date:{AMZ_DATE}\n\ncontent-type;host;x-amz-date\n9095672bbd1f56dfc5b65f3e153adc8731a4a654192329106275f4c7b24d0b6e"
+            ),
+            "42a5e5bb34198acb3e84da4f085bb7927f2bc277ca766e6d19c73c2154021281",
+            "ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a",
+        );
+    }
+
+    /// the whole request- the call's `x-amz-date` is
+    /// the signing time, and host comes from the url
+    #[test]
+    fn aws_sign_sets_authorization() {
+        let request = Request::get("https://example.amazonaws.com/?Param2=value2&Param1=value1")
+            .header("x-amz-date", AMZ_DATE);
+
+        let signed = suite_signer().sign(request, None).unwrap();
+
+        let authorization = signed.headers_ref().unwrap().get("authorization").unwrap();
+
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature=b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+    }
+
+    fn hmac_signer(template: &str, algorithm: &str, encoding: &str) -> HmacSigner {
+        HmacSigner {
+            secret: "hmac-secret".to_string(),
+            algorithm: algorithm.to_string(),
+            template: template.to_string(),
+            header: "x-signature".to_string(),
+            prefix: "sha256=".to_string(),
+            encoding: encoding.to_string(),
+            timestamp_header: Some("x-timestamp".to_string()),
+            timestamp_format: "unix".to_string(),
+        }
+    }
+
+    #[test]
+    fn hmac_canonical_template() {
+        let signer = hmac_signer(
+            "{method}\n{host}\n{path}\n{query}\n{timestamp}\n{header:X-Api-Key}\n{body_sha256}\n{body}",
+            "sha256",
+            "hex",
+        );
+
+        let uri: Uri = "https://api.example.com/orders?page=2".parse().unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", HeaderValue::from_static("key-1"));
+
+        let canonical = signer.get_canonical_string(
+            &Method::POST,
+            &uri,
+            &headers,
+            "1700000000",
+            br#"{"item":"widget"}"#,
+        );
+
+        assert_eq!(
+            canonical,
+            "POST\napi.example.com\n/orders\npage=2\n1700000000\nkey-1\n8b11ed069a7aba671580a3142cda2d1f5d043dfa4a17ec2c5f033622967ef2b8\n{\"item\":\"widget\"}"
+        );
+    }
+
+    #[test]
+    fn hmac_sign_default_template() {
+        let sign = |algorithm: &str, encoding: &str| {
+            let request = Request::post("https://api.example.com/orders").header("x-timestamp", "1700000000");
+
+            let signed = hmac_signer(DEFAULT_HMAC_TEMPLATE, algorithm, encoding)
+                .sign(request, Some(br#"{"item":"widget"}"#))
+                .unwrap();
+
+            signed.headers_ref().unwrap().get("x-signature").unwrap().to_str().unwrap().to_string()
+        };
+
+        assert_eq!(
+            sign("sha256", "hex"),
+            "sha256=db9f7f6e76e8a64cb171aed3a62c3a03f7426074d8c6d6c3c6ed8de769798c7b"
+        );
+
+        assert_eq!(
+            sign("sha512", "base64"),
+            "sha256=54B8X+bGn6/WzbfUXY4DSu7FrNklZyBoGhhegwDpyEDmEog2a2WkPEsr5Ap4TuBW46QM6pN7gK4GowTzMipdkg=="
+        );
+    }
+}
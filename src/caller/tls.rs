@@ -0,0 +1,464 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use hcl::Value;
+use isahc::{
+    config::{CaCertificate, ClientCertificate, Configurable, PrivateKey, SslOption},
+    HttpClientBuilder,
+};
+
+use crate::{cmd::ctx::CmdContext, constants::CLIENT_TLS};
+
+/// tls settings from the environment's `tls` block,
+/// with paths relative to the dropfile directory
+///
+/// `tls {`
+/// `    ca_file = "certs/internal-ca.pem"`
+/// `    client_cert = "certs/client.pem"`
+/// `    client_key = "certs/client.key.pem"`
+/// `    insecure_skip_verify = false`
+/// `    min_version = "1.2"`
+/// `}`
+#[derive(Debug, Default)]
+pub struct TlsSettings {
+    pub ca_file: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub insecure_skip_verify: bool,
+    pub min_version: Option<String>,
+}
+
+impl TlsSettings {
+    pub fn from_value(value: &Value) -> Result<TlsSettings, anyhow::Error> {
+        let Value::Object(object) = value else {
+            return Err(anyhow!("env.{CLIENT_TLS} must be a block, e.g. tls {{ ca_file = \"ca.pem\" }}"));
+        };
+
+        let mut tls_settings = TlsSettings::default();
+
+        for (key, value) in object {
+            match key.as_str() {
+                "ca_file" => tls_settings.ca_file = Some(TlsSettings::get_path(key, value)?),
+                "client_cert" => tls_settings.client_cert = Some(TlsSettings::get_path(key, value)?),
+                "client_key" => tls_settings.client_key = Some(TlsSettings::get_path(key, value)?),
+                "insecure_skip_verify" => {
+                    tls_settings.insecure_skip_verify = value
+                        .as_bool()
+                        .ok_or_else(|| anyhow!("tls insecure_skip_verify must be true or false"))?;
+                }
+                "min_version" => {
+                    let min_version = match value {
+                        Value::String(min_version) => min_version.to_string(),
+                        Value::Number(min_version) => min_version.to_string(),
+                        _ => String::new(),
+                    };
+
+                    // curl's minimum protocol version isn't exposed
+                    // by the http client, so only the minimum its
+                    // cipher list can enforce is accepted
+                    if min_version != "1.2" {
+                        return Err(anyhow!(
+                            "tls min_version {min_version} is not supported, only \"1.2\" can be required"
+                        ));
+                    }
+
+                    tls_settings.min_version = Some(min_version);
+                }
+                other => return Err(anyhow!("unknown tls setting {other}")),
+            }
+        }
+
+        if tls_settings.client_key.is_some() && tls_settings.client_cert.is_none() {
+            return Err(anyhow!("tls client_key is set without a client_cert"));
+        }
+
+        Ok(tls_settings)
+    }
+
+    fn get_path(key: &str, value: &Value) -> Result<PathBuf, anyhow::Error> {
+        let Value::String(path) = value else {
+            return Err(anyhow!("tls {key} must be a file path"));
+        };
+
+        let path = Path::new(&CmdContext::get_dir()).join(path);
+
+        if !path.is_file() {
+            return Err(anyhow!("tls {key} {} not found", path.display()));
+        }
+
+        Ok(path)
+    }
+
+    pub fn apply(self, mut client_builder: HttpClientBuilder) -> Result<HttpClientBuilder, anyhow::Error> {
+        if let Some(ca_file) = self.ca_file {
+            client_builder = client_builder.ssl_ca_certificate(CaCertificate::file(ca_file));
+        }
+
+        if let Some(client_cert) = self.client_cert {
+            // the key can be in the certificate file
+            let private_key = self
+                .client_key
+                .map(|client_key| PrivateKey::pem_file(client_key, None));
+
+            client_builder =
+                client_builder.ssl_client_certificate(ClientCertificate::pem_file(client_cert, private_key));
+        }
+
+        if self.insecure_skip_verify {
+            log::warn!("tls certificate verification is disabled for this environment");
+
+            client_builder = client_builder.ssl_options(
+                SslOption::DANGER_ACCEPT_INVALID_CERTS | SslOption::DANGER_ACCEPT_INVALID_HOSTS,
+            );
+        }
+
+        if self.min_version.is_some() {
+            // every cipher that also works with tls 1.0 and 1.1
+            // is excluded, so older versions can't complete the
+            // handshake- tls 1.3 is unaffected
+            client_builder = client_builder.ssl_ciphers(["DEFAULT:!SSLv3:!TLSv1"]);
+        }
+
+        Ok(client_builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        path::PathBuf,
+        sync::Arc,
+        time::Duration,
+    };
+
+    use hcl::Value;
+    use isahc::{
+        config::{CaCertificate, Configurable},
+        HttpClient, ReadResponseExt,
+    };
+    use openssl::{
+        asn1::Asn1Time,
+        bn::BigNum,
+        ec::{EcGroup, EcKey},
+        hash::MessageDigest,
+        nid::Nid,
+        pkey::{PKey, Private},
+        ssl::{SslAcceptor, SslAcceptorBuilder, SslMethod, SslOptions, SslVerifyMode, SslVersion},
+        x509::{
+            extension::{BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName},
+            X509NameBuilder, X509,
+        },
+    };
+
+    use super::TlsSettings;
+
+    struct Certs {
+        dir: PathBuf,
+    }
+
+    impl Certs {
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.join(name)
+        }
+    }
+
+    fn new_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    /// self-signed without an issuer
+    fn new_cert(
+        common_name: &str,
+        serial: u32,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+    ) -> X509 {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", common_name).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder
+            .set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap())
+            .unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+
+        match issuer {
+            None => {
+                builder.set_issuer_name(&name).unwrap();
+                builder
+                    .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+                    .unwrap();
+                builder
+                    .append_extension(KeyUsage::new().critical().key_cert_sign().crl_sign().build().unwrap())
+                    .unwrap();
+                builder.sign(key, MessageDigest::sha256()).unwrap();
+            }
+            Some((issuer_cert, issuer_key)) => {
+                builder.set_issuer_name(issuer_cert.subject_name()).unwrap();
+                builder
+                    .append_extension(BasicConstraints::new().build().unwrap())
+                    .unwrap();
+                builder
+                    .append_extension(
+                        ExtendedKeyUsage::new().server_auth().client_auth().build().unwrap(),
+                    )
+                    .unwrap();
+
+                let subject_alt_name = SubjectAlternativeName::new()
+                    .dns("localhost")
+                    .ip("127.0.0.1")
+                    .build(&builder.x509v3_context(Some(issuer_cert), None))
+                    .unwrap();
+                builder.append_extension(subject_alt_name).unwrap();
+
+                builder.sign(issuer_key, MessageDigest::sha256()).unwrap();
+            }
+        }
+
+        builder.build()
+    }
+
+    /// a ca, and a server and client certificate it signed
+    fn write_certs(name: &str) -> Certs {
+        let dir = std::env::temp_dir().join(format!("drop-tls-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let ca_key = new_key();
+        let ca_cert = new_cert("drop test ca", 1, &ca_key, None);
+
+        let server_key = new_key();
+        let server_cert = new_cert("localhost", 2, &server_key, Some((&ca_cert, &ca_key)));
+
+        let client_key = new_key();
+        let client_cert = new_cert("drop client", 3, &client_key, Some((&ca_cert, &ca_key)));
+
+        let write = |file: &str, pem: Vec<u8>| std::fs::write(dir.join(file), pem).unwrap();
+
+        write("ca.pem", ca_cert.to_pem().unwrap());
+        write("server.pem", server_cert.to_pem().unwrap());
+        write("server.key.pem", server_key.private_key_to_pem_pkcs8().unwrap());
+        write("client.pem", client_cert.to_pem().unwrap());
+        write("client.key.pem", client_key.private_key_to_pem_pkcs8().unwrap());
+
+        Certs { dir }
+    }
+
+    fn acceptor(certs: &Certs) -> SslAcceptorBuilder {
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor.set_private_key_file(certs.path("server.key.pem"), openssl::ssl::SslFiletype::PEM).unwrap();
+        acceptor.set_certificate_chain_file(certs.path("server.pem")).unwrap();
+        acceptor
+    }
+
+    fn serve_tls(certs: &Certs, require_client_cert: bool) -> u16 {
+        let mut acceptor = acceptor(certs);
+
+        if require_client_cert {
+            acceptor.set_ca_file(certs.path("ca.pem")).unwrap();
+            acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
+
+        serve(acceptor.build())
+    }
+
+    /// a server that only speaks tls 1.0 and 1.1
+    fn serve_tls_1_1(certs: &Certs) -> u16 {
+        let mut acceptor = acceptor(certs);
+        acceptor.clear_options(SslOptions::NO_TLSV1 | SslOptions::NO_TLSV1_1);
+        acceptor.set_min_proto_version(Some(SslVersion::TLS1)).unwrap();
+        acceptor.set_max_proto_version(Some(SslVersion::TLS1_1)).unwrap();
+        acceptor.set_cipher_list("DEFAULT@SECLEVEL=0").unwrap();
+
+        serve(acceptor.build())
+    }
+
+    /// answers every request with 200 and the client
+    /// certificate's common name, or "anonymous"
+    fn serve(acceptor: SslAcceptor) -> u16 {
+        let acceptor = Arc::new(acceptor);
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                stream.set_read_timeout(Some(Duration::from_secs(5))).ok();
+
+                let Ok(mut stream) = acceptor.accept(stream) else {
+                    continue;
+                };
+
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buf[..read]),
+                    }
+                }
+
+                let client = stream
+                    .ssl()
+                    .peer_certificate()
+                    .and_then(|cert| {
+                        cert.subject_name()
+                            .entries_by_nid(Nid::COMMONNAME)
+                            .next()
+                            .and_then(|entry| entry.data().as_utf8().ok())
+                            .map(|common_name| common_name.to_string())
+                    })
+                    .unwrap_or_else(|| "anonymous".to_string());
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{client}",
+                    client.len()
+                );
+
+                stream.write_all(response.as_bytes()).ok();
+                stream.shutdown().ok();
+            }
+        });
+
+        port
+    }
+
+    fn get(tls_settings: TlsSettings, port: u16) -> Result<String, String> {
+        let client = tls_settings
+            .apply(HttpClient::builder())
+            .and_then(|builder| Ok(builder.build()?))
+            .map_err(|err| err.to_string())?;
+
+        get_with_client(client, port)
+    }
+
+    fn get_with_client(client: HttpClient, port: u16) -> Result<String, String> {
+        let mut response = client
+            .get(format!("https://localhost:{port}/"))
+            .map_err(|err| err.to_string())?;
+
+        response.text().map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn ca_file_trusts_the_server() {
+        let certs = write_certs("ca");
+        let port = serve_tls(&certs, false);
+
+        let untrusted = get(TlsSettings::default(), port);
+        assert!(untrusted.is_err(), "{untrusted:?}");
+
+        let trusted = get(
+            TlsSettings {
+                ca_file: Some(certs.path("ca.pem")),
+                ..Default::default()
+            },
+            port,
+        );
+        assert_eq!(trusted.as_deref(), Ok("anonymous"));
+    }
+
+    #[test]
+    fn client_cert_is_sent_for_mtls() {
+        let certs = write_certs("mtls");
+        let port = serve_tls(&certs, true);
+
+        let without_client_cert = get(
+            TlsSettings {
+                ca_file: Some(certs.path("ca.pem")),
+                ..Default::default()
+            },
+            port,
+        );
+        assert!(without_client_cert.is_err(), "{without_client_cert:?}");
+
+        let with_client_cert = get(
+            TlsSettings {
+                ca_file: Some(certs.path("ca.pem")),
+                client_cert: Some(certs.path("client.pem")),
+                client_key: Some(certs.path("client.key.pem")),
+                ..Default::default()
+            },
+            port,
+        );
+        assert_eq!(with_client_cert.as_deref(), Ok("drop client"));
+    }
+
+    #[test]
+    fn insecure_skip_verify_accepts_an_untrusted_server() {
+        let certs = write_certs("insecure");
+        let port = serve_tls(&certs, false);
+
+        let res = get(
+            TlsSettings {
+                insecure_skip_verify: true,
+                ..Default::default()
+            },
+            port,
+        );
+        assert_eq!(res.as_deref(), Ok("anonymous"));
+    }
+
+    #[test]
+    fn min_version_connects_with_tls_1_2() {
+        let certs = write_certs("min_version");
+        let port = serve_tls(&certs, false);
+
+        let tls_settings = TlsSettings::from_value(&Value::from_iter([("min_version", "1.2")])).unwrap();
+
+        let res = get(
+            TlsSettings {
+                ca_file: Some(certs.path("ca.pem")),
+                ..tls_settings
+            },
+            port,
+        );
+        assert_eq!(res.as_deref(), Ok("anonymous"));
+    }
+
+    #[test]
+    fn min_version_refuses_a_tls_1_1_server() {
+        let certs = write_certs("min_version_1_1");
+        let port = serve_tls_1_1(&certs);
+
+        // openssl's default security level refuses tls 1.1 on
+        // its own, so the server is checked with it lowered
+        let legacy_client = HttpClient::builder()
+            .ssl_ca_certificate(CaCertificate::file(certs.path("ca.pem")))
+            .ssl_ciphers(["DEFAULT@SECLEVEL=0"])
+            .build()
+            .unwrap();
+        assert_eq!(get_with_client(legacy_client, port).as_deref(), Ok("anonymous"));
+
+        let tls_settings = TlsSettings::from_value(&Value::from_iter([("min_version", "1.2")])).unwrap();
+
+        let res = get(
+            TlsSettings {
+                ca_file: Some(certs.path("ca.pem")),
+                ..tls_settings
+            },
+            port,
+        );
+        assert!(res.is_err(), "{res:?}");
+    }
+
+    #[test]
+    fn min_version_rejects_unsupported_versions() {
+        for min_version in ["1.0", "1.1", "1.3"] {
+            let err = TlsSettings::from_value(&Value::from_iter([("min_version", min_version)]))
+                .err()
+                .unwrap()
+                .to_string();
+
+            assert_eq!(err, format!("tls min_version {min_version} is not supported, only \"1.2\" can be required"));
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    fs::File,
+    io::{IsTerminal, Write},
+    sync::Mutex,
+};
+
+use colored::Colorize;
+use isahc::http::{HeaderMap, Method, StatusCode, Uri, Version};
+use lazy_static::lazy_static;
+
+use crate::{
+    cmd::{ctx::CmdContext, output::Output},
+    record::CallTimings,
+    util::{pretty_printed_json, redact::Redactor},
+};
+
+lazy_static! {
+    /// created on the first write, so each
+    /// hit starts a new transcript
+    static ref TRANSCRIPT_FILE: Mutex<Option<File>> = Mutex::new(None);
+}
+
+/// request and response dump for `hit --verbose`
+/// and `hit --transcript [file]`
+///
+/// `> POST http://localhost/signup`
+/// `> content-type: application/json`
+/// `< HTTP/1.1 201 Created`
+///
+/// values are redacted in both the console
+/// dump and the transcript file
+pub struct VerboseDump {}
+
+impl VerboseDump {
+    pub fn is_enabled() -> bool {
+        CmdContext::is_verbose() || CmdContext::get_transcript_path().is_some()
+    }
+
+    pub fn request(method: &Method, uri: &Uri, headers: &HeaderMap, body: Option<&[u8]>) {
+        if !VerboseDump::is_enabled() {
+            return;
+        }
+
+        let body = body.map(String::from_utf8_lossy);
+
+        VerboseDump::dump('>', &format!("{method} {uri}"), headers, body.as_deref());
+    }
+
+    pub fn response(version: Version, status: StatusCode, headers: &HeaderMap, body: Option<&str>) {
+        if !VerboseDump::is_enabled() {
+            return;
+        }
+
+        VerboseDump::dump('<', &format!("{version:?} {status}"), headers, body);
+    }
+
+    /// `* HTTP/1.1 dns 0.02ms connect 0.1ms tls 0ms ttfb 1.2ms total 1.3ms`
+    pub fn timings(timings: Option<&CallTimings>) {
+        if let Some(timings) = timings {
+            VerboseDump::note(&timings.summary());
+        }
+    }
+
+    /// `* attempt 1 of 3 failed (status 503), retrying in 200ms`
+    pub fn note(message: &str) {
+        if !VerboseDump::is_enabled() {
+            return;
+        }
+
+        let line = format!("* {}", Redactor::redact(message));
+
+        if let Some(transcript_path) = CmdContext::get_transcript_path() {
+            VerboseDump::write_transcript(&transcript_path, &line);
+        }
+
+        if !CmdContext::is_verbose() {
+            return;
+        }
+
+        let line = if VerboseDump::is_highlighted() {
+            line.yellow().to_string()
+        } else {
+            line
+        };
+
+        if Output::is_text() {
+            println!("{line}\n");
+        } else {
+            eprintln!("{line}\n");
+        }
+    }
+
+    /// highlight only for a terminal, so a
+    /// redirected dump stays plain text
+    fn is_highlighted() -> bool {
+        if Output::is_text() {
+            std::io::stdout().is_terminal()
+        } else {
+            std::io::stderr().is_terminal()
+        }
+    }
+
+    fn dump(marker: char, start_line: &str, headers: &HeaderMap, body: Option<&str>) {
+        let body = body.filter(|body| !body.is_empty());
+
+        let header_lines: Vec<(String, String)> = headers
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string(),
+                    Redactor::redact(&String::from_utf8_lossy(value.as_bytes())),
+                )
+            })
+            .collect();
+
+        let start_line = Redactor::redact(start_line);
+
+        if let Some(transcript_path) = CmdContext::get_transcript_path() {
+            let mut raw = format!("{marker} {start_line}\n");
+
+            for (key, value) in &header_lines {
+                raw.push_str(&format!("{marker} {key}: {value}\n"));
+            }
+
+            raw.push_str(&format!("{marker}\n"));
+
+            if let Some(body) = body {
+                raw.push_str(&Redactor::redact(body));
+                raw.push('\n');
+            }
+
+            VerboseDump::write_transcript(&transcript_path, &raw);
+        }
+
+        if !CmdContext::is_verbose() {
+            return;
+        }
+
+        let highlight = VerboseDump::is_highlighted();
+
+        let mut printed = if highlight {
+            format!("{marker} {}\n", start_line.bold())
+        } else {
+            format!("{marker} {start_line}\n")
+        };
+
+        for (key, value) in &header_lines {
+            if highlight {
+                printed.push_str(&format!("{marker} {}: {value}\n", key.cyan()));
+            } else {
+                printed.push_str(&format!("{marker} {key}: {value}\n"));
+            }
+        }
+
+        printed.push_str(&format!("{marker}\n"));
+
+        if let Some(body) = body {
+            let body = match pretty_printed_json(body) {
+                Some(pretty) => {
+                    let pretty = Redactor::redact(&pretty);
+
+                    if highlight {
+                        VerboseDump::highlight_json(&pretty)
+                    } else {
+                        pretty
+                    }
+                }
+                None => Redactor::redact(body),
+            };
+
+            printed.push_str(&body);
+            printed.push('\n');
+        }
+
+        // stdout only carries documents in json output
+        if Output::is_text() {
+            println!("{printed}");
+        } else {
+            eprintln!("{printed}");
+        }
+    }
+
+    fn write_transcript(transcript_path: &str, raw: &str) {
+        let Ok(mut transcript_file) = TRANSCRIPT_FILE.lock() else {
+            log::trace!("VerboseDump error obtaining lock to transcript file");
+            return;
+        };
+
+        if transcript_file.is_none() {
+            match File::create(transcript_path) {
+                Ok(file) => *transcript_file = Some(file),
+                Err(err) => {
+                    log::error!("error creating transcript file {transcript_path}: {err}");
+                    return;
+                }
+            }
+        }
+
+        if let Some(file) = transcript_file.as_mut() {
+            if let Err(err) = writeln!(file, "{raw}") {
+                log::error!("error writing transcript file {transcript_path}: {err}");
+            }
+        }
+    }
+
+    /// keys, strings and scalars in pretty printed json
+    fn highlight_json(json: &str) -> String {
+        let mut highlighted = String::new();
+        let mut chars = json.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    let mut token = String::from('"');
+                    let mut escaped = false;
+
+                    for c in chars.by_ref() {
+                        token.push(c);
+
+                        if escaped {
+                            escaped = false;
+                        } else if c == '\\' {
+                            escaped = true;
+                        } else if c == '"' {
+                            break;
+                        }
+                    }
+
+                    // keys are followed by the colon
+                    if chars.peek() == Some(&':') {
+                        highlighted.push_str(&token.blue().to_string());
+                    } else {
+                        highlighted.push_str(&token.green().to_string());
+                    }
+                }
+                '-' | '0'..='9' | 't' | 'f' | 'n' => {
+                    let mut token = String::from(c);
+
+                    while let Some(next) = chars.peek() {
+                        if next.is_ascii_alphanumeric() || matches!(next, '.' | '-' | '+') {
+                            token.push(*next);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+
+                    highlighted.push_str(&token.yellow().to_string());
+                }
+                _ => highlighted.push(c),
+            }
+        }
+
+        highlighted
+    }
+}
@@ -22,6 +22,21 @@ pub struct Cli {
     #[arg(short, long, default_value=".")]
     pub dir: String,
 
+    /// output format- json and jsonl print one document per
+    /// call to stdout, with colours and decorations off
+    #[arg(short, long, value_enum, default_value="text")]
+    pub output: OutputFormat,
+
+    /// never prompt- confirmations are accepted and drop selection
+    /// fails. set automatically when stdin is not a terminal
+    #[arg(short = 'y', long, visible_alias = "yes")]
+    pub non_interactive: bool,
+
+    /// print secret values in output, logs and call
+    /// records instead of masking them- local debugging only
+    #[arg(long)]
+    pub unsafe_show_secrets: bool,
+
     // inputs
     // #[arg(short = 'i', long, value_parser = parse_key_val::<String, String>)]
     // pub input: Option<Vec<(String, String)>>,
@@ -34,6 +49,19 @@ pub enum LogLevelInput {
     Trace
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    /// pretty printed json document per call
+    Json,
+    /// json document per call, one per line
+    Jsonl,
+    /// no documents or decorations- for drop
+    /// embedded as a library
+    #[value(skip)]
+    Silent,
+}
+
 #[derive(Subcommand, Debug, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum Command {
@@ -52,6 +80,14 @@ pub enum Command {
     hit {
         /// either a module or the id of the call block to run
         drop_id: String,
+
+        /// print the full request and response, like `curl -v`
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// write the request and response transcript to a file
+        #[arg(long)]
+        transcript: Option<String>,
     },
 
     ///
@@ -65,18 +101,85 @@ pub enum Command {
     ///
     /// get and set secrets for environment
     ///
+    /// `drop secret check` lists declared secrets that are
+    /// missing or using their default, and stored secrets
+    /// that are never referenced
+    ///
+    /// `drop secret import --from .env` and `drop secret export --format json`
+    /// move secrets between files and the local store, `drop secret copy
+    /// --from-env staging --to-env dev` between environments
+    ///
+    /// `drop secret encrypt|decrypt|edit` manage the shared
+    /// `secrets.[env].enc.json` file, encrypted to the age
+    /// recipients in the environment's secret_providers block
+    ///
     secret {
-        /// get or set
+        /// get, set, del, check, import, export, copy, encrypt, decrypt or edit
         action: String,
 
-        /// key for secret
+        /// key for secret, or the plaintext file to encrypt from or decrypt to
         key: Option<String>,
 
         /// value for secret
         value: Option<String>,
+
+        /// print secret values instead of masking them
+        #[arg(long)]
+        reveal: bool,
+
+        /// file to import, e.g. `.env` or `secrets.json`
+        #[arg(long)]
+        from: Option<String>,
+
+        /// format to import or export, inferred from the import file's extension
+        #[arg(long, value_enum)]
+        format: Option<SecretFileFormat>,
+
+        /// environment to copy secrets from
+        #[arg(long)]
+        from_env: Option<String>,
+
+        /// environment to copy secrets to, the selected environment unless set
+        #[arg(long)]
+        to_env: Option<String>,
+    },
+
+    ///
+    /// list and clear the environment's cookie jar
+    ///
+    /// cookies are stored for calls with `cookies = true` on the
+    /// call, module or environment, and sent with later calls
+    ///
+    cookies {
+        /// list or clear
+        action: String,
+
+        /// only list or clear the cookies for this domain
+        domain: Option<String>,
+
+        /// print cookie values instead of masking them
+        #[arg(long)]
+        reveal: bool,
     },
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum SecretFileFormat {
+    Dotenv,
+    Json,
+    Yaml,
+}
+
+impl SecretFileFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SecretFileFormat::Dotenv => "dotenv",
+            SecretFileFormat::Json => "json",
+            SecretFileFormat::Yaml => "yaml",
+        }
+    }
+}
+
 /// Parse a single key-value pair
 /// `<https://github.com/clap-rs/clap/blob/master/examples/typed-derive.rs />`
 fn parse_key_val<T, U>(s: &str) -> Result<(T, U), Box<dyn Error + Send + Sync + 'static>>
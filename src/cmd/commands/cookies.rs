@@ -0,0 +1,140 @@
+use std::{
+    process,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use cli_table::{print_stdout, Cell, CellStruct, Table};
+use colored::Colorize;
+
+use crate::{
+    cmd::{ctx::CmdContext, output::Output, DropCommand},
+    persist::{PersisterProvider, StoredCookie},
+    prompt_println, text_println,
+};
+
+/// lists and clears the environment's cookie jar
+#[derive(Debug)]
+pub struct CookiesCommand {
+    pub action: String,
+    pub domain: Option<String>,
+    pub reveal: bool,
+}
+
+impl DropCommand for CookiesCommand {
+    fn announce(&self) {
+        // todo!()
+    }
+
+    fn run(&mut self) -> std::pin::Pin<Box<dyn futures::Future<Output = ()>>> {
+        let env = &CmdContext::get_env();
+
+        match self.action.as_str() {
+            "list" => CookiesCommand::list_cookies(env, self.domain.as_deref(), self.reveal),
+            "clear" => CookiesCommand::clear_cookies(env, self.domain.as_deref()),
+            action => {
+                prompt_println!("invalid action passed to cookies: {action}. Only valid actions are list and clear");
+                process::exit(1)
+            }
+        }
+
+        Box::pin(async {})
+    }
+}
+
+impl CookiesCommand {
+    /// values are masked unless `--reveal` is passed
+    fn list_cookies(env: &str, domain: Option<&str>, reveal: bool) {
+        let mut persister = PersisterProvider::get_lock_to_persister().unwrap();
+
+        let cookies = match persister.get_cookies_for_env(env) {
+            Ok(cookies) => cookies,
+            Err(err) => {
+                log::error!("error reading cookies for env {env}: {err}");
+                process::exit(1)
+            }
+        };
+
+        let domain = domain.map(|domain| domain.trim_start_matches('.').to_lowercase());
+
+        let cookies: Vec<StoredCookie> = cookies
+            .into_iter()
+            .filter(|cookie| domain.as_ref().is_none_or(|domain| &cookie.domain == domain))
+            .map(|cookie| if reveal { cookie } else { cookie.masked() })
+            .collect();
+
+        if !Output::is_text() {
+            Output::emit_document(&serde_json::json!({ "env": env, "cookies": cookies }));
+            return;
+        }
+
+        if cookies.is_empty() {
+            println!("No cookies for env: {}", env.yellow());
+            return;
+        }
+
+        println!("Cookies for env: {}\n", env.yellow());
+
+        let table: Vec<Vec<CellStruct>> = cookies
+            .iter()
+            .map(|cookie| {
+                let expires = match cookie.expires_at {
+                    Some(expires_at) => httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(expires_at.max(0) as u64)),
+                    None => "never".to_string(),
+                };
+
+                let mut flags = Vec::<&str>::new();
+
+                if cookie.secure {
+                    flags.push("secure");
+                }
+
+                if cookie.http_only {
+                    flags.push("http_only");
+                }
+
+                if !cookie.host_only {
+                    flags.push("subdomains");
+                }
+
+                vec![
+                    cookie.domain.clone().cell(),
+                    cookie.path.clone().cell(),
+                    cookie.name.clone().cell(),
+                    cookie.value.clone().cell(),
+                    expires.cell(),
+                    flags.join(", ").cell(),
+                ]
+            })
+            .collect();
+
+        assert!(print_stdout(table.table()).is_ok());
+    }
+
+    fn clear_cookies(env: &str, domain: Option<&str>) {
+        let mut persister = PersisterProvider::get_lock_to_persister().unwrap();
+
+        let cleared = match persister.clear_cookies_in_env(env, domain) {
+            Ok(cleared) => cleared,
+            Err(err) => {
+                log::error!("error clearing cookies for env {env}: {err}");
+                process::exit(1)
+            }
+        };
+
+        match domain {
+            Some(domain) => text_println!(
+                "cookies for {} in environment {} cleared: {cleared}",
+                domain.yellow(),
+                env.yellow()
+            ),
+            None => text_println!("cookies in environment {} cleared: {cleared}", env.yellow()),
+        }
+
+        Output::emit_document(&serde_json::json!({
+            "action": "clear",
+            "env": env,
+            "domain": domain,
+            "cleared": cleared,
+        }));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
     call::DropCall,
-    cmd::{commands::hit::HitCommand, ctx::CmdContext, dropdown::DropDown, DropCommand},
+    cmd::{
+        commands::hit::HitCommand, ctx::CmdContext, dropdown::DropDown, output::Output,
+        DropCommand,
+    },
     interpreter::evaluate::Evaluator,
     parser::{
         drop_block::DropBlock,
@@ -8,6 +11,9 @@ use crate::{
         hcl_block::HclBlock,
     },
     runner::{drop_run::DropRun, run_pool::RunPool},
+    record::CallRecord,
+    text_println,
+    util::{exit, redact::Redactor},
 };
 use colored::Colorize;
 use futures::Future;
@@ -22,7 +28,7 @@ pub struct GiveCommand {
 
 impl DropCommand for GiveCommand {
     fn announce(&self) {
-        println!(
+        text_println!(
             "giving {} in environment {}\n",
             self.input_drop_id_string.yellow(),
             CmdContext::get_env().yellow()
@@ -46,20 +52,43 @@ impl DropCommand for GiveCommand {
             CallType::Hit => hit_command.run_call(),
             CallType::Run => hit_command.run_run(),
             CallType::Chain => {
-                println!("\ngive chain unsupported\n");
-                std::process::exit(0);
+                if Output::is_text() {
+                    println!("\ngive chain unsupported\n");
+                } else {
+                    log::error!("give chain unsupported");
+                }
+                exit::exit(0);
             },
         };
 
         // hit and run return 1 drop_run
-        if drop_run.len() == 1 {
+        if drop_run.len() == 1 && !Output::is_text() {
+            let drop_call = drop_run[0].get_drop_call(IndexMap::new());
+
+            let outputs: Vec<String> = drop_call
+                .outputs
+                .iter()
+                .flatten()
+                .map(HclBlock::traversal_to_string)
+                .collect();
+
+            Output::emit_document(&serde_json::json!({
+                "drop_id": drop_id,
+                "env": CmdContext::get_env(),
+                "method": drop_call.method.to_string(),
+                "url": drop_call.full_url(),
+                "headers": CallRecord::headers_to_map(&drop_call.headers),
+                "body": drop_call.body,
+                "outputs": outputs,
+            }));
+        } else if drop_run.len() == 1 {
             let evaluated_block = drop_run[0].evaluate_call_block_with_blank_inputs();
 
             let serialized_body_res = hcl::to_string(&evaluated_block);
 
             match serialized_body_res {
                 Ok(body) => {
-                    println!("{}", body.yellow());
+                    println!("{}", Redactor::redact(&body).yellow());
                 }
                 Err(err) => {
                     panic!("error printing hcl for {} Err-- {:?}", drop_id, err)
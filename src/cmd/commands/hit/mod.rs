@@ -1,5 +1,6 @@
-use std::pin::Pin;
+use std::{pin::Pin, sync::Arc};
 
+use crate::util::exit;
 use crate::{
     call::DropCall,
     cmd::{ctx::CmdContext, dropdown::DropDown, DropCommand},
@@ -11,7 +12,9 @@ use crate::{
         types::{DropBlockType, DropResourceType},
     },
     runner::{drop_run::DropRun, run_pool::RunPool},
+    text_println,
 };
+use anyhow::anyhow;
 use colored::Colorize;
 use futures::Future;
 use hcl::eval::{Context, Evaluate};
@@ -28,7 +31,7 @@ pub struct HitCommand {
 
 impl DropCommand for HitCommand {
     fn announce(&self) {
-        println!(
+        text_println!(
             "hitting {} in environment {}\n",
             self.input_drop_id_string.yellow(),
             CmdContext::get_env().yellow()
@@ -57,40 +60,39 @@ impl HitCommand {
 
     #[log_attributes::log(debug, "{fn} {self:?}")]
     pub fn get_env_scope(&self) -> Context<'static> {
-        let mut env_var_scope_res =
-            Evaluator::get_module_dependencies_for_eval(&self.input_drop_id_string);
-
-        if env_var_scope_res.is_err() {
-            log::trace!("{env_var_scope_res:?}");
-            log::error!(
-                "error running call {} {}",
-                self.input_drop_id_string,
-                env_var_scope_res.unwrap_err()
-            );
-            std::process::exit(1);
-        }
-        let env_var_scope = env_var_scope_res.unwrap();
+        HitCommand::or_exit(self.try_get_env_scope())
+    }
 
-        env_var_scope
+    /// like `get_env_scope`, returning the error
+    /// to embedded callers instead of exiting
+    pub fn try_get_env_scope(&self) -> Result<Context<'static>, anyhow::Error> {
+        Evaluator::get_module_dependencies_for_eval(&self.input_drop_id_string).map_err(|err| {
+            log::trace!("{err:?}");
+            anyhow!("error running call {} {err}", self.input_drop_id_string)
+        })
     }
 
     #[log_attributes::log(debug, "{fn} {self:?}")]
-    pub fn get_drop_block_or_exit(&self, block_drop_id: &str, drop_resource_type: DropResourceType) -> &'static DropBlock {
-        let drop_container_res =
-            Evaluator::get_selected_container(block_drop_id, drop_resource_type);
-
-        if drop_container_res.is_err() {
-            log::trace!("{drop_container_res:?}");
-            log::error!(
-                "error running block {}: {}",
-                self.input_drop_id_string,
-                drop_container_res.unwrap_err()
-            );
-            std::process::exit(1);
-        }
+    pub fn get_drop_block_or_exit(&self, block_drop_id: &str, drop_resource_type: DropResourceType) -> Arc<DropBlock> {
+        HitCommand::or_exit(self.try_get_drop_block(block_drop_id, drop_resource_type))
+    }
 
-        let call_drop_container = drop_container_res.unwrap();
+    pub fn try_get_drop_block(
+        &self,
+        block_drop_id: &str,
+        drop_resource_type: DropResourceType,
+    ) -> Result<Arc<DropBlock>, anyhow::Error> {
+        Evaluator::get_selected_container(block_drop_id, drop_resource_type).map_err(|err| {
+            log::trace!("{err:?}");
+            anyhow!("error running block {}: {err}", self.input_drop_id_string)
+        })
+    }
 
-        call_drop_container
+    /// the cli logs the error and exits
+    pub fn or_exit<T>(res: Result<T, anyhow::Error>) -> T {
+        res.unwrap_or_else(|err| {
+            log::error!("{err}");
+            exit::exit(1)
+        })
     }
 }
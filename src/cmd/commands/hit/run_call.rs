@@ -20,10 +20,14 @@ use super::HitCommand;
 impl HitCommand {
     #[log_attributes::log(debug, "{fn} {self:?}")]
     pub fn run_call(&self) -> Vec<DropRun> {
+        HitCommand::or_exit(self.try_run_call())
+    }
+
+    pub fn try_run_call(&self) -> Result<Vec<DropRun>, anyhow::Error> {
 
-        let mut env_var_scope = self.get_env_scope();
+        let mut env_var_scope = self.try_get_env_scope()?;
 
-        let call_drop_container = self.get_drop_block_or_exit(&self.input_drop_id_string, DropResourceType::Call);
+        let call_drop_container = self.try_get_drop_block(&self.input_drop_id_string, DropResourceType::Call)?;
 
         let mut input_index_map = IndexMap::<String, hcl::Value>::new();
 
@@ -38,12 +42,12 @@ impl HitCommand {
 
         log::debug!("run_call input_index_map {input_index_map:?}");
 
-        vec![DropRun {
+        Ok(vec![DropRun {
             call_drop_container,
             input_index_map,
             env_var_scope,
             depends_on: None,
             call_block_overwrites: None,
-        }]
+        }])
     }
 }
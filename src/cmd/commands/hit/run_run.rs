@@ -18,12 +18,16 @@ impl HitCommand {
     
     #[log_attributes::log(debug, "{fn} {self:?}")]
     pub fn run_run(&self) -> Vec<DropRun> {
+        HitCommand::or_exit(self.try_run_run())
+    }
+
+    pub fn try_run_run(&self) -> Result<Vec<DropRun>, anyhow::Error> {
 
-        let mut env_var_scope = self.get_env_scope();
+        let mut env_var_scope = self.try_get_env_scope()?;
 
         // get the run container for the drop id
 
-        let run_drop_container = self.get_drop_block_or_exit(&self.input_drop_id_string, DropResourceType::Run);
+        let run_drop_container = self.try_get_drop_block(&self.input_drop_id_string, DropResourceType::Run)?;
 
         log::debug!("HitCommand run_run run_container {run_drop_container:?}");
 
@@ -47,16 +51,16 @@ impl HitCommand {
             &mut env_var_scope,
         );
 
-        let call_drop_container = self.get_drop_block_or_exit(call_drop_id, DropResourceType::Call);
+        let call_drop_container = self.try_get_drop_block(call_drop_id, DropResourceType::Call)?;
 
         let call_block_overwrites = CallBlockOverWrites::new().from_run_block(evaluated_run_hcl_block);
 
-        vec![DropRun {
+        Ok(vec![DropRun {
             call_drop_container,
             call_block_overwrites: Some(call_block_overwrites),
             input_index_map,
             env_var_scope,
             depends_on: None,
-        }]
+        }])
     }
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
 pub mod hit;
 pub mod give;
-pub mod secret;
\ No newline at end of file
+pub mod secret;
+pub mod cookies;
\ No newline at end of file
@@ -1,11 +1,24 @@
 use crate::{
-    cmd::{ctx::CmdContext, DropCommand},
+    cmd::{cli::SecretFileFormat, ctx::CmdContext, output::Output, DropCommand},
     parser::GlobalDropConfigProvider,
-    persist::{Persister, PersisterProvider},
-    s,
+    persist::{Persister, PersisterProvider, Secret},
+    prompt_println, s,
+    secret_provider::{
+        encrypted_file::EncryptedSecretsFile, file_provider::FileSecretProvider,
+        SecretProviderManager,
+    },
+    text_println,
 };
+use cli_table::{print_stdout, Cell, CellStruct, Table};
 use colored::Colorize;
-use std::{io, process};
+use hcl::Value;
+use indexmap::IndexMap;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::Path,
+    process,
+};
 
 /// manages getting setting secrets in environment
 #[derive(Debug)]
@@ -13,6 +26,11 @@ pub struct SecretCommand {
     pub action: String,
     pub key: Option<String>,
     pub value: Option<String>,
+    pub reveal: bool,
+    pub from: Option<String>,
+    pub format: Option<SecretFileFormat>,
+    pub from_env: Option<String>,
+    pub to_env: Option<String>,
 }
 
 impl DropCommand for SecretCommand {
@@ -21,7 +39,7 @@ impl DropCommand for SecretCommand {
     }
 
     fn run(&mut self) -> std::pin::Pin<Box<dyn futures::Future<Output = ()>>> {
-        let env = CmdContext::get_env();
+        let env = &CmdContext::get_env();
 
         let mut persister = PersisterProvider::get_lock_to_persister().unwrap();
 
@@ -31,9 +49,12 @@ impl DropCommand for SecretCommand {
 
         match action {
             "del" => match key {
-                Some(key) => persister.delete_secret_in_env(key, env),
+                Some(key) => {
+                    persister.delete_secret_in_env(key, env);
+                    SecretCommand::emit_action_document("del", env, &[key.as_str()]);
+                }
                 None => {
-                    println!("must set key to delete");
+                    prompt_println!("must set key to delete");
                     std::process::exit(1)
                 }
             },
@@ -44,37 +65,47 @@ impl DropCommand for SecretCommand {
                 );
                 let key = key.as_ref().unwrap();
                 let value = value.as_ref().unwrap();
-                println!("Please confirm setting secret:\n\nenvironment {}\nkey {}\nvalue {}\n\n'Y' or 'y' to proceed, any other key to cancel.", env.yellow(), key.yellow(), value.yellow());
-                let stdin = io::stdin();
-                let input = &mut String::new();
-                loop {
-                    input.clear();
-                    let _ = stdin.read_line(input);
-                    let input_val = (*input).to_string();
-                    let matcher = |answer: &str| match answer {
-                        "y" | "Y" => true,
-                        _ => {
-                            println!("\ncancelled");
-                            process::exit(0)
-                        }
-                    };
-                    let is_confirmed = matcher(input_val.trim());
-                    if is_confirmed {
-                        break;
-                    }
-                }
-                persister.insert_secret_into_env(key, value, env, false);
+                prompt_println!("Please confirm setting secret:\n\nenvironment {}\nkey {}\n", env.yellow(), key.yellow());
+                SecretCommand::confirm();
+                persister.insert_secret_into_env(key, value, env, false, "cli");
+                SecretCommand::emit_action_document("set", env, &[key.as_str()]);
+            }
+            "import" => SecretCommand::import_secrets(
+                env,
+                self.from.as_deref(),
+                self.format,
+                &mut **persister,
+            ),
+            "export" => SecretCommand::export_secrets(
+                env,
+                self.format.unwrap_or(SecretFileFormat::Dotenv),
+                self.reveal,
+                &mut **persister,
+            ),
+            "copy" => SecretCommand::copy_secrets(
+                self.from_env.as_deref(),
+                self.to_env.as_deref().unwrap_or(env),
+                key.as_deref(),
+                &mut **persister,
+            ),
+            "check" => {
+                // providers take their own lock on the persister
+                drop(persister);
+                SecretCommand::check_secrets(env);
             }
+            "encrypt" => SecretCommand::encrypt_secrets_file(env, key.as_deref()),
+            "decrypt" => SecretCommand::decrypt_secrets_file(env, key.as_deref()),
+            "edit" => SecretCommand::edit_secrets_file(env),
             "get" => {
                 if env.is_empty() {
-                    persister.get_all_secrets();
+                    SecretCommand::print_all_secrets(&mut **persister, self.reveal);
                 } else {
-                    persister.get_secrets_for_env(env, true);
+                    SecretCommand::print_secrets_for_env(env, &mut **persister, self.reveal);
                 }
             }
             _ => {
                 panic!(
-                    "invalid action passed to secret: {action}. Only valid actions are get and set"
+                    "invalid action passed to secret: {action}. Only valid actions are get, set, del, check, import, export, copy, encrypt, decrypt and edit"
                 )
             }
         }
@@ -82,3 +113,606 @@ impl DropCommand for SecretCommand {
         Box::pin(async {})
     }
 }
+
+impl SecretCommand {
+    /// values are masked unless `--reveal` is passed
+    fn print_secrets_for_env(env: &str, persister: &mut dyn Persister, reveal: bool) {
+        let stored: Vec<Secret> = SecretCommand::get_stored_secrets(persister)
+            .into_iter()
+            .filter(|secret| secret.env() == env)
+            .collect();
+
+        let secrets: Vec<Secret> = if reveal {
+            let decrypted = SecretCommand::get_decrypted_secrets(env, persister);
+
+            stored
+                .iter()
+                .map(|secret| {
+                    secret.with_value(
+                        decrypted
+                            .get(secret.key())
+                            .and_then(|value| value.as_str())
+                            .unwrap_or_default(),
+                    )
+                })
+                .collect()
+        } else {
+            stored.iter().map(Secret::masked).collect()
+        };
+
+        if !Output::is_text() {
+            Output::emit_document(&serde_json::json!({ "env": env, "secrets": secrets }));
+            return;
+        }
+
+        if secrets.is_empty() {
+            println!("No secrets for env: {}", env.yellow());
+        } else {
+            println!("Secrets for env: {}", env.yellow());
+
+            for secret in &secrets {
+                println!("{secret:?}");
+            }
+        }
+    }
+
+    fn print_all_secrets(persister: &mut dyn Persister, reveal: bool) {
+        let stored = SecretCommand::get_stored_secrets(persister);
+
+        if !Output::is_text() && !reveal {
+            let masked: Vec<Secret> = stored.iter().map(Secret::masked).collect();
+            Output::emit_document(&serde_json::json!({ "secrets": masked }));
+            return;
+        }
+
+        if stored.is_empty() {
+            text_println!("No secrets set");
+            return;
+        }
+
+        text_println!("Secrets: {}", "all secrets".yellow());
+
+        if reveal {
+            let mut envs: Vec<&String> = stored.iter().map(Secret::env).collect();
+            envs.sort();
+            envs.dedup();
+
+            for env in envs {
+                SecretCommand::print_secrets_for_env(env, persister, true);
+            }
+        } else {
+            for secret in &stored {
+                println!("{:?}", secret.masked());
+            }
+        }
+    }
+
+    /// result of set, del, import and copy in json output
+    fn emit_action_document(action: &str, env: &str, keys: &[&str]) {
+        Output::emit_document(&serde_json::json!({
+            "action": action,
+            "env": env,
+            "keys": keys,
+        }));
+    }
+
+    fn get_decrypted_secrets(env: &str, persister: &mut dyn Persister) -> IndexMap<String, Value> {
+        match persister.get_secrets_for_env(env) {
+            Ok(secrets) => secrets,
+            Err(err) => {
+                log::error!("error reading secrets for env {env}: {err}");
+                process::exit(1)
+            }
+        }
+    }
+
+    /// exits unless the user enters 'y'- confirmed
+    /// without prompting when not interactive
+    fn confirm() {
+        if !CmdContext::is_interactive() {
+            prompt_println!("confirmed (non-interactive)\n");
+            return;
+        }
+
+        prompt_println!("'Y' or 'y' to proceed, any other key to cancel.");
+
+        let input = &mut String::new();
+        let _ = io::stdin().read_line(input);
+
+        match input.trim() {
+            "y" | "Y" => {}
+            _ => {
+                prompt_println!("\ncancelled");
+                process::exit(0)
+            }
+        }
+    }
+
+    /// import every key in a dotenv, json or yaml file into the
+    /// local secret store, overwriting existing keys
+    fn import_secrets(
+        env: &str,
+        from: Option<&str>,
+        format: Option<SecretFileFormat>,
+        persister: &mut dyn Persister,
+    ) {
+        let Some(from) = from else {
+            prompt_println!("must set --from file to import");
+            process::exit(1)
+        };
+
+        let format = FileSecretProvider::get_format(from, format.map(SecretFileFormat::as_str));
+
+        let imported = fs::read_to_string(from)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| FileSecretProvider::parse_contents(&format, &contents));
+
+        let imported = match imported {
+            Ok(imported) => imported,
+            Err(err) => {
+                log::error!("error importing secrets from {from}: {err}");
+                process::exit(1)
+            }
+        };
+
+        if imported.is_empty() {
+            println!("No secrets in {from}");
+            return;
+        }
+
+        let mut values = Vec::<(String, String)>::new();
+
+        for (key, value) in &imported {
+            match value.as_str() {
+                Some(value) => values.push((key.to_string(), value.to_string())),
+                None => {
+                    log::error!("error importing secrets from {from}: value for {key} is not a string");
+                    process::exit(1)
+                }
+            }
+        }
+
+        let existing = SecretCommand::get_decrypted_secrets(env, persister);
+
+        prompt_println!("Please confirm importing secrets from {from}:\n\nenvironment {}", env.yellow());
+
+        for (key, _) in &values {
+            let overwrites = if existing.contains_key(key) { " (overwrites)" } else { "" };
+            prompt_println!("key {}{overwrites}", key.yellow());
+        }
+
+        prompt_println!();
+
+        SecretCommand::confirm();
+
+        for (key, value) in &values {
+            persister.insert_secret_into_env(key, value, env, true, &format!("import {from}"));
+        }
+
+        let keys: Vec<&str> = values.iter().map(|(key, _)| key.as_str()).collect();
+        SecretCommand::emit_action_document("import", env, &keys);
+    }
+
+    /// print the env's stored secrets as a dotenv, json or
+    /// yaml file- values are masked unless `--reveal` is passed
+    fn export_secrets(
+        env: &str,
+        format: SecretFileFormat,
+        reveal: bool,
+        persister: &mut dyn Persister,
+    ) {
+        let secrets = SecretCommand::get_decrypted_secrets(env, persister);
+
+        let exported: IndexMap<String, String> = secrets
+            .iter()
+            .map(|(key, value)| {
+                let value = if reveal {
+                    value.as_str().unwrap_or_default().to_string()
+                } else {
+                    "********".to_string()
+                };
+
+                (key.to_string(), value)
+            })
+            .collect();
+
+        // `--output json` and `jsonl` emit the secrets as a
+        // document, whatever the file format
+        if !Output::is_text() {
+            Output::emit_document(&serde_json::json!({ "env": env, "secrets": exported }));
+            return;
+        }
+
+        match format {
+            SecretFileFormat::Dotenv => {
+                for (key, value) in exported {
+                    let needs_quotes = value.is_empty()
+                        || value.trim() != value
+                        || value.contains(|c: char| c.is_whitespace() || c == '#' || c == '"' || c == '\'');
+
+                    if needs_quotes && !value.contains('"') {
+                        println!("{key}=\"{value}\"");
+                    } else if needs_quotes {
+                        println!("{key}='{value}'");
+                    } else {
+                        println!("{key}={value}");
+                    }
+                }
+            }
+            SecretFileFormat::Json => println!("{}", serde_json::to_string_pretty(&exported).unwrap()),
+            SecretFileFormat::Yaml => print!("{}", serde_yaml::to_string(&exported).unwrap()),
+        }
+    }
+
+    /// copy secrets between environments, or a
+    /// single secret if a key is passed
+    fn copy_secrets(
+        from_env: Option<&str>,
+        to_env: &str,
+        key: Option<&str>,
+        persister: &mut dyn Persister,
+    ) {
+        let Some(from_env) = from_env else {
+            prompt_println!("must set --from-env to copy");
+            process::exit(1)
+        };
+
+        if from_env == to_env {
+            prompt_println!("--from-env and --to-env must be different environments");
+            process::exit(1)
+        }
+
+        let source = SecretCommand::get_decrypted_secrets(from_env, persister);
+
+        let copied: Vec<(&String, &str)> = source
+            .iter()
+            .filter(|(source_key, _)| key.is_none_or(|key| key == source_key.as_str()))
+            .map(|(source_key, value)| (source_key, value.as_str().unwrap_or_default()))
+            .collect();
+
+        if copied.is_empty() {
+            prompt_println!("No secrets to copy from env: {}", from_env.yellow());
+            process::exit(1)
+        }
+
+        let existing = SecretCommand::get_decrypted_secrets(to_env, persister);
+
+        prompt_println!(
+            "Please confirm copying secrets from environment {} to environment {}:\n",
+            from_env.yellow(),
+            to_env.yellow()
+        );
+
+        for (key, _) in &copied {
+            let overwrites = if existing.contains_key(*key) { " (overwrites)" } else { "" };
+            prompt_println!("key {}{overwrites}", key.yellow());
+        }
+
+        prompt_println!();
+
+        SecretCommand::confirm();
+
+        for (key, value) in &copied {
+            persister.insert_secret_into_env(key, value, to_env, true, &format!("copy {from_env}"));
+        }
+
+        let keys: Vec<&str> = copied.iter().map(|(key, _)| key.as_str()).collect();
+        SecretCommand::emit_action_document("copy", to_env, &keys);
+    }
+
+    fn get_stored_secrets(persister: &mut dyn Persister) -> Vec<Secret> {
+        match persister.get_all_secrets() {
+            Ok(secrets) => secrets,
+            Err(err) => {
+                log::error!("error reading secrets: {err}");
+                process::exit(1)
+            }
+        }
+    }
+
+    /// report declared secrets that are missing or set to their
+    /// default, and secrets that no drop file references.
+    /// exits with 1 if a required secret is missing
+    ///
+    /// values are resolved through the secret providers
+    /// configured for the environment
+    fn check_secrets(env: &str) {
+        let global_drop_config = GlobalDropConfigProvider::get();
+
+        let declarations = global_drop_config.get_secret_declarations(env);
+        let referenced = global_drop_config.get_referenced_secret_keys();
+
+        let stored = match SecretProviderManager::resolve_secrets_for_env(env) {
+            Ok(stored) => stored,
+            Err(err) => {
+                log::error!("error reading secrets for env {env}: {err}");
+                process::exit(1)
+            }
+        };
+
+        let mut all_keys: Vec<String> = declarations.keys().cloned().collect();
+        all_keys.extend(stored.keys().filter(|key| !declarations.contains_key(*key)).cloned());
+
+        let mut table = Vec::<Vec<CellStruct>>::new();
+        let mut rows = Vec::<serde_json::Value>::new();
+        let mut has_missing_required = false;
+        let mut seen = HashSet::<String>::new();
+
+        for key in all_keys {
+            if !seen.insert(key.to_string()) {
+                continue;
+            }
+
+            let declaration = declarations.get(&key).map(|(declaration, _)| *declaration);
+            // any non-null value is set- providers can resolve
+            // json secrets to numbers, bools, arrays or objects
+            let stored_value = stored.get(&key).filter(|value| !value.is_null());
+
+            let mut statuses = Vec::<String>::new();
+
+            if let Some(declaration) = declaration {
+                match (stored_value, &declaration.default) {
+                    (None, None) if declaration.required => {
+                        has_missing_required = true;
+                        statuses.push(format!("{}", "missing".red()));
+                    }
+                    (None, None) => statuses.push(format!("{}", "missing (optional)".yellow())),
+                    (None, Some(_)) => statuses.push(format!("{}", "default".yellow())),
+                    (Some(value), Some(default)) if value.as_str() == Some(default) => {
+                        statuses.push(format!("{}", "default".yellow()));
+                    }
+                    _ => {}
+                }
+            } else {
+                statuses.push(format!("{}", "undeclared".purple()));
+            }
+
+            if !referenced.contains(&key) {
+                statuses.push(format!("{}", "unused".purple()));
+            }
+
+            if statuses.is_empty() {
+                statuses.push(format!("{}", "ok".green()));
+            }
+
+            let description = declaration
+                .and_then(|declaration| declaration.description.clone())
+                .unwrap_or_default();
+
+            rows.push(serde_json::json!({
+                "key": key,
+                "statuses": statuses,
+                "description": description,
+            }));
+
+            table.push(vec![key.cell(), statuses.join(", ").cell(), description.cell()]);
+        }
+
+        if !Output::is_text() {
+            Output::emit_document(&serde_json::json!({
+                "env": env,
+                "secrets": rows,
+                "missing_required": has_missing_required,
+            }));
+
+            if has_missing_required {
+                process::exit(1)
+            }
+
+            return;
+        }
+
+        if table.is_empty() {
+            println!("No secrets declared or set for env: {}", env.yellow());
+            return;
+        }
+
+        println!("Secrets check for env: {}\n", env.yellow());
+
+        assert!(print_stdout(table.table()).is_ok());
+
+        if has_missing_required {
+            prompt_println!("\nrequired secrets are missing- set them with `drop secret set [key] [value]`");
+            process::exit(1)
+        }
+    }
+
+    /// encrypt a plaintext json file, `secrets.[env].json` unless
+    /// passed, into the environment's shared encrypted secrets file
+    fn encrypt_secrets_file(env: &str, plaintext_path: Option<&str>) {
+        let plaintext_path = Path::new(&CmdContext::get_dir()).join(
+            plaintext_path
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("secrets.{env}.json")),
+        );
+
+        let plaintext = match fs::read_to_string(&plaintext_path) {
+            Ok(contents) => EncryptedSecretsFile::plaintext_from_json(&contents),
+            Err(err) => {
+                log::error!("error reading {}: {err}", plaintext_path.display());
+                process::exit(1)
+            }
+        };
+
+        let encrypted_path = EncryptedSecretsFile::get_path_for_env(env);
+
+        let write_res = plaintext.and_then(|plaintext| {
+            SecretCommand::write_secrets_file(env, &encrypted_path, &plaintext, None)
+                .map(|_| plaintext.len())
+        });
+
+        match write_res {
+            Ok(count) => text_println!(
+                "Encrypted {count} secrets for env {} to {}",
+                env.yellow(),
+                encrypted_path.display()
+            ),
+            Err(err) => {
+                log::error!("error encrypting {}: {err}", plaintext_path.display());
+                process::exit(1)
+            }
+        }
+    }
+
+    /// print the decrypted secrets file, or write
+    /// it to a plaintext file if a path is passed
+    fn decrypt_secrets_file(env: &str, plaintext_path: Option<&str>) {
+        let encrypted_path = EncryptedSecretsFile::get_path_for_env(env);
+
+        let plaintext = match EncryptedSecretsFile::read(&encrypted_path)
+            .and_then(|secrets_file| secrets_file.decrypt())
+        {
+            Ok(plaintext) => plaintext,
+            Err(err) => {
+                log::error!("error decrypting secrets for env {env}: {err}");
+                process::exit(1)
+            }
+        };
+
+        let json = serde_json::to_string_pretty(&plaintext).unwrap();
+
+        match plaintext_path {
+            Some(plaintext_path) => {
+                let plaintext_path = Path::new(&CmdContext::get_dir()).join(plaintext_path);
+
+                if let Err(err) = fs::write(&plaintext_path, format!("{json}\n")) {
+                    log::error!("error writing {}: {err}", plaintext_path.display());
+                    process::exit(1)
+                }
+
+                text_println!(
+                    "Decrypted secrets for env {} to {}- do not commit this file",
+                    env.yellow(),
+                    plaintext_path.display()
+                );
+            }
+            None => println!("{json}"),
+        }
+    }
+
+    /// open the decrypted secrets file in `$VISUAL` or `$EDITOR`
+    /// and re-encrypt it when the editor exits
+    fn edit_secrets_file(env: &str) {
+        if !CmdContext::is_interactive() {
+            log::error!(
+                "secret edit requires an interactive terminal- use `secret decrypt [file]` and `secret encrypt [file]` instead"
+            );
+            process::exit(1)
+        }
+
+        let encrypted_path = EncryptedSecretsFile::get_path_for_env(env);
+
+        let previous = if encrypted_path.exists() {
+            match EncryptedSecretsFile::read(&encrypted_path) {
+                Ok(previous) => Some(previous),
+                Err(err) => {
+                    log::error!("{err}");
+                    process::exit(1)
+                }
+            }
+        } else {
+            None
+        };
+
+        let plaintext = match &previous {
+            Some(previous) => match previous.decrypt() {
+                Ok(plaintext) => plaintext,
+                Err(err) => {
+                    log::error!("error decrypting secrets for env {env}: {err}");
+                    process::exit(1)
+                }
+            },
+            None => BTreeMap::new(),
+        };
+
+        let original = format!("{}\n", serde_json::to_string_pretty(&plaintext).unwrap());
+
+        let edit_res = SecretCommand::edit_in_temp_file(env, &original);
+
+        // the temp file is removed before any error is reported
+        let edited = match edit_res {
+            Ok(edited) => edited,
+            Err(err) => {
+                log::error!("error editing secrets for env {env}: {err}");
+                process::exit(1)
+            }
+        };
+
+        if edited == original {
+            text_println!("No changes to secrets for env {}", env.yellow());
+            return;
+        }
+
+        let write_res = EncryptedSecretsFile::plaintext_from_json(&edited).and_then(|edited| {
+            SecretCommand::write_secrets_file(env, &encrypted_path, &edited, previous.as_ref())
+        });
+
+        match write_res {
+            Ok(_) => text_println!(
+                "Encrypted secrets for env {} to {}",
+                env.yellow(),
+                encrypted_path.display()
+            ),
+            Err(err) => {
+                log::error!("error encrypting secrets for env {env}, changes discarded: {err}");
+                process::exit(1)
+            }
+        }
+    }
+
+    fn edit_in_temp_file(env: &str, contents: &str) -> Result<String, anyhow::Error> {
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+
+        let temp_path = std::env::temp_dir().join(format!(
+            "drop-secrets-{env}-{}.json",
+            rand::random::<u32>()
+        ));
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let edit_res = options
+            .open(&temp_path)
+            .and_then(|mut file| io::Write::write_all(&mut file, contents.as_bytes()))
+            .map_err(anyhow::Error::from)
+            .and_then(|_| {
+                let mut editor_args = editor.split_whitespace();
+                let program = editor_args.next().unwrap_or("vi");
+
+                let status = process::Command::new(program)
+                    .args(editor_args)
+                    .arg(&temp_path)
+                    .status()
+                    .map_err(|err| anyhow::anyhow!("failed to run {editor}: {err}"))?;
+
+                if !status.success() {
+                    return Err(anyhow::anyhow!("{editor} exited with {status}"));
+                }
+
+                Ok(fs::read_to_string(&temp_path)?)
+            });
+
+        let _ = fs::remove_file(&temp_path);
+
+        edit_res
+    }
+
+    fn write_secrets_file(
+        env: &str,
+        encrypted_path: &Path,
+        plaintext: &BTreeMap<String, String>,
+        previous: Option<&EncryptedSecretsFile>,
+    ) -> Result<(), anyhow::Error> {
+        let recipients = GlobalDropConfigProvider::get().get_secret_recipients(env);
+
+        EncryptedSecretsFile::encrypt(plaintext, &recipients, previous)?.write(encrypted_path)
+    }
+}
@@ -1,9 +1,15 @@
-use std::sync::OnceLock;
+use std::{
+    io::IsTerminal,
+    sync::{Arc, RwLock},
+};
 use anyhow::anyhow;
 
 use super::cli::{Cli, Command};
 
-static GLOBAL_CMD_CTX_PROVIDER: OnceLock<CmdContext> = OnceLock::new();
+/// replaced when an embedded session selects another
+/// directory or environment- each session keeps its own
+/// context and sets it again before running
+static GLOBAL_CMD_CTX_PROVIDER: RwLock<Option<Arc<CmdContext>>> = RwLock::new(None);
 
 /// global context provider for clap cli input
 #[derive(Debug)]
@@ -13,24 +19,26 @@ pub struct CmdContext{
 
 impl CmdContext {
 
-    pub fn set(cli: Cli) {
+    pub fn set(cli: Cli) -> Arc<CmdContext> {
 
         log::debug!("CmdContext set {cli:?}");
 
-        let cmd = CmdContext { cli };
+        let cmd = Arc::new(CmdContext { cli });
 
-        let cell_set_result = GLOBAL_CMD_CTX_PROVIDER.set(cmd);
+        CmdContext::set_arc(Arc::clone(&cmd));
 
-        if cell_set_result.is_err() {
-            log::error!(
-                "Error setting global cmd context provider: {:?}",
-                cell_set_result.unwrap_err()
-            )
+        cmd
+    }
+
+    pub fn set_arc(cmd: Arc<CmdContext>) {
+        match GLOBAL_CMD_CTX_PROVIDER.write() {
+            Ok(mut provider) => *provider = Some(cmd),
+            Err(err) => log::error!("Error setting global cmd context provider: {err:?}"),
         }
     }
 
-    pub fn get() -> Result<&'static CmdContext, anyhow::Error> {
-        let res = GLOBAL_CMD_CTX_PROVIDER.get();
+    pub fn get() -> Result<Arc<CmdContext>, anyhow::Error> {
+        let res = GLOBAL_CMD_CTX_PROVIDER.read().ok().and_then(|provider| provider.clone());
 
         if let Some(ctx) = res {
             Ok(ctx)
@@ -39,16 +47,57 @@ impl CmdContext {
         }
     }
 
-    pub fn get_env() -> &'static str {
+    pub fn get_dir() -> String {
+        let cmd = CmdContext::get();
+
+        if cmd.is_err() {
+            log::warn!("error unwrapping CmdContext {:?}", cmd.unwrap_err());
+
+            ".".to_string()
+        } else {
+            cmd.unwrap().cli.dir.clone()
+        }
+    }
+
+    pub fn get_env() -> String {
 
         let cmd = CmdContext::get();
 
         if cmd.is_err() {
             log::warn!("error unwrapping CmdContext {:?}", cmd.unwrap_err());
 
-            "base"
+            "base".to_string()
         } else {
-            &cmd.unwrap().cli.env
+            cmd.unwrap().cli.env.clone()
+        }
+    }
+
+    /// `hit --verbose`
+    pub fn is_verbose() -> bool {
+        match CmdContext::get() {
+            Ok(cmd) => matches!(cmd.cli.command, Command::hit { verbose: true, .. }),
+            Err(_) => false,
+        }
+    }
+
+    /// `hit --transcript [file]`
+    pub fn get_transcript_path() -> Option<String> {
+        match CmdContext::get().as_deref() {
+            Ok(CmdContext { cli: Cli { command: Command::hit { transcript, .. }, .. } }) => {
+                transcript.clone()
+            }
+            _ => None,
         }
     }
+
+    /// false with `--non-interactive`/`--yes`, or
+    /// when stdin is not a terminal, e.g. in CI
+    pub fn is_interactive() -> bool {
+        let non_interactive_flag = match CmdContext::get() {
+            Ok(cmd) => cmd.cli.non_interactive,
+            Err(_) => false,
+        };
+
+        !non_interactive_flag && std::io::stdin().is_terminal()
+    }
 }
@@ -1,7 +1,12 @@
+use colored::Colorize;
 use inquire::{ui::RenderConfig, InquireError, Select};
 use log::trace;
 
-use crate::parser::{drop_id::DropId, GlobalDropConfigProvider};
+use crate::{
+    cmd::ctx::CmdContext,
+    parser::{drop_id::DropId, GlobalDropConfigProvider},
+    util::exit,
+};
 
 pub struct DropDown {}
 
@@ -30,6 +35,16 @@ impl DropDown {
 
         let options: Vec<&str> = drop_ids_in_env.iter().map(String::as_str).collect();
 
+        if !CmdContext::is_interactive() {
+            log::error!(
+                "{} is not a drop id and drop selection requires an interactive terminal. Pass one of:\n\n{}\n",
+                selected_module.yellow(),
+                options.join("\n")
+            );
+
+            exit::exit(1)
+        }
+
         let question = "Select drop";
 
         // RenderConfig
@@ -40,9 +55,14 @@ impl DropDown {
 
         match drop_id_of_call_to_hit {
             Ok(drop_id_of_call_to_hit) => drop_id_of_call_to_hit.to_string(),
+            Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
+                println!("\ncancelled");
+                exit::exit(0)
+            }
             Err(err) => {
                 trace!("matcher err {}", err);
-                panic!("error selecting drop")
+                log::error!("error selecting drop: {err}");
+                exit::exit(1)
             }
         }
     }
@@ -1,7 +1,7 @@
 use std::pin::Pin;
 
 use cli::Command;
-use commands::{give::GiveCommand, hit::HitCommand, secret::SecretCommand};
+use commands::{cookies::CookiesCommand, give::GiveCommand, hit::HitCommand, secret::SecretCommand};
 use futures::{future::BoxFuture, Future};
 
 
@@ -9,6 +9,7 @@ pub mod cli;
 pub mod ctx;
 pub mod commands;
 pub mod dropdown;
+pub mod output;
 
 pub trait DropCommand {
     fn announce(&self);
@@ -25,9 +26,23 @@ impl CommandManager {
         log::debug!("command {:?}", command);
 
         match command {
-            Command::hit { drop_id } => Box::new(HitCommand{ input_drop_id_string: drop_id.to_string() }),
+            Command::hit { drop_id, .. } => Box::new(HitCommand{ input_drop_id_string: drop_id.to_string() }),
             Command::give { drop_id } => Box::new(GiveCommand{ input_drop_id_string: drop_id.to_string() }),
-            Command::secret { action, key, value } => Box::new(SecretCommand{ action: action.to_string(), key: key.to_owned(), value: value.to_owned() }),
+            Command::secret { action, key, value, reveal, from, format, from_env, to_env } => Box::new(SecretCommand{
+                action: action.to_string(),
+                key: key.to_owned(),
+                value: value.to_owned(),
+                reveal: *reveal,
+                from: from.to_owned(),
+                format: *format,
+                from_env: from_env.to_owned(),
+                to_env: to_env.to_owned(),
+            }),
+            Command::cookies { action, domain, reveal } => Box::new(CookiesCommand{
+                action: action.to_string(),
+                domain: domain.to_owned(),
+                reveal: *reveal,
+            }),
         }
     }
 }
@@ -0,0 +1,51 @@
+use std::sync::OnceLock;
+
+use crate::util::redact::Redactor;
+
+use super::cli::OutputFormat;
+
+static GLOBAL_OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// manages stdout for text, json and jsonl output
+///
+/// in json and jsonl mode only documents are written
+/// to stdout- logs and prompts go to stderr
+pub struct Output {}
+
+impl Output {
+    /// set before the logger so every
+    /// line is routed for the format
+    pub fn set_format(format: OutputFormat) {
+        let cell_set_result = GLOBAL_OUTPUT_FORMAT.set(format);
+
+        if cell_set_result.is_err() {
+            log::error!(
+                "Error setting global output format: {:?}",
+                cell_set_result.unwrap_err()
+            )
+        }
+    }
+
+    pub fn get_format() -> OutputFormat {
+        *GLOBAL_OUTPUT_FORMAT.get().unwrap_or(&OutputFormat::Text)
+    }
+
+    pub fn is_text() -> bool {
+        Output::get_format() == OutputFormat::Text
+    }
+
+    /// write a document in json or jsonl mode,
+    /// redacting sensitive values
+    pub fn emit_document(document: &serde_json::Value) {
+        let serialized = match Output::get_format() {
+            OutputFormat::Text | OutputFormat::Silent => return,
+            OutputFormat::Json => serde_json::to_string_pretty(document),
+            OutputFormat::Jsonl => serde_json::to_string(document),
+        };
+
+        match serialized {
+            Ok(serialized) => println!("{}", Redactor::redact(&serialized)),
+            Err(err) => log::error!("error serializing output document: {err}"),
+        }
+    }
+}
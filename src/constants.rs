@@ -23,6 +23,8 @@ pub const CALL_BASE_URL: &str = "base_url";
 pub const CALL_BODY: &str = "body";
 // /// Property key for the "after" action in a call block.
 pub const CALL_AFTER: &str = "after";
+// /// Property key for the "before" actions in a call block.
+pub const CALL_BEFORE: &str = "before";
 // /// Property key for the path in a call block.
 pub const CALL_PATH: &str = "path";
 // /// Property key for the headers in a call block.
@@ -34,6 +36,46 @@ pub const CALL_ASSERT: &str = "assert";
 // /// Property key for asserts in a call block.
 pub const CALL_INPUTS: &str = "inputs";
 
+// /// call settings- also read from module and environment \\\
+// /// Property key for the request timeout.
+pub const CALL_TIMEOUT: &str = "timeout";
+// /// Property key for the number of retries.
+pub const CALL_RETRIES: &str = "retries";
+// /// Property key for the status codes and errors to retry.
+pub const CALL_RETRY_ON: &str = "retry_on";
+// /// Property key for the retry backoff.
+pub const CALL_BACKOFF: &str = "backoff";
+// /// Property key for the proxy url.
+pub const CALL_PROXY: &str = "proxy";
+// /// Property key for the hosts that bypass the proxy.
+pub const CALL_NO_PROXY: &str = "no_proxy";
+// /// Property key for the dns overrides.
+pub const CALL_RESOLVE: &str = "resolve";
+// /// Property key for following redirects.
+pub const CALL_FOLLOW_REDIRECTS: &str = "follow_redirects";
+// /// Property key for the redirect limit.
+pub const CALL_MAX_REDIRECTS: &str = "max_redirects";
+// /// Property key for the persistent cookie jar.
+pub const CALL_COOKIES: &str = "cookies";
+// /// Property key for the auth block.
+pub const CALL_AUTH: &str = "auth";
+// /// Property key for the script run before the call is sent.
+pub const CALL_SCRIPT_BEFORE: &str = "script_before";
+// /// Property key for the script run after the call responds.
+pub const CALL_SCRIPT_AFTER: &str = "script_after";
+// /// Property key for the time a script can run.
+pub const CALL_SCRIPT_TIMEOUT: &str = "script_timeout";
+
+// /// client settings- read from the environment \\\
+// /// Property key for the http version negotiation.
+pub const CLIENT_HTTP_VERSION: &str = "http_version";
+// /// Property key for the connection pool size.
+pub const CLIENT_MAX_CONNECTIONS: &str = "max_connections";
+// /// Property key for the connection pool size per host.
+pub const CLIENT_MAX_CONNECTIONS_PER_HOST: &str = "max_connections_per_host";
+// /// Block key for the tls settings.
+pub const CLIENT_TLS: &str = "tls";
+
 // /// internal variables \\\
 // /// Property key for the response in internal variables.
 pub const RESPONSE_PROP: &str = "response";
@@ -53,6 +95,15 @@ pub const SECRET_OBJECT_VAR_PREFIX: &str = "secrets";
 // /// Prefix for input object variables.
 pub const INPUT_OBJECT_VAR_PREFIX: &str = "inputs";
 
+// /// Prefix for cookie jar object variables.
+pub const COOKIE_OBJECT_VAR_PREFIX: &str = "cookies";
+
+// /// Prefix for variables stored by after actions.
+pub const VARIABLE_OBJECT_VAR_PREFIX: &str = "vars";
+
+// /// Prefix for auth block object variables.
+pub const AUTH_OBJECT_VAR_PREFIX: &str = "auth";
+
 // // auth
 // /// Bearer key for authentication.
 pub const AUTH_BEARER_KEY: &str = "Bearer";
@@ -67,6 +118,30 @@ pub const AUTH_AUTHORIZATION_KEY: &str = "authorization";
 // /// Type value for the "`set_secret`" action in post actions.
 pub const AFTER_SET_SECRET_TYPE_VALUE: &str = "set_secret";
 
+// /// Type value for the "`write_file`" action in post actions.
+pub const AFTER_WRITE_FILE_TYPE_VALUE: &str = "write_file";
+
+// /// Type value for the "`set_variable`" action in post actions.
+pub const AFTER_SET_VARIABLE_TYPE_VALUE: &str = "set_variable";
+
+// /// Type value for the "`append_jsonl`" action in post actions.
+pub const AFTER_APPEND_JSONL_TYPE_VALUE: &str = "append_jsonl";
+
+// /// Type value for the "`exec`" action in post actions.
+pub const AFTER_EXEC_TYPE_VALUE: &str = "exec";
+
+// /// Type value for the "`http_notify`" action in post actions.
+pub const AFTER_HTTP_NOTIFY_TYPE_VALUE: &str = "http_notify";
+
+// /// Property key for when an after action runs- "success", "failure" or "always".
+pub const AFTER_ON_PROP: &str = "on";
+
+// /// Property key for the condition of an after action.
+pub const AFTER_WHEN_PROP: &str = "when";
+
+// /// Property key for running an after action only when the call's asserts pass.
+pub const AFTER_ONLY_IF_ASSERTS_PASS_PROP: &str = "only_if_asserts_pass";
+
 // /// Property key for the target in the "`set_secret`" action.
 pub const SET_ENV_TARGET_PROP: &str = "target";
 
@@ -76,6 +151,19 @@ pub const SET_ENV_VARIABLE_PROP: &str = "variable";
 // /// Property key for the output in the "`set_secret`" action.
 pub const SET_ENV_OUTPUT_PROP: &str = "output";
 
+// // pre actions
+// /// Type value for the "`ensure_secret`" before action.
+pub const BEFORE_ENSURE_SECRET_TYPE_VALUE: &str = "ensure_secret";
+
+// /// Type value for the "`set_input`" before action.
+pub const BEFORE_SET_INPUT_TYPE_VALUE: &str = "set_input";
+
+// /// Type value for the "`sleep`" before action.
+pub const BEFORE_SLEEP_TYPE_VALUE: &str = "sleep";
+
+// /// Type value for the "`exec`" before action.
+pub const BEFORE_EXEC_TYPE_VALUE: &str = "exec";
+
 // // other
 // /// Property key for the type in post actions.
 pub const TYPE_PROP: &str = "type";
@@ -86,3 +174,9 @@ pub const SET_POST_ACTION_LABEL: &str = "set_secret";
 
 // /// Property key for the type in post actions.
 pub const POST_ACTION_TYPE: &str = "type";
+
+// /// Folder in the dropfile directory with wasm plugins.
+pub const PLUGINS_DIR: &str = "plugins";
+
+// /// Prefix of outputs set by a call's `script_after`.
+pub const SCRIPT_OUTPUT_PREFIX: &str = "script";
@@ -3,6 +3,11 @@ use derive_getters::Getters;
 use hcl::eval::Errors;
 use log::trace;
 
+use crate::util::exit;
+use crate::{cmd::ctx::CmdContext, constants::{AUTH_OBJECT_VAR_PREFIX, BASE_ENVIRONMENT_TITLE, SCRIPT_OUTPUT_PREFIX}, parser::GlobalDropConfigProvider};
+
+use super::scope::Scope;
+
 #[derive(Debug, Getters)]
 pub struct EvalDiagnostics {
     errors: Option<Vec<hcl::eval::Error>>,
@@ -56,6 +61,12 @@ impl EvalDiagnostics {
                 continue;
             }
 
+            // outputs set by the call's script_after
+            if message.contains(&format!("{SCRIPT_OUTPUT_PREFIX}.")) {
+                total_errs -= 1;
+                continue;
+            }
+
             log::trace!(
                 "EvalDiagnostics evaluate_errors error {}",
                 error.to_string()
@@ -107,22 +118,37 @@ impl EvalDiagnostics {
 
                 log::trace!("EvalDiagnostics evaluate_errors panic {errors:?}");
 
-                errors.iter().for_each(|error| {
-                    let message = error.to_string();
-
+                self.error_messages().iter().for_each(|message| {
                     log::trace!("");
-                    log::error!(
-                        "Error evaluating environment variables in file {}:\n{message}\n",
-                        self.file_name.yellow()
-                    );
+                    log::error!("{message}");
                 });
 
                 log::trace!("panic");
-                std::process::exit(1);
+                exit::exit(1);
             }
         }
     }
 
+    /// the errors `panic` logs, for embedded
+    /// callers that return them instead
+    pub fn error_messages(&self) -> Vec<String> {
+        let Some(errors) = &self.errors else {
+            return Vec::new();
+        };
+
+        errors
+            .iter()
+            .map(|error| {
+                let hint = EvalDiagnostics::unresolved_auth_hint(error, &CmdContext::get_env()).unwrap_or_default();
+
+                format!(
+                    "Error evaluating environment variables in file {}:\n{error}\n{hint}",
+                    self.file_name.yellow()
+                )
+            })
+            .collect()
+    }
+
     fn print_input_secret_helpers(&self) {
         match &self.errors {
             None => {}
@@ -183,10 +209,86 @@ impl EvalDiagnostics {
                                 self.file_name.yellow(),
                                 var.yellow(),
                             );
+
+                            if let Some(hint) = EvalDiagnostics::missing_secret_hint(&var, &CmdContext::get_env()) {
+                                println!("{hint}");
+                            }
                         }
                     }
                 }
             }
         }
     }
+
+    /// hints for every missing secret in a set of evaluation errors,
+    /// and for auth blocks that couldn't be evaluated because of one
+    pub fn missing_secret_hints(errors: &Errors, env: &str) -> String {
+        let mut hints = Vec::<String>::new();
+
+        for error in errors {
+            if let Some(hint) = EvalDiagnostics::unresolved_auth_hint(error, env) {
+                hints.push(hint);
+                continue;
+            }
+
+            if !error.to_string().contains("secrets") {
+                continue;
+            }
+
+            if let hcl::eval::ErrorKind::NoSuchKey(var) = error.kind() {
+                if let Some(hint) = EvalDiagnostics::missing_secret_hint(var, env) {
+                    hints.push(hint);
+                }
+            }
+        }
+
+        hints.join("")
+    }
+
+    /// `auth.[name]` is missing because its block
+    /// couldn't be evaluated in the environment
+    pub fn unresolved_auth_hint(error: &hcl::eval::Error, env: &str) -> Option<String> {
+        if !error.to_string().contains(&format!("{AUTH_OBJECT_VAR_PREFIX}.")) {
+            return None;
+        }
+
+        let hcl::eval::ErrorKind::NoSuchKey(auth_name) = error.kind() else {
+            return None;
+        };
+
+        let unresolved_error = Scope::get_unresolved_auth_error(auth_name)?;
+
+        Some(format!(
+            "\tauth block {} can't be evaluated in environment {}:\n\t{unresolved_error}\n",
+            auth_name.yellow(),
+            env.yellow(),
+        ))
+    }
+
+    /// if the missing secret is declared in a secrets block,
+    /// name the declaration and the command to set it
+    pub fn missing_secret_hint(key: &str, env: &str) -> Option<String> {
+        let global_drop_config = GlobalDropConfigProvider::get();
+        let declarations = global_drop_config.get_secret_declarations(env);
+
+        let (declaration, file_name) = declarations.get(key)?;
+
+        let description = match &declaration.description {
+            Some(description) => format!(" ({description})"),
+            None => String::new(),
+        };
+
+        let env_flag = if env == BASE_ENVIRONMENT_TITLE {
+            String::new()
+        } else {
+            format!("-e {env} ")
+        };
+
+        Some(format!(
+            "\tsecret {}{description} is declared in {} but has no value in environment {}.\n\tset it with `drop {env_flag}secret set {key} <value>`\n",
+            key.yellow(),
+            file_name.yellow(),
+            env.yellow(),
+        ))
+    }
 }
@@ -5,7 +5,7 @@ use super::{
 use crate::{
     call::DropCall,
     cmd::ctx::CmdContext,
-    constants::MOD_OBJECT_VAR_PREFIX,
+    constants::{CALL_BEFORE, MOD_OBJECT_VAR_PREFIX},
     parser::{
         self,
         block_type::run::RunBlock,
@@ -16,6 +16,7 @@ use crate::{
         GlobalDropConfigProvider,
     },
 };
+use anyhow::anyhow;
 use colored::Colorize;
 use hcl::{
     eval::{Context, Evaluate},
@@ -24,6 +25,7 @@ use hcl::{
 use indexmap::IndexMap;
 use log::trace;
 use log_derive::logfn;
+use std::sync::Arc;
 
 /// methods to evaluate hcl blocks
 /// with specific environment variables
@@ -47,7 +49,7 @@ impl Evaluator {
 
         let mut env_var_scope = GlobalScopeProvider::get_mut()?;
 
-        Evaluator::generate_module_context(selected_module_block, &mut env_var_scope);
+        Evaluator::generate_module_context(&selected_module_block, &mut env_var_scope);
 
         Ok(env_var_scope)
     }
@@ -58,27 +60,27 @@ impl Evaluator {
         fmt = "get_selected_module_block: {:?}",
         log_ts = true
     )]
-    pub fn get_selected_module_block(drop_id: &str) -> Result<&'static DropBlock, anyhow::Error> {
+    pub fn get_selected_module_block(drop_id: &str) -> Result<Arc<DropBlock>, anyhow::Error> {
         let drop_config = GlobalDropConfigProvider::get();
 
         let module_from_drop_id = DropId::get_module_from_drop_id(drop_id);
 
         let modules = &drop_config.modules;
 
-        let matched_modules: Vec<&'static DropBlock> = modules
+        let matched_modules: Vec<&Arc<DropBlock>> = modules
             .iter()
             .filter(|each| each.drop_id.as_ref().unwrap().resource_name == module_from_drop_id)
             .collect();
 
         // todo- handle errors
 
-        Ok(matched_modules[0])
+        Ok(Arc::clone(matched_modules[0]))
     }
 
     pub fn get_selected_container(
         target_drop_id: &str,
         drop_resource_type: DropResourceType,
-    ) -> Result<&'static DropBlock, anyhow::Error> {
+    ) -> Result<Arc<DropBlock>, anyhow::Error> {
         let global_config = GlobalDropConfigProvider::get();
 
         let vector = match drop_resource_type {
@@ -88,26 +90,25 @@ impl Evaluator {
             DropResourceType::Run => &global_config.runs,
             DropResourceType::Chain => &global_config.chains,
             DropResourceType::ChainNode => &global_config.chain_nodes,
+            DropResourceType::Secrets => &global_config.secrets,
+            DropResourceType::SecretProviders => &global_config.secret_providers,
+            DropResourceType::Auth => &global_config.auths,
         };
 
-        let matched_call: Vec<&'static DropBlock> = vector
+        let matched_call: Vec<&Arc<DropBlock>> = vector
             .iter()
             .filter(|each| each.drop_id.as_ref().unwrap().drop_id().unwrap() == target_drop_id)
             .collect();
 
-        assert!(
-            !matched_call.is_empty(),
-            "No block found for {}",
-            target_drop_id.yellow()
-        );
+        if matched_call.is_empty() {
+            return Err(anyhow!("No block found for {}", target_drop_id.yellow()));
+        }
 
-        assert!(
-            matched_call.len() == 1,
-            "Multiple blocks found for {}",
-            target_drop_id.yellow()
-        );
+        if matched_call.len() > 1 {
+            return Err(anyhow!("Multiple blocks found for {}", target_drop_id.yellow()));
+        }
 
-        Ok(matched_call[0])
+        Ok(Arc::clone(matched_call[0]))
     }
 
     pub fn generate_module_context(
@@ -151,8 +152,9 @@ impl Evaluator {
             let eval_result = expr.evaluate_in_place(variable_context);
 
             if eval_result.is_err() {
-                let msg = eval_result.err().unwrap().to_string();
-                panic!("{file_name} error evaluating {key_as_str}: {msg}")
+                let errors = eval_result.unwrap_err();
+                let hints = EvalDiagnostics::missing_secret_hints(&errors, &CmdContext::get_env());
+                panic!("{file_name} error evaluating {key_as_str}: {errors}\n\n{hints}")
             }
 
             let val_as_hcl_val = HclBlock::value_from_expr(expr);
@@ -188,7 +190,16 @@ impl Evaluator {
 
         // we have to clone here because evaluate in place
         // requires mut borrow
-        let hcl_block = call_block.hcl_block.clone();
+        let mut hcl_block = call_block.hcl_block.clone();
+
+        // before actions are evaluated as they run, and
+        // `run` is a drop id rather than a variable
+        if let Some(block) = hcl_block.as_mut() {
+            block.body = std::mem::take(&mut block.body)
+                .into_iter()
+                .filter(|structure| !matches!(structure, hcl::Structure::Attribute(attr) if attr.key() == CALL_BEFORE))
+                .collect();
+        }
 
         let file_name = &call_block.file_name;
 
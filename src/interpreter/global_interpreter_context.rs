@@ -5,6 +5,9 @@ use hcl::{
 };
 use indexmap::IndexMap;
 
+use crate::util::exit;
+use crate::{plugin::PluginRegistry, util::jwt::Jwt};
+
 pub struct GlobalInterpreterContext {}
 
 impl GlobalInterpreterContext {
@@ -20,7 +23,10 @@ impl GlobalInterpreterContext {
             "basic_auth",
             GlobalInterpreterContext::basic_auth_hcl_func(),
         );
-        variable_context.declare_func("base64", GlobalInterpreterContext::base64_hcl_func());
+        PluginRegistry::get().declare_functions(&mut variable_context);
+        variable_context.declare_func("jwt_decode", GlobalInterpreterContext::jwt_decode_hcl_func());
+        variable_context.declare_func("jwt_verify", GlobalInterpreterContext::jwt_verify_hcl_func());
+        variable_context.declare_func("jwt_sign", GlobalInterpreterContext::jwt_sign_hcl_func());
         variable_context.declare_var("inputs", IndexMap::new());
         variable_context.declare_var("secrets", IndexMap::new());
         variable_context
@@ -58,7 +64,7 @@ impl GlobalInterpreterContext {
                     if let Value::Array(arr) = each {
                         if arr.len() != 2 {
                             println!("params can only have two members- key and value");
-                            std::process::exit(1)
+                            exit::exit(1)
                         }
 
                         let this_string =
@@ -68,7 +74,7 @@ impl GlobalInterpreterContext {
                     }
                 }
 
-                let joined = "?".to_string() + &param_list.join("&");
+                let joined = "?".to_string() + param_list.join("&").as_str();
 
                 Ok(Value::String(joined))
             })
@@ -94,20 +100,37 @@ impl GlobalInterpreterContext {
             })
     }
 
-    pub fn base64_hcl_func() -> FuncDef {
+    /// `jwt_decode(token).claims.sub`- header and
+    /// claims, the signature isn't verified
+    pub fn jwt_decode_hcl_func() -> FuncDef {
+        FuncDef::builder().param(ParamType::String).build(|args| {
+            let decoded = Jwt::decode(args[0].as_str().unwrap())?;
+            serde_json::from_value(decoded).map_err(|err| err.to_string())
+        })
+    }
+
+    /// `jwt_verify(token, key)`- true when the signature is
+    /// valid and the token hasn't expired
+    pub fn jwt_verify_hcl_func() -> FuncDef {
         FuncDef::builder()
             .param(ParamType::String)
-            .build(|args| match &args[0] {
-                Value::String(str) => {
-                    let mut buf = String::new();
-
-                    BASE64_STANDARD.encode_string(str, &mut buf);
+            .param(ParamType::String)
+            .build(|args| {
+                let is_valid = Jwt::verify(args[0].as_str().unwrap(), args[1].as_str().unwrap())?;
+                Ok(Value::Bool(is_valid))
+            })
+    }
 
-                    Ok(Value::String(buf))
-                }
-                _ => {
-                    std::process::exit(1)
-                }
+    /// `jwt_sign({ sub = "example_user" }, key, "HS256")`
+    pub fn jwt_sign_hcl_func() -> FuncDef {
+        FuncDef::builder()
+            .param(ParamType::Object(Box::new(ParamType::Any)))
+            .param(ParamType::String)
+            .param(ParamType::String)
+            .build(|args| {
+                let claims = serde_json::to_value(&args[0]).map_err(|err| err.to_string())?;
+                let token = Jwt::sign(&claims, args[1].as_str().unwrap(), args[2].as_str().unwrap())?;
+                Ok(Value::String(token))
             })
     }
 }
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, sync::OnceLock};
+use std::{collections::HashSet, sync::{Arc, RwLock}};
 use anyhow::anyhow;
 use hcl::{
     eval::{Context, Evaluate},
@@ -7,41 +7,49 @@ use hcl::{
 use indexmap::IndexMap;
 use log_derive::logfn;
 
+use crate::util::exit;
 use crate::{
+    caller::cookies::CookieJar,
+    cmd::ctx::CmdContext,
     constants::*,
+    persist::PersisterProvider,
     parser::{
         constants::GLOBAL_MOD_BLOCK_KEY, drop_block::DropBlock, hcl_block::{HclBlock, HclObject}, types::DropBlockType, GlobalDropConfigProvider
     },
 };
 
-use super::global_interpreter_context::GlobalInterpreterContext;
+use super::{diagnostics::EvalDiagnostics, global_interpreter_context::GlobalInterpreterContext};
 
-static GLOBAL_SCOPE_PROVIDER: OnceLock<Context> = OnceLock::new();
+/// replaced when an embedded session selects another
+/// environment, or its secrets change between calls
+static GLOBAL_SCOPE_PROVIDER: RwLock<Option<Context<'static>>> = RwLock::new(None);
 
 pub struct GlobalScopeProvider{}
 
 impl GlobalScopeProvider{
-    pub fn set(scope: Context<'static>){
-        let cell_set_result = GLOBAL_SCOPE_PROVIDER.set(scope);
-        if cell_set_result.is_err() {
-            log::error!(
-                "Error setting global config provider: {:?}",
-                cell_set_result.unwrap_err()
-            )
+    pub fn set(scope: Context<'static>) {
+        match GLOBAL_SCOPE_PROVIDER.write() {
+            Ok(mut provider) => *provider = Some(scope),
+            Err(err) => log::error!("Error setting global scope provider: {err:?}"),
         }
     }
 
     pub fn get_mut() -> Result<Context<'static>, anyhow::Error> {
-        let res = GLOBAL_SCOPE_PROVIDER.get();
+        let res = GLOBAL_SCOPE_PROVIDER.read().ok().and_then(|provider| provider.clone());
 
         if let Some(ctx) = res {
-            Ok(ctx.to_owned())
+            Ok(ctx)
         } else {
             Err(anyhow!("error accessing global cmd ctx"))
         }
     }
 }
 
+/// auth blocks that couldn't be evaluated in the selected
+/// environment, and why- they're left out of `auth.*`, so
+/// only calls, modules and environments that use them fail
+static UNRESOLVED_AUTH_BLOCKS: RwLock<Option<IndexMap<String, String>>> = RwLock::new(None);
+
 #[derive(Debug)]
 pub struct Scope {}
 
@@ -61,14 +69,30 @@ impl Scope {
 
         let mut global_variable_context = GlobalInterpreterContext::create();
 
+        let secrets_hash_for_env =
+            Scope::apply_secret_declarations(secrets_hash_for_env, user_selected_env);
+
         Scope::insert_object_into_hcl_context(
             &mut global_variable_context,
             SECRET_OBJECT_VAR_PREFIX,
             &secrets_hash_for_env,
         );
 
+        Scope::insert_object_into_hcl_context(
+            &mut global_variable_context,
+            COOKIE_OBJECT_VAR_PREFIX,
+            &CookieJar::get_values_for_env(user_selected_env),
+        );
+
+        Scope::insert_object_into_hcl_context(
+            &mut global_variable_context,
+            VARIABLE_OBJECT_VAR_PREFIX,
+            &Scope::get_variables_for_env(user_selected_env),
+        );
+
         let global_blocks: Vec<&DropBlock> = module_blocks
             .iter()
+            .map(|block| block.as_ref())
             .filter(|block_ref| {
                 let rn = block_ref.drop_id.as_ref().unwrap().resource_name.to_string();
                 rn == GLOBAL_MOD_BLOCK_KEY
@@ -80,10 +104,18 @@ impl Scope {
             &global_blocks,
         );
 
+        // auth blocks can reference secrets and global
+        // values, and environments can reference auth blocks
+        Scope::insert_auth_blocks_into_hcl_context(
+            &mut global_variable_context,
+            &global_drop_config.auths,
+        );
+
         let env_blocks = &global_drop_config.environments;
 
         let env_blocks_for_this_env: Vec<&DropBlock> = env_blocks
             .iter()
+            .map(|block| block.as_ref())
             .filter(|b| {
                 let rn = b.drop_id.as_ref().unwrap().resource_name.to_string();
                 rn == user_selected_env
@@ -110,6 +142,53 @@ impl Scope {
         Ok(global_variable_context)
     }
 
+    /// fill in declared secrets that have no stored value-
+    /// the declared default, or an empty string if the
+    /// secret is optional. required secrets without a default
+    /// are left missing so evaluation reports them
+    pub fn apply_secret_declarations(
+        mut secrets_hash_for_env: IndexMap<String, Value>,
+        user_selected_env: &str,
+    ) -> IndexMap<String, Value> {
+        let global_drop_config = GlobalDropConfigProvider::get();
+        let declarations = global_drop_config.get_secret_declarations(user_selected_env);
+
+        for (key, (declaration, _file_name)) in declarations {
+            if secrets_hash_for_env.contains_key(&key) {
+                continue;
+            }
+
+            match &declaration.default {
+                Some(default) => {
+                    secrets_hash_for_env.insert(key, Value::String(default.to_string()));
+                }
+                None => {
+                    if !declaration.required {
+                        secrets_hash_for_env.insert(key, Value::String(String::new()));
+                    }
+                }
+            }
+        }
+
+        secrets_hash_for_env
+    }
+
+    /// values stored with `set_variable`, as `vars.[key]`
+    pub fn get_variables_for_env(env: &str) -> IndexMap<String, Value> {
+        let variables = match PersisterProvider::get_lock_to_persister() {
+            Some(mut persister) => persister.get_variables_for_env(env),
+            None => Ok(IndexMap::new()),
+        };
+
+        match variables {
+            Ok(variables) => variables,
+            Err(err) => {
+                log::error!("error reading variables for env {env}: {err}");
+                exit::exit(1)
+            }
+        }
+    }
+
     pub fn insert_object_into_hcl_context(
         variable_context: &mut Context<'_>,
         entry_key: &str,
@@ -144,8 +223,9 @@ impl Scope {
                             let eval_result = expr.evaluate_in_place(variable_context);
 
                             if eval_result.is_err() {
-                                let msg = eval_result.err().unwrap().to_string();
-                                panic!("error evaluating {key_as_str}: {msg}")
+                                let errors = eval_result.unwrap_err();
+                                let hints = EvalDiagnostics::missing_secret_hints(&errors, &CmdContext::get_env());
+                                panic!("error evaluating {key_as_str}: {errors}\n\n{hints}")
                             }
 
                             let val_as_hcl_val = HclBlock::value_from_expr(expr);
@@ -157,12 +237,66 @@ impl Scope {
                     }
                 }
                 _ => {
-                    std::process::exit(1);
+                    exit::exit(1);
                 }
             }
         }
     }
 
+    /// each auth block as `auth.[name]`- blocks are per
+    /// environment credentials, so a block that can't be
+    /// evaluated here is skipped and reported where it's used
+    pub fn insert_auth_blocks_into_hcl_context(
+        variable_context: &mut Context<'_>,
+        auth_blocks: &[Arc<DropBlock>],
+    ) {
+        let mut auth_map: IndexMap<String, Value> = IndexMap::new();
+        let mut unresolved: IndexMap<String, String> = IndexMap::new();
+
+        for auth_block in auth_blocks {
+            let auth_name = auth_block.drop_id.as_ref().unwrap().resource_name.to_string();
+
+            assert!(!auth_map.contains_key(&auth_name) && !unresolved.contains_key(&auth_name), "multiple auth blocks named {auth_name}. Second in: {}.", auth_block.file_name);
+
+            if let DropBlockType::Auth(block) = &auth_block.drop_block {
+                let mut attribute_map: IndexMap<String, Value> = IndexMap::new();
+
+                let evaluated = Scope::try_evaluate_and_insert_values_from_object_body_into_context(
+                    &block.attributes,
+                    &mut attribute_map,
+                    &auth_block.file_name,
+                    variable_context,
+                );
+
+                match evaluated {
+                    Ok(()) => {
+                        auth_map.insert(auth_name, Value::Object(attribute_map));
+                    }
+                    Err(err) => {
+                        log::debug!("auth block {auth_name} unresolved in env {}: {err}", &CmdContext::get_env());
+                        unresolved.insert(auth_name, err);
+                    }
+                }
+            }
+        }
+
+        if let Ok(mut unresolved_auth_blocks) = UNRESOLVED_AUTH_BLOCKS.write() {
+            *unresolved_auth_blocks = Some(unresolved);
+        }
+
+        variable_context.declare_var(AUTH_OBJECT_VAR_PREFIX, auth_map);
+    }
+
+    /// why `auth.[name]` couldn't be evaluated, if it wasn't
+    pub fn get_unresolved_auth_error(auth_name: &str) -> Option<String> {
+        UNRESOLVED_AUTH_BLOCKS
+            .read()
+            .ok()?
+            .as_ref()?
+            .get(auth_name)
+            .cloned()
+    }
+
     pub fn insert_current_env_into_hcl_context(
         variable_context: &mut Context<'_>,
         current_env_module_block: Option<&DropBlock>,
@@ -201,7 +335,25 @@ impl Scope {
         file_name: &str,
         variable_context: &mut Context<'_>,
     ) {
-        object_block.iter().for_each(|(k, v)| {
+        let evaluated = Scope::try_evaluate_and_insert_values_from_object_body_into_context(
+            object_block,
+            env_map,
+            file_name,
+            variable_context,
+        );
+
+        if let Err(err) = evaluated {
+            panic!("{err}")
+        }
+    }
+
+    pub fn try_evaluate_and_insert_values_from_object_body_into_context(
+        object_block: &HclObject,
+        env_map: &mut IndexMap<String, Value>,
+        file_name: &str,
+        variable_context: &mut Context<'_>,
+    ) -> Result<(), String> {
+        for (k, v) in object_block.iter() {
             let key_as_str = k.to_string();
 
             assert!(!env_map.contains_key(&key_as_str), "environment block contains multiple entries for key: {key_as_str}. Second in: {file_name}.");
@@ -212,14 +364,16 @@ impl Scope {
             let eval_result = expr.evaluate_in_place(variable_context);
 
             if eval_result.is_err() {
-                let msg = eval_result.err().unwrap().to_string();
-                panic!("{file_name} error evaluating {key_as_str}: {msg}")
+                let errors = eval_result.unwrap_err();
+                let hints = EvalDiagnostics::missing_secret_hints(&errors, &CmdContext::get_env());
+                return Err(format!("{file_name} error evaluating {key_as_str}: {errors}\n\n{hints}"));
             }
 
             let val_as_hcl_val = HclBlock::value_from_expr(expr);
 
             env_map.insert(key_as_str.clone(), val_as_hcl_val);
+        }
 
-        });
+        Ok(())
     }
 }
@@ -0,0 +1,21 @@
+#![allow(warnings)]
+
+//! drop as a library- the `drop-rs` binary and
+//! the python bindings in `drop-py` are built on it
+
+pub mod action;
+pub mod api;
+pub mod assert;
+pub mod call;
+pub mod caller;
+pub mod cmd;
+pub mod constants;
+pub mod interpreter;
+pub mod parser;
+pub mod persist;
+pub mod plugin;
+pub mod record;
+pub mod runner;
+pub mod script;
+pub mod secret_provider;
+pub mod util;
@@ -3,30 +3,25 @@
 use std::fmt;
 
 use clap::Parser;
-use cmd::{
-    cli::{Cli, LogLevelInput},
-    ctx::CmdContext, CommandManager,
-};
 use colored::Colorize;
-use interpreter::scope::{GlobalScopeProvider, Scope};
+use drop_core::{
+    cmd::{
+        self,
+        cli::{Cli, LogLevelInput},
+        ctx::CmdContext,
+        output::Output,
+        CommandManager,
+    },
+    interpreter::scope::{GlobalScopeProvider, Scope},
+    parser::{file_walker::FileWalker, GlobalDropConfig, GlobalDropConfigProvider},
+    persist::{sqlite_persister::SqlitePersister, Persister, PersisterProvider},
+    plugin::PluginRegistry,
+    secret_provider::SecretProviderManager,
+    util::redact::{RedactingLogger, Redactor},
+};
 use log::{error, LevelFilter};
-use parser::{file_walker::FileWalker, GlobalDropConfig, GlobalDropConfigProvider};
-use persist::{sqlite_persister::SqlitePersister, Persister, PersisterProvider};
 use simplelog::{ColorChoice, Config, ConfigBuilder, TermLogger, TerminalMode};
 
-mod action;
-mod assert;
-mod call;
-mod caller;
-mod cmd;
-mod constants;
-mod interpreter;
-mod parser;
-mod persist;
-mod record;
-mod runner;
-mod util;
-
 //
 // search feature- search for path 
 // add "description" and tags to dropdown so you
@@ -37,18 +32,35 @@ async fn main() {
 
     let cli = Cli::parse();
 
-    setup_logger(cli.level);
+    if cli.unsafe_show_secrets {
+        Redactor::disable();
+    }
+
+    Output::set_format(cli.output);
 
-    let command = &cli.command;
+    if !Output::is_text() {
+        colored::control::set_override(false);
+    }
 
-    setup_global_config(&cli.dir);
+    setup_logger(cli.level);
 
-    setup_variable_scope(&cli.env);
+    let mut drop_command = CommandManager::get_command(&cli.command);
 
-    let mut drop_command = CommandManager::get_command(command);
+    let dropfile_dir = cli.dir.to_string();
+    let user_selected_env = cli.env.to_string();
 
+    // set before scope evaluation so diagnostics can
+    // reference the selected environment
     CmdContext::set(cli);
 
+    // before the dropfiles are parsed, so plugin
+    // after action types are known
+    setup_plugin_registry(&dropfile_dir);
+
+    setup_global_config(&dropfile_dir);
+
+    setup_variable_scope(&user_selected_env);
+
     drop_command.announce();
 
     drop_command.run().await;
@@ -57,14 +69,19 @@ async fn main() {
 fn setup_panic_handler() {
     // prevent panic from printing generic rust message
     std::panic::set_hook(Box::new(|err| {
-        let entire_error = err.to_string();
-
-        println!("\n\n{entire_error}\n\n");
+        let entire_error = Redactor::redact(&err.to_string());
 
         let exited = "\ndrop exited on error\n".red();
         let run_with_log = "run with log level flag ('-l debug') for more info.".red();
 
-        println!("{exited}{run_with_log}");
+        // stdout only carries documents in json output
+        if Output::is_text() {
+            println!("\n\n{entire_error}\n\n");
+            println!("{exited}{run_with_log}");
+        } else {
+            eprintln!("\n\n{entire_error}\n\n");
+            eprintln!("{exited}{run_with_log}");
+        }
     }));
 }
 
@@ -78,13 +95,34 @@ fn setup_logger(level: LogLevelInput) {
         cmd::cli::LogLevelInput::Trace => LevelFilter::Trace,
     };
 
-    TermLogger::init(
+    // logs stay off stdout for json output
+    let (terminal_mode, color_choice) = if Output::is_text() {
+        (TerminalMode::default(), ColorChoice::Always)
+    } else {
+        (TerminalMode::Stderr, ColorChoice::Never)
+    };
+
+    let term_logger = TermLogger::new(
         level_filter,
         log_config.build(),
-        TerminalMode::default(),
-        ColorChoice::Always,
-    )
-    .unwrap();
+        terminal_mode,
+        color_choice,
+    );
+
+    log::set_boxed_logger(Box::new(RedactingLogger { inner: term_logger })).unwrap();
+    log::set_max_level(level_filter);
+}
+
+fn setup_plugin_registry(dropfile_dir: &str) {
+    match PluginRegistry::load(dropfile_dir) {
+        Ok(registry) => {
+            PluginRegistry::set(registry);
+        }
+        Err(err) => {
+            error!("Error loading plugins: {err}");
+            std::process::exit(1)
+        }
+    }
 }
 
 fn setup_global_config(dropfile_dir: &str) {
@@ -114,36 +152,31 @@ fn setup_global_config(dropfile_dir: &str) {
 }
 
 fn setup_variable_scope(user_selected_env: &str) {
-    let persister_lock = PersisterProvider::get_lock_to_persister();
+    let secrets_hash_for_env_res =
+        SecretProviderManager::resolve_secrets_for_env(user_selected_env);
 
-    if persister_lock.is_none() {
-        log::warn!("setup_variable_scope failed to obtain lock to persister")
+    if secrets_hash_for_env_res.is_err() {
+        error!(
+            "Error resolving variable scope: {:?}",
+            secrets_hash_for_env_res.unwrap_err()
+        );
+        std::process::exit(1)
     } else {
-        let secrets_hash_for_env_res = persister_lock
-            .unwrap()
-            .get_secrets_for_env(user_selected_env, false);
+        let secrets_hash_for_env = secrets_hash_for_env_res.unwrap();
 
-        if secrets_hash_for_env_res.is_err() {
+        let variable_context_res = Scope::evaluate_variable_scope(
+            secrets_hash_for_env,
+            user_selected_env,
+        );
+
+        if variable_context_res.is_err() {
             error!(
                 "Error resolving variable scope: {:?}",
-                secrets_hash_for_env_res.unwrap_err()
+                variable_context_res.unwrap_err()
             );
             std::process::exit(1)
         } else {
-            let variable_context_res = Scope::evaluate_variable_scope(
-                secrets_hash_for_env_res.unwrap(),
-                user_selected_env,
-            );
-
-            if variable_context_res.is_err() {
-                error!(
-                    "Error resolving variable scope: {:?}",
-                    variable_context_res.unwrap_err()
-                );
-                std::process::exit(1)
-            } else {
-                GlobalScopeProvider::set(variable_context_res.unwrap());
-            }
+            GlobalScopeProvider::set(variable_context_res.unwrap());
         }
     }
 }
@@ -0,0 +1,80 @@
+use hcl::{Block, Expression};
+use serde::{Deserialize, Serialize};
+
+use crate::parser::{
+    drop_block::DropBlock,
+    drop_id::DropId,
+    hcl_block::HclObject,
+    types::{DropBlockType, DropResourceType},
+};
+
+use super::BlockParser;
+
+/// `type` values of an auth block
+pub const AUTH_TYPES: [&str; 7] = ["bearer", "basic", "api_key", "digest", "oauth2", "aws_sigv4", "hmac"];
+
+/// named auth scheme, referenced from a call,
+/// module or environment with `auth = auth.[name]`
+///
+/// `auth "api_user" {
+///     type = "bearer"
+///     token = secrets.api_token
+/// }`
+#[derive(Deserialize, Serialize, Debug)]
+pub struct AuthBlock {
+    pub attributes: HclObject,
+}
+
+impl AuthBlock {
+    pub fn get_drop_block(
+        block: Block,
+        drop_id: DropId,
+        file_name: &str,
+    ) -> Result<DropBlock, anyhow::Error> {
+        let body_res: Result<HclObject, hcl::Error> = hcl::from_body(block.body.clone());
+
+        let attributes = match body_res {
+            Ok(attributes) => attributes,
+            Err(err) => {
+                return Err(BlockParser::handle_block_parse_error(
+                    &err.to_string(),
+                    &drop_id,
+                    file_name,
+                ))
+            }
+        };
+
+        // values are evaluated with the scope,
+        // but the type must be written out
+        let auth_type = attributes
+            .iter()
+            .find(|(key, _)| key.to_string() == "type")
+            .map(|(_, expr)| expr);
+
+        match auth_type {
+            Some(Expression::String(auth_type)) if AUTH_TYPES.contains(&auth_type.as_str()) => {}
+            Some(Expression::String(auth_type)) => {
+                return Err(BlockParser::handle_block_parse_error(
+                    &format!("invalid auth type {auth_type}, expected one of {}", AUTH_TYPES.join(", ")),
+                    &drop_id,
+                    file_name,
+                ))
+            }
+            _ => {
+                return Err(BlockParser::handle_block_parse_error(
+                    &format!("auth block must set type to one of {}", AUTH_TYPES.join(", ")),
+                    &drop_id,
+                    file_name,
+                ))
+            }
+        }
+
+        Ok(DropBlock::new(
+            drop_id,
+            DropBlockType::Auth(AuthBlock { attributes }),
+            Some(block),
+            file_name,
+            DropResourceType::Auth,
+        ))
+    }
+}
@@ -1,7 +1,12 @@
-use hcl::Block;
+use hcl::{Block, Expression};
 use serde::{Deserialize, Serialize};
 
-use crate::parser::{drop_block::DropBlock, drop_id::DropId, hcl_block::HclObject, types::{DropBlockType, DropResourceType}};
+use crate::{
+    action::{pre_action::BEFORE_ACTIONS, AFTER_ON_VALUES},
+    constants::{AFTER_ONLY_IF_ASSERTS_PASS_PROP, AFTER_ON_PROP, TYPE_PROP},
+    parser::{drop_block::DropBlock, drop_id::DropId, hcl_block::HclObject, types::{DropBlockType, DropResourceType}},
+    plugin::PluginRegistry,
+};
 
 use super::BlockParser;
 
@@ -13,10 +18,25 @@ pub struct CallBlock {
     pub path: Option<hcl::Expression>,
     pub headers: Option<Vec<hcl::Expression>>,
     pub body: Option<hcl::Expression>,
+    pub before: Option<Vec<HclObject>>,
     pub after: Option<Vec<HclObject>>,
     pub outputs: Option<Vec<hcl::Traversal>>,
     pub inputs: Option<hcl::Expression>,
     pub assert: Option<hcl::Object<hcl::ObjectKey, hcl::Expression>>,
+    pub timeout: Option<hcl::Expression>,
+    pub retries: Option<hcl::Expression>,
+    pub retry_on: Option<hcl::Expression>,
+    pub backoff: Option<hcl::Expression>,
+    pub proxy: Option<hcl::Expression>,
+    pub no_proxy: Option<hcl::Expression>,
+    pub resolve: Option<hcl::Expression>,
+    pub follow_redirects: Option<hcl::Expression>,
+    pub max_redirects: Option<hcl::Expression>,
+    pub cookies: Option<hcl::Expression>,
+    pub auth: Option<hcl::Expression>,
+    pub script_before: Option<hcl::Expression>,
+    pub script_after: Option<hcl::Expression>,
+    pub script_timeout: Option<hcl::Expression>,
 }
 
 impl CallBlock {
@@ -37,9 +57,19 @@ impl CallBlock {
 
         } else {
 
+        let call_block: CallBlock = call_block.unwrap();
+
+        let actions_res = CallBlock::validate_actions(call_block.before.as_deref(), "before", &BEFORE_ACTIONS)
+            .and_then(|_| CallBlock::validate_actions(call_block.after.as_deref(), "after", &PluginRegistry::get().get_after_action_types()))
+            .and_then(|_| CallBlock::validate_after_conditions(call_block.after.as_deref()));
+
+        if let Err(error_msg) = actions_res {
+            return Err(BlockParser::handle_block_parse_error(&error_msg, &drop_id, file_name));
+        }
+
         Ok(DropBlock::new(
             drop_id,
-            DropBlockType::Call(call_block.unwrap()),
+            DropBlockType::Call(call_block),
             Some(block),
             file_name,
             DropResourceType::Call,
@@ -47,3 +77,69 @@ impl CallBlock {
     }
     }
 }
+
+impl CallBlock {
+    /// actions are checked when the file is parsed- values are
+    /// evaluated when the call runs, but the type must be written out
+    fn validate_actions(
+        actions: Option<&[HclObject]>,
+        kind: &str,
+        action_types: &[(&str, &[&str])],
+    ) -> Result<(), String> {
+        let type_names: Vec<&str> = action_types.iter().map(|(action_type, _)| *action_type).collect();
+
+        for action in actions.unwrap_or_default() {
+            let get_expr = |key: &str| {
+                action
+                    .iter()
+                    .find(|(object_key, _)| object_key.to_string() == key)
+                    .map(|(_, expr)| expr)
+            };
+
+            let action_type = match get_expr(TYPE_PROP) {
+                Some(Expression::String(action_type)) => action_type,
+                _ => {
+                    return Err(format!(
+                        "{kind} actions must set type to one of {}",
+                        type_names.join(", ")
+                    ))
+                }
+            };
+
+            let Some((_, required_keys)) = action_types.iter().find(|(name, _)| name == action_type) else {
+                return Err(format!(
+                    "invalid {kind} action type {action_type}, expected one of {}",
+                    type_names.join(", ")
+                ));
+            };
+
+            for key in *required_keys {
+                if get_expr(key).is_none() {
+                    return Err(format!("{kind} action {action_type} must set {key}"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `on` and `only_if_asserts_pass` are literals- `when`
+    /// is an expression on the response, checked when it runs
+    fn validate_after_conditions(actions: Option<&[HclObject]>) -> Result<(), String> {
+        for action in actions.unwrap_or_default() {
+            for (key, expr) in action.iter() {
+                let key = key.to_string();
+
+                if key == AFTER_ON_PROP && !matches!(expr, Expression::String(on) if AFTER_ON_VALUES.contains(&on.as_str())) {
+                    return Err(format!("after action on must be one of {}", AFTER_ON_VALUES.join(", ")));
+                }
+
+                if key == AFTER_ONLY_IF_ASSERTS_PASS_PROP && !matches!(expr, Expression::Bool(_)) {
+                    return Err(format!("after action {AFTER_ONLY_IF_ASSERTS_PASS_PROP} must be true or false"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
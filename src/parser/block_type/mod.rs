@@ -3,11 +3,14 @@
 /// and the values themselves are used
 /// by the hcl parser to perform initial
 /// input validation
+pub mod auth;
 pub mod call;
 pub mod chain;
 pub mod env;
 pub mod module;
 pub mod run;
+pub mod secret_providers;
+pub mod secrets;
 use anyhow::anyhow;
 use colored::Colorize;
 
@@ -0,0 +1,90 @@
+use hcl::Block;
+use serde::{Deserialize, Serialize};
+
+use crate::parser::{
+    drop_block::DropBlock,
+    drop_id::DropId,
+    types::{DropBlockType, DropResourceType},
+};
+
+use super::BlockParser;
+
+/// ordered secret sources for an environment- later
+/// layers overwrite keys from earlier layers
+///
+/// `secret_providers "staging" {
+///     layers = [
+///         { type = "sqlite" },
+///         { type = "file", path = "secrets.staging.json" },
+///         { type = "exec", command = ["pass", "show", "drop/staging"] },
+///     ]
+///     recipients = ["age1..."]
+/// }`
+///
+/// `recipients` are the age public keys the shared
+/// encrypted secrets file is encrypted to
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SecretProvidersBlock {
+    #[serde(skip)]
+    pub env: Option<String>,
+    pub layers: Option<Vec<SecretProviderConfig>>,
+    pub recipients: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SecretProviderConfig {
+    /// the local secret store, `drop secret set`
+    Sqlite,
+    /// json, yaml or dotenv file, format inferred
+    /// from the extension unless set
+    File {
+        path: String,
+        format: Option<String>,
+    },
+    /// command printing a json object to stdout
+    Exec { command: Vec<String> },
+    /// age encrypted file committed with the drop files,
+    /// `secrets.[env].enc.json` unless set
+    EncryptedFile { path: Option<String> },
+}
+
+impl SecretProvidersBlock {
+    pub fn get_drop_block(
+        block: Block,
+        drop_id: DropId,
+        env: Option<String>,
+        file_name: &str,
+    ) -> Result<DropBlock, anyhow::Error> {
+        let providers_res: Result<SecretProvidersBlock, hcl::Error> =
+            hcl::from_body(block.body.clone());
+
+        if providers_res.is_err() {
+            let error_msg = providers_res.unwrap_err().to_string();
+
+            Err(BlockParser::handle_block_parse_error(
+                &error_msg, &drop_id, file_name,
+            ))
+        } else {
+            let mut providers_block = providers_res.unwrap();
+
+            providers_block.env = env;
+
+            Ok(DropBlock::new(
+                drop_id,
+                DropBlockType::SecretProviders(providers_block),
+                Some(block),
+                file_name,
+                DropResourceType::SecretProviders,
+            ))
+        }
+    }
+
+    pub fn applies_to_env(&self, env: &str) -> bool {
+        match &self.env {
+            Some(block_env) => block_env == env,
+            None => true,
+        }
+    }
+}
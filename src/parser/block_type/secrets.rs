@@ -0,0 +1,73 @@
+use hcl::Block;
+use serde::{Deserialize, Serialize};
+
+use crate::parser::{
+    drop_block::DropBlock,
+    drop_id::DropId,
+    types::{DropBlockType, DropResourceType},
+};
+
+use super::BlockParser;
+
+/// declared secret, e.g.
+///
+/// `secrets {
+///     csrf_token = { description = "csrf token from signup", required = true }
+/// }`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SecretDeclaration {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    pub default: Option<String>,
+}
+
+/// secrets block- an unlabeled block applies to
+/// every environment, a labeled block only to
+/// the environment in the label
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SecretsBlock {
+    pub env: Option<String>,
+    pub declarations: hcl::Map<String, SecretDeclaration>,
+}
+
+impl SecretsBlock {
+    pub fn get_drop_block(
+        block: Block,
+        drop_id: DropId,
+        env: Option<String>,
+        file_name: &str,
+    ) -> Result<DropBlock, anyhow::Error> {
+        let declarations_res: Result<hcl::Map<String, SecretDeclaration>, hcl::Error> =
+            hcl::from_body(block.body.clone());
+
+        if declarations_res.is_err() {
+            let error_msg = declarations_res.unwrap_err().to_string();
+
+            Err(BlockParser::handle_block_parse_error(
+                &error_msg, &drop_id, file_name,
+            ))
+        } else {
+            let secrets_block = SecretsBlock {
+                env,
+                declarations: declarations_res.unwrap(),
+            };
+
+            Ok(DropBlock::new(
+                drop_id,
+                DropBlockType::Secrets(secrets_block),
+                Some(block),
+                file_name,
+                DropResourceType::Secrets,
+            ))
+        }
+    }
+
+    pub fn applies_to_env(&self, env: &str) -> bool {
+        match &self.env {
+            Some(block_env) => block_env == env,
+            None => true,
+        }
+    }
+}
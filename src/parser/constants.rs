@@ -7,6 +7,9 @@ pub const PATCH_BLOCK_KEY: &str = "patch";
 pub const GLOBAL_MOD_BLOCK_KEY: &str = "global";
 pub const MOD_BLOCK_KEY: &str = "mod";
 pub const ENVIRONMENT_BLOCK_KEY: &str = "environment";
+pub const SECRETS_BLOCK_KEY: &str = "secrets";
+pub const SECRET_PROVIDERS_BLOCK_KEY: &str = "secret_providers";
+pub const AUTH_BLOCK_KEY: &str = "auth";
 
 pub const RUN_BLOCK_KEY: &str = "run";
 pub const CHAIN_BLOCK_KEY: &str = "chain";
@@ -2,19 +2,23 @@ use anyhow::anyhow;
 use hcl::{self, Block};
 use serde::{Deserialize, Serialize};
 
+use crate::util::exit;
 use crate::parser::block_type::env::DropEnvironment;
 use crate::parser::block_type::module::DropModule;
 use crate::parser::constants::*;
 use crate::parser::hcl_block::HclBlock;
 
+use super::block_type::auth::AuthBlock;
 use super::block_type::call::CallBlock;
 use super::block_type::chain::{ChainBlock, ChainNode};
 use super::block_type::run::RunBlock;
+use super::block_type::secret_providers::SecretProvidersBlock;
+use super::block_type::secrets::SecretsBlock;
 use super::types::{DropBlockType, DropResourceType};
 use super::{drop_id::DropId, hcl_block::HclObject};
 
-static NON_MODULE_BLOCK_TYPES: &str = "global mod environment";
-static NO_LABEL_BLOCKS: &str = "global";
+static NON_MODULE_BLOCK_TYPES: &str = "global mod environment secrets secret_providers auth";
+static NO_LABEL_BLOCKS: &str = "global secrets secret_providers";
 pub static CALL_BLOCKS: &str = "get post put delete run chain";
 
 // structured data from user input hcl block
@@ -63,11 +67,13 @@ impl DropBlock {
         let block_title = if labels.is_empty() {
             if !NO_LABEL_BLOCKS.contains(block_type) {
                 println!("{file_name} block {block_type} must have a label");
-                std::process::exit(1);
+                exit::exit(1);
             }
 
             match block_type {
                 GLOBAL_MOD_BLOCK_KEY => GLOBAL_MOD_BLOCK_KEY,
+                SECRETS_BLOCK_KEY => SECRETS_BLOCK_KEY,
+                SECRET_PROVIDERS_BLOCK_KEY => SECRET_PROVIDERS_BLOCK_KEY,
                 _ => "",
             }
         } else {
@@ -143,6 +149,32 @@ impl DropBlock {
 
                 ChainNode::get_chain_node_block(hcl_block, drop_id_struct, file_name)
             },
+            DropResourceType::Secrets => {
+                // the label, if any, scopes the declarations to an environment
+                let env = labels.first().map(|label| label.as_str().to_string());
+
+                SecretsBlock::get_drop_block(
+                    hcl_block,
+                    get_drop_id(DropResourceType::Secrets),
+                    env,
+                    file_name,
+                )
+            }
+            DropResourceType::SecretProviders => {
+                let env = labels.first().map(|label| label.as_str().to_string());
+
+                SecretProvidersBlock::get_drop_block(
+                    hcl_block,
+                    get_drop_id(DropResourceType::SecretProviders),
+                    env,
+                    file_name,
+                )
+            }
+            DropResourceType::Auth => AuthBlock::get_drop_block(
+                hcl_block,
+                get_drop_id(DropResourceType::Auth),
+                file_name,
+            ),
         }
     }
 
@@ -157,7 +189,7 @@ impl DropBlock {
             );
             log::trace!("");
             println!("{panic_msg}");
-            std::process::exit(1)
+            exit::exit(1)
         }
     }
 }
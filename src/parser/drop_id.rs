@@ -1,3 +1,4 @@
+use crate::util::exit;
 use serde::{Deserialize, Serialize};
 use anyhow::anyhow;
 
@@ -97,7 +98,7 @@ impl DropId {
             _ => {
                 log::trace!("");
                 log::error!("invalid call type in drop_id: {call}");
-                std::process::exit(1)},
+                exit::exit(1)},
         }
     }
 
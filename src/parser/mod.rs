@@ -1,12 +1,18 @@
-use block_type::module::DropModule;
+use block_type::{
+    module::DropModule, secret_providers::{SecretProviderConfig, SecretProvidersBlock}, secrets::SecretDeclaration,
+};
 use colored::Colorize;
 use drop_block::DropBlock;
 use drop_id::DropId;
 use hcl::Body;
+use indexmap::IndexMap;
 use log_derive::logfn;
-use std::{collections::HashSet, fs, path::PathBuf, sync::OnceLock};
+use std::{collections::HashSet, fs, path::PathBuf, sync::{Arc, RwLock}};
 use types::{DropBlockType, DropResourceType};
 
+use crate::util::exit;
+use crate::text_println;
+
 pub mod block_type;
 pub mod constants;
 pub mod drop_block;
@@ -15,36 +21,53 @@ pub mod file_walker;
 pub mod hcl_block;
 pub mod types;
 
-static GLOBAL_DROP_CONFIG_PROVIDER: OnceLock<GlobalDropConfig> = OnceLock::new();
+/// replaced when an embedded session selects another
+/// directory- see `CmdContext`
+static GLOBAL_DROP_CONFIG_PROVIDER: RwLock<Option<Arc<GlobalDropConfig>>> = RwLock::new(None);
 
 pub struct GlobalDropConfigProvider {}
 
 impl GlobalDropConfigProvider {
-    pub fn set(global_drop_config: GlobalDropConfig) {
-        let cell_set_result = GLOBAL_DROP_CONFIG_PROVIDER.set(global_drop_config);
-        if cell_set_result.is_err() {
-            log::error!(
-                "Error setting global config provider: {:?}",
-                cell_set_result.unwrap_err()
-            )
+    pub fn set(global_drop_config: GlobalDropConfig) -> Arc<GlobalDropConfig> {
+        let global_drop_config = Arc::new(global_drop_config);
+
+        GlobalDropConfigProvider::set_arc(global_drop_config.clone());
+
+        global_drop_config
+    }
+
+    pub fn set_arc(global_drop_config: Arc<GlobalDropConfig>) {
+        match GLOBAL_DROP_CONFIG_PROVIDER.write() {
+            Ok(mut provider) => *provider = Some(global_drop_config),
+            Err(err) => log::error!("Error setting global config provider: {err:?}"),
         }
     }
 
-    pub fn get() -> &'static GlobalDropConfig {
-        GLOBAL_DROP_CONFIG_PROVIDER.get().unwrap()
+    pub fn get() -> Arc<GlobalDropConfig> {
+        GLOBAL_DROP_CONFIG_PROVIDER
+            .read()
+            .ok()
+            .and_then(|provider| provider.clone())
+            .unwrap()
     }
 }
 
 /// parsed hcl blocks prior
 /// to evaluation by interpreter
 #[derive(Debug)]
+///
+/// blocks are shared, so runs keep the blocks
+/// they evaluate when the config is replaced
 pub struct GlobalDropConfig {
-    pub hits: Vec<DropBlock>,
-    pub runs: Vec<DropBlock>,
-    pub chains: Vec<DropBlock>,
-    pub chain_nodes: Vec<DropBlock>,
-    pub modules: Vec<DropBlock>,
-    pub environments: Vec<DropBlock>,
+    pub hits: Vec<Arc<DropBlock>>,
+    pub runs: Vec<Arc<DropBlock>>,
+    pub chains: Vec<Arc<DropBlock>>,
+    pub chain_nodes: Vec<Arc<DropBlock>>,
+    pub modules: Vec<Arc<DropBlock>>,
+    pub environments: Vec<Arc<DropBlock>>,
+    pub secrets: Vec<Arc<DropBlock>>,
+    pub secret_providers: Vec<Arc<DropBlock>>,
+    pub auths: Vec<Arc<DropBlock>>,
 }
 
 impl GlobalDropConfig {
@@ -56,6 +79,9 @@ impl GlobalDropConfig {
             chain_nodes: Vec::new(),
             modules: Vec::new(),
             environments: Vec::new(),
+            secrets: Vec::new(),
+            secret_providers: Vec::new(),
+            auths: Vec::new(),
         }
     }
 
@@ -76,7 +102,7 @@ impl GlobalDropConfig {
             if body.is_err() {
                 println!("");
                 log::error!("error parsing file {}: \n{}\n", file_name.yellow(), body.unwrap_err().to_string().red());
-                std::process::exit(1)
+                exit::exit(1)
             }
 
             // warn if error in unevaluated block
@@ -146,6 +172,7 @@ impl GlobalDropConfig {
 
         for container in unevaluated_blocks.drain(..) {
             let rt = container.resource_type;
+            let container = Arc::new(container);
 
             match rt {
                 DropResourceType::Call => global_drop_config.hits.push(container),
@@ -154,6 +181,9 @@ impl GlobalDropConfig {
                 DropResourceType::Environment => global_drop_config.environments.push(container),
                 DropResourceType::Chain => global_drop_config.chains.push(container),
                 DropResourceType::ChainNode => global_drop_config.chain_nodes.push(container),
+                DropResourceType::Secrets => global_drop_config.secrets.push(container),
+                DropResourceType::SecretProviders => global_drop_config.secret_providers.push(container),
+                DropResourceType::Auth => global_drop_config.auths.push(container),
             }
         }
 
@@ -214,6 +244,22 @@ impl GlobalDropConfig {
     }
 
     pub fn get_all_resource_type_in_modules(&self, selected_module: &str) -> Vec<String> {
+        let all_calls_in_modules = self.get_drop_ids_in_module(selected_module);
+
+        if all_calls_in_modules.is_empty() {
+            log::trace!("");
+            log::error!("No calls found in module {}\n", selected_module.yellow());
+            exit::exit(1);
+        }
+
+        text_println!("calls in module: {}\n", selected_module.yellow());
+
+        all_calls_in_modules
+    }
+
+    /// the calls, runs and chains in the module- empty
+    /// when there are none
+    pub fn get_drop_ids_in_module(&self, selected_module: &str) -> Vec<String> {
         let get_resource_in_module = |drop_resource: DropResourceType| {
             let drop_blocks = match drop_resource {
                 DropResourceType::Call => &self.hits,
@@ -222,6 +268,9 @@ impl GlobalDropConfig {
                 DropResourceType::ChainNode => &self.chain_nodes,
                 DropResourceType::Module => &self.modules,
                 DropResourceType::Environment => &self.environments,
+                DropResourceType::Secrets => &self.secrets,
+                DropResourceType::SecretProviders => &self.secret_providers,
+                DropResourceType::Auth => &self.auths,
             };
 
             if drop_blocks.is_empty() {
@@ -256,14 +305,129 @@ impl GlobalDropConfig {
         all_calls_in_modules.extend(all_runs_in_modules);
         all_calls_in_modules.extend(all_chains_in_modules);
 
-        if all_calls_in_modules.is_empty() {
-            log::trace!("");
-            log::error!("No calls found in module {}\n", selected_module.yellow());
-            std::process::exit(1);
+        all_calls_in_modules
+    }
+
+    /// secret declarations for the environment, keyed by secret
+    /// name with the file they were declared in- declarations
+    /// labeled with the environment take precedence
+    pub fn get_secret_declarations(
+        &self,
+        env: &str,
+    ) -> IndexMap<String, (&SecretDeclaration, &str)> {
+        let mut declarations = IndexMap::<String, (&SecretDeclaration, &str)>::new();
+
+        let mut secrets_blocks: Vec<&DropBlock> = self.secrets.iter().map(|block| block.as_ref()).collect();
+
+        // unlabeled blocks first so env specific blocks overwrite them
+        secrets_blocks.sort_by_key(|block| match &block.drop_block {
+            DropBlockType::Secrets(secrets_block) => secrets_block.env.is_some(),
+            _ => false,
+        });
+
+        for block in secrets_blocks {
+            if let DropBlockType::Secrets(secrets_block) = &block.drop_block {
+                if !secrets_block.applies_to_env(env) {
+                    continue;
+                }
+
+                for (key, declaration) in &secrets_block.declarations {
+                    declarations.insert(key.to_string(), (declaration, &block.file_name));
+                }
+            }
         }
 
-        println!("calls in module: {}\n", selected_module.yellow());
+        declarations
+    }
 
-        all_calls_in_modules
+    /// secret provider layers for the environment- a block labeled
+    /// with the environment replaces an unlabeled block. the local
+    /// secret store and the shared encrypted file are used if no
+    /// block declares layers
+    pub fn get_secret_provider_layers(&self, env: &str) -> Vec<SecretProviderConfig> {
+        let layers = self.get_secret_providers_attribute(env, |providers_block| {
+            providers_block.layers.as_ref()
+        });
+
+        match layers {
+            Some(layers) => layers.clone(),
+            None => vec![
+                SecretProviderConfig::Sqlite,
+                SecretProviderConfig::EncryptedFile { path: None },
+            ],
+        }
+    }
+
+    /// age recipients of the shared encrypted secrets file
+    pub fn get_secret_recipients(&self, env: &str) -> Vec<String> {
+        self.get_secret_providers_attribute(env, |providers_block| {
+            providers_block.recipients.as_ref()
+        })
+        .cloned()
+        .unwrap_or_default()
+    }
+
+    fn get_secret_providers_attribute<'a, T>(
+        &'a self,
+        env: &str,
+        get_attribute: impl Fn(&'a SecretProvidersBlock) -> Option<&'a T>,
+    ) -> Option<&'a T> {
+        let mut attribute: Option<&T> = None;
+
+        for block in &self.secret_providers {
+            if let DropBlockType::SecretProviders(providers_block) = &block.drop_block {
+                if !providers_block.applies_to_env(env) {
+                    continue;
+                }
+
+                let Some(value) = get_attribute(providers_block) else {
+                    continue;
+                };
+
+                if providers_block.env.is_some() || attribute.is_none() {
+                    attribute = Some(value);
+                }
+            }
+        }
+
+        attribute
+    }
+
+    /// every key referenced as `secrets.[key]` in
+    /// calls, runs, chains, modules, environments and auth blocks
+    pub fn get_referenced_secret_keys(&self) -> HashSet<String> {
+        let prefix = format!("{}.", crate::constants::SECRET_OBJECT_VAR_PREFIX);
+
+        let mut referenced = HashSet::<String>::new();
+
+        let all_blocks = self
+            .hits
+            .iter()
+            .chain(&self.runs)
+            .chain(&self.chains)
+            .chain(&self.chain_nodes)
+            .chain(&self.modules)
+            .chain(&self.environments)
+            .chain(&self.auths);
+
+        for block in all_blocks {
+            let serialized = match &block.hcl_block {
+                Some(hcl_block) => hcl::to_string(hcl_block).unwrap_or_default(),
+                None => continue,
+            };
+
+            for (idx, _) in serialized.match_indices(&prefix) {
+                let key: String = serialized[idx + prefix.len()..]
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+                    .collect();
+
+                if !key.is_empty() {
+                    referenced.insert(key);
+                }
+            }
+        }
+
+        referenced
     }
 }
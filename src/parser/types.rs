@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use super::{block_type::{call::CallBlock, chain::{ChainBlock, ChainNode}, run::RunBlock}, constants::*, hcl_block::HclObject};
+use super::{block_type::{auth::AuthBlock, call::CallBlock, chain::{ChainBlock, ChainNode}, run::RunBlock, secret_providers::SecretProvidersBlock, secrets::SecretsBlock}, constants::*, hcl_block::HclObject};
 use anyhow::anyhow;
 
 // DropBlockType and DropResourceType have identical members
@@ -16,6 +16,9 @@ pub enum DropBlockType {
     Run(RunBlock),
     Chain(ChainBlock),
     ChainNode(ChainNode),
+    Secrets(SecretsBlock),
+    SecretProviders(SecretProvidersBlock),
+    Auth(AuthBlock),
 }
 
 
@@ -27,6 +30,9 @@ pub enum DropResourceType {
     Run,
     Chain,
     ChainNode,
+    Secrets,
+    SecretProviders,
+    Auth,
 }
 
 impl DropResourceType {
@@ -43,6 +49,9 @@ impl DropResourceType {
             RUN_BLOCK_KEY =>  Ok(DropResourceType::Run),
             CHAIN_BLOCK_KEY => Ok(DropResourceType::Chain),
             CHAIN_NODE_KEY => Ok(DropResourceType::ChainNode),
+            SECRETS_BLOCK_KEY => Ok(DropResourceType::Secrets),
+            SECRET_PROVIDERS_BLOCK_KEY => Ok(DropResourceType::SecretProviders),
+            AUTH_BLOCK_KEY => Ok(DropResourceType::Auth),
             _ => Err(anyhow!("invalid block type: '{raw_block_type}' in {file_name}")),
         }
     }
@@ -5,10 +5,12 @@ use hcl::Value;
 use indexmap::IndexMap;
 use lazy_static::lazy_static;
 use rusqlite::Result;
+use serde::Serialize;
 use sqlite_persister::SqlitePersister;
 
 use crate::record::CallRecord;
 
+pub mod secret_cipher;
 pub mod sqlite_persister;
 
 lazy_static! {
@@ -19,34 +21,147 @@ pub struct PersisterProvider {}
 
 impl PersisterProvider {
 
+    /// the lock is poisoned when an embedded session catches
+    /// a panic or exit while it's held- each persister call is
+    /// one sqlite statement, so the connection is still usable
     pub fn get_lock_to_persister() -> Option<MutexGuard<'static, Box<dyn Persister>>> {
 
-        let lock = GLOBAL_PERSISTER_PROVIDER.lock();
+        let lock = GLOBAL_PERSISTER_PROVIDER.lock().unwrap_or_else(|err| {
+            log::trace!("PersisterProvider recovering poisoned lock");
+            GLOBAL_PERSISTER_PROVIDER.clear_poison();
+            err.into_inner()
+        });
 
-        if lock.is_err() {
-            log::trace!("error unwrapping PersisterProvider");
-            None 
-        } else {
-            Some(lock.unwrap())
-        }
+        Some(lock)
     }
 }
 
 pub trait Persister: Send + Sync {
     fn persist_call_record(&mut self, call_record: &CallRecord) -> Result<bool>;
-    fn insert_secret_into_env(&mut self, key: &str, value: &str, env: &str, is_overwrite: bool);
-    fn get_all_secrets(&mut self);
-    fn get_secrets_for_env(
+    /// `set_by` records the source of the value, e.g.
+    /// `cli` or `after example.post.login`
+    fn insert_secret_into_env(
         &mut self,
+        key: &str,
+        value: &str,
         env: &str,
-        is_cli: bool,
-    ) -> Result<IndexMap<String, Value>, anyhow::Error>;
+        is_overwrite: bool,
+        set_by: &str,
+    );
+    /// replaces the secret without printing, for
+    /// secrets stored while a call runs
+    fn set_secret_in_env(&mut self, key: &str, value: &str, env: &str, set_by: &str) -> Result<(), anyhow::Error>;
+    /// every stored secret, values still encrypted
+    fn get_all_secrets(&mut self) -> Result<Vec<Secret>, anyhow::Error>;
+    /// decrypted secrets for the env
+    fn get_secrets_for_env(&mut self, env: &str) -> Result<IndexMap<String, Value>, anyhow::Error>;
     fn delete_secret_in_env(&mut self, key: &str, env: &str);
+    /// replaces the cookie with the same
+    /// domain, path and name, value encrypted
+    fn set_cookie_in_env(&mut self, cookie: &StoredCookie, env: &str) -> Result<(), anyhow::Error>;
+    fn delete_cookie_in_env(&mut self, cookie: &StoredCookie, env: &str) -> Result<(), anyhow::Error>;
+    /// decrypted cookies for the env- expired
+    /// cookies are removed first
+    fn get_cookies_for_env(&mut self, env: &str) -> Result<Vec<StoredCookie>, anyhow::Error>;
+    /// every cookie in the env, or only a domain's-
+    /// returns the number deleted
+    fn clear_cookies_in_env(&mut self, env: &str, domain: Option<&str>) -> Result<usize, anyhow::Error>;
+    /// decrypted cached token for an oauth2 client
+    fn get_token_in_env(&mut self, cache_key: &str, env: &str) -> Result<Option<StoredToken>, anyhow::Error>;
+    /// replaces the cached token, values encrypted
+    fn set_token_in_env(&mut self, cache_key: &str, token: &StoredToken, env: &str) -> Result<(), anyhow::Error>;
+    fn delete_token_in_env(&mut self, cache_key: &str, env: &str) -> Result<(), anyhow::Error>;
+    /// a non-secret value set by an after action, stored as plain text
+    fn set_variable_in_env(&mut self, key: &str, value: &str, env: &str, set_by: &str) -> Result<(), anyhow::Error>;
+    fn get_variables_for_env(&mut self, env: &str) -> Result<IndexMap<String, Value>, anyhow::Error>;
+}
+
+/// an oauth2 access token, cached per environment and
+/// client until it expires or the server rejects it
+#[derive(Debug, Clone)]
+pub struct StoredToken {
+    pub access_token: String,
+    pub token_type: String,
+    /// unix seconds- tokens without an
+    /// expiry are used until rejected
+    pub expires_at: Option<i64>,
+    pub refresh_token: Option<String>,
 }
 
-#[derive(Debug, Getters)]
+/// a cookie in an environment's cookie jar
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredCookie {
+    pub domain: String,
+    pub path: String,
+    pub name: String,
+    pub value: String,
+    /// unix seconds- cookies without an expiry are
+    /// kept until they are replaced or cleared
+    pub expires_at: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    /// set without a Domain attribute, so only
+    /// sent to the exact host that set it
+    pub host_only: bool,
+    pub updated_at: Option<String>,
+}
+
+impl StoredCookie {
+    pub fn masked(&self) -> StoredCookie {
+        StoredCookie {
+            value: "********".to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Getters, Clone, Serialize)]
 pub struct Secret {
     key: String,
     value: String,
+    #[serde(rename = "env")]
     _env: String,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    set_by: Option<String>,
+}
+
+impl Secret {
+    pub fn new(key: &str, value: &str, env: &str) -> Secret {
+        Secret {
+            key: key.to_string(),
+            value: value.to_string(),
+            _env: env.to_string(),
+            created_at: None,
+            updated_at: None,
+            set_by: None,
+        }
+    }
+
+    pub fn with_metadata(
+        mut self,
+        created_at: Option<String>,
+        updated_at: Option<String>,
+        set_by: Option<String>,
+    ) -> Secret {
+        self.created_at = created_at;
+        self.updated_at = updated_at;
+        self.set_by = set_by;
+        self
+    }
+
+    pub fn env(&self) -> &String {
+        &self._env
+    }
+
+    pub fn with_value(&self, value: &str) -> Secret {
+        Secret {
+            value: value.to_string(),
+            ..self.clone()
+        }
+    }
+
+    pub fn masked(&self) -> Secret {
+        self.with_value("********")
+    }
 }
@@ -0,0 +1,162 @@
+use std::{fs, path::PathBuf};
+#[cfg(test)]
+use std::sync::Once;
+
+use anyhow::anyhow;
+use argon2::Argon2;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+
+/// passphrase the secret store key is derived from
+pub static MASTER_KEY_ENV_VAR: &str = "DROP_MASTER_KEY";
+
+/// path to a key file the secret store key is derived from
+pub static MASTER_KEY_FILE_ENV_VAR: &str = "DROP_MASTER_KEY_FILE";
+
+/// prefix for encrypted values in the secret store
+pub static ENCRYPTED_VALUE_PREFIX: &str = "enc:v1:";
+
+static DEFAULT_KEY_FILE_DIR: &str = ".drop";
+static DEFAULT_KEY_FILE_NAME: &str = "master.key";
+
+const NONCE_LEN: usize = 12;
+
+/// encrypts secret values at rest
+///
+/// the key is derived with argon2 from, in order:
+/// - the passphrase in `DROP_MASTER_KEY`
+/// - the contents of the file at `DROP_MASTER_KEY_FILE`
+/// - the contents of `~/.drop/master.key`, generated on first use
+///
+/// the salt is stored alongside the secrets, the key never is
+pub struct SecretCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl SecretCipher {
+    /// tests share the per process db, so they share a key
+    /// that isn't the user's `~/.drop/master.key`
+    #[cfg(test)]
+    pub fn set_test_key() {
+        static TEST_KEY: Once = Once::new();
+
+        TEST_KEY.call_once(|| std::env::set_var(MASTER_KEY_ENV_VAR, "drop tests"));
+    }
+
+    pub fn from_env(salt: &[u8]) -> Result<SecretCipher, anyhow::Error> {
+        let key_material = SecretCipher::get_key_material()?;
+
+        let mut key = [0u8; 32];
+
+        Argon2::default()
+            .hash_password_into(&key_material, salt, &mut key)
+            .map_err(|err| anyhow!("error deriving secret store key: {err}"))?;
+
+        Ok(SecretCipher {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        })
+    }
+
+    pub fn generate_salt() -> String {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        BASE64_STANDARD.encode(salt)
+    }
+
+    pub fn is_encrypted(value: &str) -> bool {
+        value.starts_with(ENCRYPTED_VALUE_PREFIX)
+    }
+
+    pub fn encrypt(&self, value: &str) -> Result<String, anyhow::Error> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, value.as_bytes())
+            .map_err(|err| anyhow!("error encrypting secret: {err}"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+
+        Ok(format!("{ENCRYPTED_VALUE_PREFIX}{}", BASE64_STANDARD.encode(payload)))
+    }
+
+    /// values stored before encryption was enabled
+    /// are returned as they are
+    pub fn decrypt(&self, value: &str) -> Result<String, anyhow::Error> {
+        let Some(encoded) = value.strip_prefix(ENCRYPTED_VALUE_PREFIX) else {
+            return Ok(value.to_string());
+        };
+
+        let payload = BASE64_STANDARD.decode(encoded)?;
+
+        if payload.len() < NONCE_LEN {
+            return Err(anyhow!("invalid encrypted secret"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                anyhow!(
+                    "error decrypting secret- check {MASTER_KEY_ENV_VAR} or {MASTER_KEY_FILE_ENV_VAR}"
+                )
+            })?;
+
+        Ok(String::from_utf8(plaintext)?)
+    }
+
+    fn get_key_material() -> Result<Vec<u8>, anyhow::Error> {
+        if let Ok(passphrase) = std::env::var(MASTER_KEY_ENV_VAR) {
+            if !passphrase.is_empty() {
+                return Ok(passphrase.into_bytes());
+            }
+        }
+
+        if let Ok(key_file) = std::env::var(MASTER_KEY_FILE_ENV_VAR) {
+            return fs::read(&key_file)
+                .map_err(|err| anyhow!("error reading {MASTER_KEY_FILE_ENV_VAR} {key_file}: {err}"));
+        }
+
+        SecretCipher::get_or_create_default_key_file()
+    }
+
+    fn get_or_create_default_key_file() -> Result<Vec<u8>, anyhow::Error> {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .map_err(|_| {
+                anyhow!("no home directory found- set {MASTER_KEY_ENV_VAR} or {MASTER_KEY_FILE_ENV_VAR}")
+            })?;
+
+        let key_dir = PathBuf::from(home).join(DEFAULT_KEY_FILE_DIR);
+        let key_file = key_dir.join(DEFAULT_KEY_FILE_NAME);
+
+        if key_file.exists() {
+            return Ok(fs::read(&key_file)?);
+        }
+
+        log::debug!("SecretCipher generating key file {key_file:?}");
+
+        fs::create_dir_all(&key_dir)?;
+
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        let encoded = BASE64_STANDARD.encode(key);
+
+        fs::write(&key_file, &encoded)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&key_file, fs::Permissions::from_mode(0o600))?;
+        }
+
+        Ok(encoded.into_bytes())
+    }
+}
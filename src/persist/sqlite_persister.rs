@@ -1,4 +1,4 @@
-use std::sync::Mutex;
+use std::{path::PathBuf, sync::Mutex};
 
 use colored::Colorize;
 use hcl::Value;
@@ -7,17 +7,23 @@ use log::{error, trace};
 use log_derive::logfn;
 use rusqlite::{Connection, Result};
 
-use crate::record::CallRecord;
+use crate::util::exit;
+use crate::{record::CallRecord, text_println, util::redact::Redactor};
 
-use super::{Persister, Secret};
+use super::{secret_cipher::SecretCipher, Persister, Secret, StoredCookie, StoredToken};
+
+static SALT_META_KEY: &str = "secret_salt";
+static KEY_CHECK_META_KEY: &str = "secret_key_check";
+static KEY_CHECK_VALUE: &str = "drop";
 
 pub struct SqlitePersister {
     conn: Mutex<Connection>,
+    cipher: Option<SecretCipher>,
 }
 
 impl SqlitePersister {
     pub fn init() -> SqlitePersister {
-        let conn = Connection::open("drop.db");
+        let conn = Connection::open(SqlitePersister::get_db_path());
 
         match conn {
             Ok(conn) => {
@@ -35,6 +41,9 @@ impl SqlitePersister {
 
                 trace!("SqlitePersister create drop_record call res: {drop_record_call:#?}");
 
+                // each request of a call with retries
+                SqlitePersister::add_missing_columns(&conn, "drop_record", &["attempts"]);
+
                 let secret_call = conn.execute(
                     "create table if not exists secrets (
                          id integer primary key,
@@ -48,16 +57,215 @@ impl SqlitePersister {
 
                 trace!("SqlitePersister create secrets call res: {secret_call:#?}");
 
-                SqlitePersister { conn: Mutex::new(conn) }
+                // secrets stored before metadata was tracked
+                // have no created_at, updated_at or set_by
+                SqlitePersister::add_missing_columns(
+                    &conn,
+                    "secrets",
+                    &["created_at", "updated_at", "set_by"],
+                );
+
+                let meta_call = conn.execute(
+                    "create table if not exists meta (
+                         key text primary key,
+                         value text not null
+                     )",
+                    (),
+                );
+
+                trace!("SqlitePersister create meta call res: {meta_call:#?}");
+
+                let cookies_call = conn.execute(
+                    "create table if not exists cookies (
+                         id integer primary key,
+                         env text not null,
+                         domain text not null,
+                         path text not null,
+                         name text not null,
+                         value text not null,
+                         expires_at integer,
+                         secure integer not null default 0,
+                         http_only integer not null default 0,
+                         host_only integer not null default 1,
+                         created_at text,
+                         updated_at text,
+                         UNIQUE(env, domain, path, name)
+                     )",
+                    (),
+                );
+
+                trace!("SqlitePersister create cookies call res: {cookies_call:#?}");
+
+                let tokens_call = conn.execute(
+                    "create table if not exists oauth_tokens (
+                         id integer primary key,
+                         env text not null,
+                         cache_key text not null,
+                         access_token text not null,
+                         token_type text not null,
+                         expires_at integer,
+                         refresh_token text,
+                         created_at text,
+                         UNIQUE(env, cache_key)
+                     )",
+                    (),
+                );
+
+                trace!("SqlitePersister create oauth_tokens call res: {tokens_call:#?}");
+
+                let variables_call = conn.execute(
+                    "create table if not exists variables (
+                         id integer primary key,
+                         key text not null,
+                         value text not null,
+                         env text not null,
+                         created_at text,
+                         updated_at text,
+                         set_by text,
+                         UNIQUE(key, env)
+                     )",
+                    (),
+                );
+
+                trace!("SqlitePersister create variables call res: {variables_call:#?}");
+
+                SqlitePersister {
+                    conn: Mutex::new(conn),
+                    cipher: None,
+                }
             }
             Err(err) => {
                 error!("SqlitePersister conn err {err:#?}");
-                std::process::exit(1);
+                exit::exit(1);
             }
         }
     }
 }
 
+impl SqlitePersister {
+    /// unit tests use a database of their own,
+    /// outside of the working directory
+    fn get_db_path() -> PathBuf {
+        if cfg!(test) {
+            let path = std::env::temp_dir().join(format!("drop-test-{}.db", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            return path;
+        }
+
+        PathBuf::from("drop.db")
+    }
+
+    /// text columns added after a table was created
+    fn add_missing_columns(conn: &Connection, table: &str, columns: &[&str]) {
+        let existing_columns: Vec<String> = match conn.prepare(&format!("PRAGMA table_info({table})")) {
+            Ok(mut table_info) => table_info
+                .query_map([], |row| row.get::<usize, String>(1))
+                .map(|rows| rows.filter_map(Result::ok).collect())
+                .unwrap_or_default(),
+            Err(err) => {
+                error!("SqlitePersister table_info err {err:#?}");
+                exit::exit(1);
+            }
+        };
+
+        for column in columns {
+            if existing_columns.iter().any(|existing| existing == column) {
+                continue;
+            }
+
+            let alter_call = conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {column} text"), ());
+
+            trace!("SqlitePersister add {table} column {column} res: {alter_call:#?}");
+        }
+    }
+
+    /// the cipher is only derived when a secret is read or
+    /// written, and existing plaintext secrets are encrypted
+    /// the first time it is
+    fn get_cipher(&mut self) -> Result<&SecretCipher, anyhow::Error> {
+        if self.cipher.is_none() {
+            let salt = match self.get_meta(SALT_META_KEY)? {
+                Some(salt) => salt,
+                None => {
+                    let salt = SecretCipher::generate_salt();
+                    self.set_meta(SALT_META_KEY, &salt)?;
+                    salt
+                }
+            };
+
+            let cipher = SecretCipher::from_env(salt.as_bytes())?;
+
+            match self.get_meta(KEY_CHECK_META_KEY)? {
+                Some(key_check) => {
+                    let decrypted = cipher.decrypt(&key_check)?;
+
+                    if decrypted != KEY_CHECK_VALUE {
+                        return Err(anyhow::anyhow!("secret store key check failed"));
+                    }
+                }
+                None => {
+                    let key_check = cipher.encrypt(KEY_CHECK_VALUE)?;
+                    self.set_meta(KEY_CHECK_META_KEY, &key_check)?;
+                }
+            }
+
+            self.cipher = Some(cipher);
+
+            self.encrypt_plaintext_secrets()?;
+        }
+
+        Ok(self.cipher.as_ref().unwrap())
+    }
+
+    fn encrypt_plaintext_secrets(&mut self) -> Result<(), anyhow::Error> {
+        let conn = self.conn.get_mut().unwrap();
+        let cipher = self.cipher.as_ref().unwrap();
+
+        let mut select_plaintext = conn.prepare("SELECT id, value FROM secrets")?;
+
+        let rows: Vec<(i64, String)> = select_plaintext
+            .query_map([], |row| Ok((row.get::<usize, i64>(0)?, row.get::<usize, String>(1)?)))?
+            .filter_map(Result::ok)
+            .filter(|(_, value)| !SecretCipher::is_encrypted(value))
+            .collect();
+
+        drop(select_plaintext);
+
+        for (id, value) in rows {
+            trace!("SqlitePersister encrypting plaintext secret id {id}");
+
+            conn.execute(
+                "UPDATE secrets SET value = ?1 WHERE id = ?2",
+                (cipher.encrypt(&value)?, id),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn get_meta(&mut self, key: &str) -> Result<Option<String>, anyhow::Error> {
+        let conn = self.conn.get_mut().unwrap();
+
+        let mut select_meta = conn.prepare("SELECT value FROM meta where key = ?1")?;
+
+        let mut rows = select_meta.query_map([key], |row| row.get::<usize, String>(0))?;
+
+        match rows.next() {
+            Some(value) => Ok(Some(value?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_meta(&mut self, key: &str, value: &str) -> Result<(), anyhow::Error> {
+        self.conn.get_mut().unwrap().execute(
+            "INSERT or replace INTO meta (key, value) VALUES (?1, ?2)",
+            (key, value),
+        )?;
+
+        Ok(())
+    }
+}
+
 impl Persister for SqlitePersister {
     fn persist_call_record(&mut self, call_record: &CallRecord) -> Result<bool> {
         trace!("persist_call_record call_record: {call_record:#?}");
@@ -72,13 +280,15 @@ impl Persister for SqlitePersister {
             Ok(false)
         } else {
 
+        // sensitive values are never written to call history
         let res = conn_attempt.unwrap().execute(
-            "INSERT INTO drop_record (drop_id, full_url, status_code, full_response) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO drop_record (drop_id, full_url, status_code, full_response, attempts) VALUES (?1, ?2, ?3, ?4, ?5)",
             (
                 &call_record.drop_id,
-                &call_record.full_url,
+                Redactor::redact(&call_record.full_url),
                 &call_record.status_code.unwrap().as_u16(),
-                &call_record.full_response,
+                call_record.full_response.as_deref().map(Redactor::redact),
+                serde_json::to_string(&call_record.attempts).ok().map(|attempts| Redactor::redact(&attempts)),
             ),
         );
 
@@ -97,20 +307,43 @@ impl Persister for SqlitePersister {
 
     }
 
-    fn insert_secret_into_env(&mut self, key: &str, value: &str, env: &str, is_overwrite: bool) {
+    fn insert_secret_into_env(
+        &mut self,
+        key: &str,
+        value: &str,
+        env: &str,
+        is_overwrite: bool,
+        set_by: &str,
+    ) {
+        // an overwrite keeps created_at from the original secret
         let sql = if is_overwrite {
-            "INSERT or replace INTO secrets (key, value, env) VALUES (?1, ?2, ?3)"
+            "INSERT INTO secrets (key, value, env, created_at, updated_at, set_by)
+             VALUES (?1, ?2, ?3, datetime('now','localtime'), datetime('now','localtime'), ?4)
+             ON CONFLICT(key, env) DO UPDATE SET
+                value = excluded.value,
+                updated_at = excluded.updated_at,
+                set_by = excluded.set_by"
         } else {
-            "INSERT INTO secrets (key, value, env) VALUES (?1, ?2, ?3)"
+            "INSERT INTO secrets (key, value, env, created_at, updated_at, set_by)
+             VALUES (?1, ?2, ?3, datetime('now','localtime'), datetime('now','localtime'), ?4)"
+        };
+
+        let encrypted_value = match self.get_cipher().and_then(|cipher| cipher.encrypt(value)) {
+            Ok(encrypted_value) => encrypted_value,
+            Err(err) => panic!("Failed to encrypt secret {key}: {err}"),
         };
 
         // todo- handle unwrap correctly
-        let insert_secret_call = self.conn.get_mut().unwrap().execute(sql, (key, value, env));
+        let insert_secret_call = self
+            .conn
+            .get_mut()
+            .unwrap()
+            .execute(sql, (key, &encrypted_value, env, set_by));
 
         match insert_secret_call {
             Ok(res) => {
                 trace!("SqlitePersister create insert_secret_call call res: {res:#?}");
-                println!(
+                text_println!(
                     "secret {} in environment {} set successfully.\n",
                     key.yellow(),
                     env.yellow()
@@ -124,12 +357,29 @@ impl Persister for SqlitePersister {
                         env.yellow()
                     )
                 } else {
-                    panic!("Failed to insert secret {key} into environment {value}: {err}");
+                    panic!("Failed to insert secret {key} into environment {env}: {err}");
                 }
             }
         }
     }
 
+    fn set_secret_in_env(&mut self, key: &str, value: &str, env: &str, set_by: &str) -> Result<(), anyhow::Error> {
+        let encrypted_value = self.get_cipher()?.encrypt(value)?;
+
+        // an overwrite keeps created_at from the original secret
+        self.conn.get_mut().unwrap().execute(
+            "INSERT INTO secrets (key, value, env, created_at, updated_at, set_by)
+             VALUES (?1, ?2, ?3, datetime('now','localtime'), datetime('now','localtime'), ?4)
+             ON CONFLICT(key, env) DO UPDATE SET
+                value = excluded.value,
+                updated_at = excluded.updated_at,
+                set_by = excluded.set_by",
+            (key, &encrypted_value, env, set_by),
+        )?;
+
+        Ok(())
+    }
+
     fn delete_secret_in_env(&mut self, key: &str, env: &str) {
         let sql = "delete from secrets where key = ?1 and env = ?2";
 
@@ -139,7 +389,7 @@ impl Persister for SqlitePersister {
         match delete_secret_call {
             Ok(res) => {
                 trace!("SqlitePersister create delete_secret_call call res: {res:#?}");
-                println!("secrets in environment {} deleted: {res:?}\n", env.yellow());
+                text_println!("secrets in environment {} deleted: {res:?}\n", env.yellow());
             }
             Err(err) => {
                 trace!("delete_secret_in_env err {err:?}");
@@ -148,10 +398,10 @@ impl Persister for SqlitePersister {
         }
     }
 
-    fn get_all_secrets(&mut self) {
+    fn get_all_secrets(&mut self) -> Result<Vec<Secret>, anyhow::Error> {
 
         // todo- handle unwrap correctly
-        let select_secrets_for_all_env = self.conn.get_mut().unwrap().prepare("SELECT key, value, env FROM secrets");
+        let select_secrets_for_all_env = self.conn.get_mut().unwrap().prepare("SELECT key, value, env, created_at, updated_at, set_by FROM secrets");
 
         let mut select_secrets_for_env = match select_secrets_for_all_env {
             Ok(res) => res,
@@ -169,11 +419,7 @@ impl Persister for SqlitePersister {
             let value = value_res.unwrap();
             let env = env_val.unwrap();
 
-            Ok(Secret {
-                key,
-                value,
-                _env: env,
-            })
+            Ok(Secret::new(&key, &value, &env).with_metadata(row.get(3)?, row.get(4)?, row.get(5)?))
         });
 
         let secrets_for_env = match secrets_for_env_res {
@@ -194,26 +440,18 @@ impl Persister for SqlitePersister {
             })
             .collect();
 
-        if collected.is_empty() {
-            println!("No secrets set");
-        } else {
-            println!("Secrets: {}", "all secrets".yellow());
-            for secret in collected {
-                println!("{secret:?}");
-            }
-        }
+        Ok(collected)
     }
 
-    #[logfn(
-        ok = "TRACE",
-        err = "ERROR",
-        fmt = "get_secrets_for_env: {:?}",
-        log_ts = true
-    )]
-    fn get_secrets_for_env(&mut self, env: &str, is_cli: bool) -> Result<IndexMap<String, Value>, anyhow::Error> {
+    /// the decrypted values are never logged
+    #[logfn(err = "ERROR", log_ts = true)]
+    fn get_secrets_for_env(&mut self, env: &str) -> Result<IndexMap<String, Value>, anyhow::Error> {
+
+        // derive the cipher before borrowing the connection
+        self.get_cipher()?;
 
         // todo- handle unwrap correctly
-        
+
         let select_secrets_for_env_call = self
             .conn
             .get_mut().unwrap()
@@ -233,11 +471,7 @@ impl Persister for SqlitePersister {
             let key = key_res.unwrap();
             let value = value_res.unwrap();
 
-            Ok(Secret {
-                key,
-                value,
-                _env: env.to_string(),
-            })
+            Ok(Secret::new(&key, &value, env))
         });
 
         let secrets_for_env = match secrets_for_env_res {
@@ -258,27 +492,220 @@ impl Persister for SqlitePersister {
             })
             .collect();
 
-        if is_cli {
-            if collected.is_empty() {
-                println!("No secrets for env: {}", env.yellow());
-            } else {
-                println!("Secrets for env: {}", env.yellow());
-
-                for secret in &collected {
-                    println!("{secret:?}",);
-                }
-            }
-        }
+        let cipher = self.cipher.as_ref().unwrap();
 
         let mut secret_map: IndexMap<String, Value> = IndexMap::new();
 
+        // secrets are only decrypted here, when the scope is built
         for secret in collected {
-            secret_map.insert(
-                secret.key().to_string(),
-                Value::String(secret.value().to_string()),
-            );
+            let decrypted = cipher.decrypt(secret.value()).map_err(|err| {
+                anyhow::anyhow!("secret {} in env {env}: {err}", secret.key())
+            })?;
+
+            secret_map.insert(secret.key().to_string(), Value::String(decrypted));
         }
     
         Ok(secret_map)
     }
+
+    fn set_cookie_in_env(&mut self, cookie: &StoredCookie, env: &str) -> Result<(), anyhow::Error> {
+        let encrypted_value = self.get_cipher()?.encrypt(&cookie.value)?;
+
+        // a replaced cookie keeps its created_at
+        self.conn.get_mut().unwrap().execute(
+            "INSERT INTO cookies (env, domain, path, name, value, expires_at, secure, http_only, host_only, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, datetime('now','localtime'), datetime('now','localtime'))
+             ON CONFLICT(env, domain, path, name) DO UPDATE SET
+                value = excluded.value,
+                expires_at = excluded.expires_at,
+                secure = excluded.secure,
+                http_only = excluded.http_only,
+                host_only = excluded.host_only,
+                updated_at = excluded.updated_at",
+            (
+                env,
+                &cookie.domain,
+                &cookie.path,
+                &cookie.name,
+                &encrypted_value,
+                cookie.expires_at,
+                cookie.secure,
+                cookie.http_only,
+                cookie.host_only,
+            ),
+        )?;
+
+        trace!("SqlitePersister set cookie {} for {} in env {env}", cookie.name, cookie.domain);
+
+        Ok(())
+    }
+
+    fn delete_cookie_in_env(&mut self, cookie: &StoredCookie, env: &str) -> Result<(), anyhow::Error> {
+        self.conn.get_mut().unwrap().execute(
+            "DELETE FROM cookies WHERE env = ?1 AND domain = ?2 AND path = ?3 AND name = ?4",
+            (env, &cookie.domain, &cookie.path, &cookie.name),
+        )?;
+
+        Ok(())
+    }
+
+    fn get_cookies_for_env(&mut self, env: &str) -> Result<Vec<StoredCookie>, anyhow::Error> {
+        let conn = self.conn.get_mut().unwrap();
+
+        let expired_call = conn.execute(
+            "DELETE FROM cookies WHERE expires_at IS NOT NULL AND expires_at <= CAST(strftime('%s','now') AS integer)",
+            (),
+        );
+
+        trace!("SqlitePersister delete expired cookies res: {expired_call:#?}");
+
+        let mut select_cookies = conn.prepare(
+            "SELECT domain, path, name, value, expires_at, secure, http_only, host_only, updated_at
+             FROM cookies WHERE env = ?1 ORDER BY domain, path, updated_at",
+        )?;
+
+        let cookies: Vec<StoredCookie> = select_cookies
+            .query_map([env], |row| {
+                Ok(StoredCookie {
+                    domain: row.get(0)?,
+                    path: row.get(1)?,
+                    name: row.get(2)?,
+                    value: row.get(3)?,
+                    expires_at: row.get(4)?,
+                    secure: row.get(5)?,
+                    http_only: row.get(6)?,
+                    host_only: row.get(7)?,
+                    updated_at: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<StoredCookie>>>()?;
+
+        drop(select_cookies);
+
+        // the cipher is only derived if the jar has cookies
+        if cookies.is_empty() {
+            return Ok(cookies);
+        }
+
+        let cipher = self.get_cipher()?;
+
+        cookies
+            .into_iter()
+            .map(|cookie| {
+                let value = cipher.decrypt(&cookie.value).map_err(|err| {
+                    anyhow::anyhow!("cookie {} for {} in env {env}: {err}", cookie.name, cookie.domain)
+                })?;
+
+                Ok(StoredCookie { value, ..cookie })
+            })
+            .collect()
+    }
+
+    fn clear_cookies_in_env(&mut self, env: &str, domain: Option<&str>) -> Result<usize, anyhow::Error> {
+        let conn = self.conn.get_mut().unwrap();
+
+        let deleted = match domain {
+            Some(domain) => conn.execute(
+                "DELETE FROM cookies WHERE env = ?1 AND domain = ?2",
+                (env, domain.trim_start_matches('.').to_lowercase()),
+            )?,
+            None => conn.execute("DELETE FROM cookies WHERE env = ?1", [env])?,
+        };
+
+        Ok(deleted)
+    }
+
+    fn get_token_in_env(&mut self, cache_key: &str, env: &str) -> Result<Option<StoredToken>, anyhow::Error> {
+        let conn = self.conn.get_mut().unwrap();
+
+        let mut select_token = conn.prepare(
+            "SELECT access_token, token_type, expires_at, refresh_token
+             FROM oauth_tokens WHERE env = ?1 AND cache_key = ?2",
+        )?;
+
+        let mut rows = select_token.query_map((env, cache_key), |row| {
+            Ok(StoredToken {
+                access_token: row.get(0)?,
+                token_type: row.get(1)?,
+                expires_at: row.get(2)?,
+                refresh_token: row.get(3)?,
+            })
+        })?;
+
+        let token = match rows.next() {
+            Some(token) => token?,
+            None => return Ok(None),
+        };
+
+        drop(rows);
+        drop(select_token);
+
+        let cipher = self.get_cipher()?;
+
+        let refresh_token = match &token.refresh_token {
+            Some(refresh_token) => Some(cipher.decrypt(refresh_token)?),
+            None => None,
+        };
+
+        Ok(Some(StoredToken {
+            access_token: cipher.decrypt(&token.access_token)?,
+            refresh_token,
+            ..token
+        }))
+    }
+
+    fn set_token_in_env(&mut self, cache_key: &str, token: &StoredToken, env: &str) -> Result<(), anyhow::Error> {
+        let cipher = self.get_cipher()?;
+
+        let access_token = cipher.encrypt(&token.access_token)?;
+
+        let refresh_token = match &token.refresh_token {
+            Some(refresh_token) => Some(cipher.encrypt(refresh_token)?),
+            None => None,
+        };
+
+        self.conn.get_mut().unwrap().execute(
+            "INSERT or replace INTO oauth_tokens (env, cache_key, access_token, token_type, expires_at, refresh_token, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now','localtime'))",
+            (env, cache_key, &access_token, &token.token_type, token.expires_at, &refresh_token),
+        )?;
+
+        Ok(())
+    }
+
+    fn delete_token_in_env(&mut self, cache_key: &str, env: &str) -> Result<(), anyhow::Error> {
+        self.conn.get_mut().unwrap().execute(
+            "DELETE FROM oauth_tokens WHERE env = ?1 AND cache_key = ?2",
+            (env, cache_key),
+        )?;
+
+        Ok(())
+    }
+
+    fn set_variable_in_env(&mut self, key: &str, value: &str, env: &str, set_by: &str) -> Result<(), anyhow::Error> {
+        // an overwrite keeps created_at from the original variable
+        self.conn.get_mut().unwrap().execute(
+            "INSERT INTO variables (key, value, env, created_at, updated_at, set_by)
+             VALUES (?1, ?2, ?3, datetime('now','localtime'), datetime('now','localtime'), ?4)
+             ON CONFLICT(key, env) DO UPDATE SET
+                value = excluded.value,
+                updated_at = excluded.updated_at,
+                set_by = excluded.set_by",
+            (key, value, env, set_by),
+        )?;
+
+        Ok(())
+    }
+
+    fn get_variables_for_env(&mut self, env: &str) -> Result<IndexMap<String, Value>, anyhow::Error> {
+        let conn = self.conn.get_mut().unwrap();
+
+        let mut select_variables = conn.prepare("SELECT key, value FROM variables WHERE env = ?1 ORDER BY key")?;
+
+        let variables = select_variables
+            .query_map([env], |row| Ok((row.get::<_, String>(0)?, Value::String(row.get(1)?))))?
+            .collect::<Result<IndexMap<String, Value>>>()?;
+
+        Ok(variables)
+    }
 }
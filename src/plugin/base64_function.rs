@@ -0,0 +1,34 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use hcl::{eval::ParamType, Value};
+
+use super::DropFunction;
+
+/// `base64(secrets.api_key)`- the reference function plugin
+pub struct Base64Function {}
+
+impl DropFunction for Base64Function {
+    fn name(&self) -> &str {
+        "base64"
+    }
+
+    fn params(&self) -> Vec<ParamType> {
+        vec![ParamType::String]
+    }
+
+    fn variadic_param(&self) -> Option<ParamType> {
+        None
+    }
+
+    fn call(&self, args: &[Value]) -> Result<Value, String> {
+        match &args[0] {
+            Value::String(str) => {
+                let mut buf = String::new();
+
+                BASE64_STANDARD.encode_string(str, &mut buf);
+
+                Ok(Value::String(buf))
+            }
+            other => Err(format!("base64 expects a string, found {other}")),
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use base64_function::Base64Function;
+use hcl::{
+    eval::{Context, Func, FuncArgs, FuncDef, ParamType},
+    Value,
+};
+use set_secret::SetSecretAction;
+
+use crate::{
+    action::{ActionValue, AfterActionEffect, PostAction},
+    constants::*,
+    record::CallRecord,
+};
+
+pub mod base64_function;
+pub mod set_secret;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+static GLOBAL_PLUGIN_REGISTRY: RwLock<Option<Arc<PluginRegistry>>> = RwLock::new(None);
+
+static BUILTIN_PLUGIN_REGISTRY: OnceLock<Arc<PluginRegistry>> = OnceLock::new();
+
+/// an after action type, run with the action's
+/// object once the call responds
+///
+/// `after = [{ type = "set_secret", input = response.body.token, ... }]`
+pub trait AfterAction: Send + Sync {
+    /// the action's `type` in dropfiles
+    fn action_type(&self) -> &str;
+
+    /// keys checked when the dropfiles are parsed
+    fn required_keys(&self) -> &[&str] {
+        &[]
+    }
+
+    /// when the action runs unless `on` is set-
+    /// "success", "failure" or "always"
+    fn default_on(&self) -> &str {
+        "always"
+    }
+
+    fn run(
+        &self,
+        action_config: &HashMap<String, ActionValue>,
+        call_record: &CallRecord,
+    ) -> AfterActionEffect;
+}
+
+/// a function available in dropfile expressions
+///
+/// `headers = [{ "authorization" : base64(secrets.api_key) }]`
+pub trait DropFunction: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// positional parameters, checked by the
+    /// interpreter before `call`
+    fn params(&self) -> Vec<ParamType> {
+        Vec::new()
+    }
+
+    /// parameters after the positional ones
+    fn variadic_param(&self) -> Option<ParamType> {
+        Some(ParamType::Any)
+    }
+
+    fn call(&self, args: &[Value]) -> Result<Value, String>;
+}
+
+/// the after actions that are part of drop, outside
+/// of the reference plugins
+struct BuiltinAfterAction {
+    action_type: &'static str,
+    required_keys: &'static [&'static str],
+    default_on: &'static str,
+    run: fn(&HashMap<String, ActionValue>, &CallRecord) -> AfterActionEffect,
+}
+
+impl AfterAction for BuiltinAfterAction {
+    fn action_type(&self) -> &str {
+        self.action_type
+    }
+
+    fn required_keys(&self) -> &[&str] {
+        self.required_keys
+    }
+
+    fn default_on(&self) -> &str {
+        self.default_on
+    }
+
+    fn run(
+        &self,
+        action_config: &HashMap<String, ActionValue>,
+        call_record: &CallRecord,
+    ) -> AfterActionEffect {
+        (self.run)(action_config, call_record)
+    }
+}
+
+/// hcl functions are plain fn pointers, so each
+/// registered function is declared with a slot that
+/// calls it through the global registry
+macro_rules! function_slots {
+    ($($slot:literal),*) => {
+        [$(|args: FuncArgs| PluginRegistry::get().call_function($slot, &args)),*]
+    };
+}
+
+const FUNCTION_SLOTS: [Func; 32] = function_slots!(
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+    25, 26, 27, 28, 29, 30, 31
+);
+
+/// after actions and functions, populated at startup
+/// with drop's own and any plugins in the dropfile
+/// directory's `plugins` folder
+pub struct PluginRegistry {
+    after_actions: Vec<Box<dyn AfterAction>>,
+    functions: Vec<Box<dyn DropFunction>>,
+}
+
+impl PluginRegistry {
+    /// replaced when an embedded session loads another
+    /// dropfile directory- see `CmdContext`
+    pub fn set(registry: PluginRegistry) -> Arc<PluginRegistry> {
+        let registry = Arc::new(registry);
+
+        PluginRegistry::set_arc(Arc::clone(&registry));
+
+        registry
+    }
+
+    pub fn set_arc(registry: Arc<PluginRegistry>) {
+        match GLOBAL_PLUGIN_REGISTRY.write() {
+            Ok(mut provider) => *provider = Some(registry),
+            Err(err) => log::error!("Error setting global plugin registry: {err:?}"),
+        }
+    }
+
+    /// drop's own actions and functions
+    /// until the registry is set
+    pub fn get() -> Arc<PluginRegistry> {
+        let registry = GLOBAL_PLUGIN_REGISTRY.read().ok().and_then(|provider| provider.clone());
+
+        registry.unwrap_or_else(|| {
+            Arc::clone(BUILTIN_PLUGIN_REGISTRY.get_or_init(|| Arc::new(PluginRegistry::with_builtins())))
+        })
+    }
+
+    pub fn with_builtins() -> PluginRegistry {
+        let mut registry = PluginRegistry {
+            after_actions: Vec::new(),
+            functions: Vec::new(),
+        };
+
+        registry.register_after_action(Box::new(SetSecretAction {}));
+
+        registry.register_after_action(Box::new(BuiltinAfterAction {
+            action_type: AFTER_WRITE_FILE_TYPE_VALUE,
+            required_keys: &["path"],
+            default_on: "always",
+            run: |action_config, call_record| {
+                PostAction::post_action_write_file(action_config)(call_record)
+            },
+        }));
+
+        registry.register_after_action(Box::new(BuiltinAfterAction {
+            action_type: AFTER_SET_VARIABLE_TYPE_VALUE,
+            required_keys: &["input", "key"],
+            default_on: "success",
+            run: |action_config, call_record| {
+                PostAction::post_action_set_variable(action_config)(call_record)
+            },
+        }));
+
+        registry.register_after_action(Box::new(BuiltinAfterAction {
+            action_type: AFTER_APPEND_JSONL_TYPE_VALUE,
+            required_keys: &["path"],
+            default_on: "always",
+            run: |action_config, call_record| {
+                PostAction::post_action_append_jsonl(action_config)(call_record)
+            },
+        }));
+
+        registry.register_after_action(Box::new(BuiltinAfterAction {
+            action_type: AFTER_EXEC_TYPE_VALUE,
+            required_keys: &["command"],
+            default_on: "always",
+            run: |action_config, call_record| {
+                PostAction::post_action_exec(action_config)(call_record)
+            },
+        }));
+
+        registry.register_after_action(Box::new(BuiltinAfterAction {
+            action_type: AFTER_HTTP_NOTIFY_TYPE_VALUE,
+            required_keys: &["url"],
+            default_on: "always",
+            run: |action_config, call_record| {
+                PostAction::post_action_http_notify(action_config)(call_record)
+            },
+        }));
+
+        registry.register_function(Box::new(Base64Function {}));
+
+        registry
+    }
+
+    /// drop's own actions and functions, and the
+    /// `.wasm` and `.wat` plugins in `[dir]/plugins`
+    pub fn load(dropfile_dir: &str) -> Result<PluginRegistry, anyhow::Error> {
+        #[cfg_attr(not(feature = "wasm"), allow(unused_mut))]
+        let mut registry = PluginRegistry::with_builtins();
+
+        #[cfg(feature = "wasm")]
+        wasm::WasmPlugin::load_plugins(dropfile_dir, &mut registry)?;
+
+        #[cfg(not(feature = "wasm"))]
+        if std::path::Path::new(dropfile_dir).join(PLUGINS_DIR).is_dir() {
+            log::warn!("{PLUGINS_DIR} found in {dropfile_dir}- drop was built without the wasm feature, plugins are not loaded");
+        }
+
+        Ok(registry)
+    }
+
+    /// a plugin with the type of an
+    /// earlier action replaces it
+    pub fn register_after_action(&mut self, after_action: Box<dyn AfterAction>) {
+        self.after_actions
+            .retain(|existing| existing.action_type() != after_action.action_type());
+
+        self.after_actions.push(after_action);
+    }
+
+    /// a plugin with the name of an
+    /// earlier function replaces it
+    pub fn register_function(&mut self, function: Box<dyn DropFunction>) {
+        if let Some(existing) = self.functions.iter_mut().find(|existing| existing.name() == function.name()) {
+            *existing = function;
+            return;
+        }
+
+        if self.functions.len() == FUNCTION_SLOTS.len() {
+            log::error!(
+                "function {} not registered, at most {} plugin functions are supported",
+                function.name(),
+                FUNCTION_SLOTS.len()
+            );
+            return;
+        }
+
+        self.functions.push(function);
+    }
+
+    pub fn get_after_action(&self, action_type: &str) -> Option<&dyn AfterAction> {
+        self.after_actions
+            .iter()
+            .find(|after_action| after_action.action_type() == action_type)
+            .map(|after_action| after_action.as_ref())
+    }
+
+    /// each action type, and the keys it must set
+    pub fn get_after_action_types(&self) -> Vec<(&str, &[&str])> {
+        self.after_actions
+            .iter()
+            .map(|after_action| (after_action.action_type(), after_action.required_keys()))
+            .collect()
+    }
+
+    pub fn declare_functions(&self, ctx: &mut Context) {
+        for (slot, function) in self.functions.iter().enumerate() {
+            let mut builder = FuncDef::builder().params(function.params());
+
+            if let Some(variadic_param) = function.variadic_param() {
+                builder = builder.variadic_param(variadic_param);
+            }
+
+            ctx.declare_func(function.name(), builder.build(FUNCTION_SLOTS[slot]));
+        }
+    }
+
+    fn call_function(&self, slot: usize, args: &[Value]) -> Result<Value, String> {
+        match self.functions.get(slot) {
+            Some(function) => function.call(args),
+            None => Err(format!("no function registered in slot {slot}")),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use colored::Colorize;
+
+use crate::{
+    action::{accessor::PostActionAccessor, ActionValue, AfterActionEffect},
+    constants::AFTER_SET_SECRET_TYPE_VALUE,
+    parser::hcl_block::HclBlock,
+    persist::PersisterProvider,
+    record::CallRecord,
+};
+
+use super::AfterAction;
+
+/// stores one of the call's outputs as a secret-
+/// the reference after action plugin
+///
+/// `after = [{ type = "set_secret", input = response.body.token, key = "api_token", overwrite = true }]`
+pub struct SetSecretAction {}
+
+impl AfterAction for SetSecretAction {
+    fn action_type(&self) -> &str {
+        AFTER_SET_SECRET_TYPE_VALUE
+    }
+
+    fn required_keys(&self) -> &[&str] {
+        &["input", "key", "overwrite"]
+    }
+
+    /// a failed call doesn't overwrite a good secret
+    fn default_on(&self) -> &str {
+        "success"
+    }
+
+    fn run(
+        &self,
+        action_config: &HashMap<String, ActionValue>,
+        call_record: &CallRecord,
+    ) -> AfterActionEffect {
+        let input = PostActionAccessor::get_trav_or_panic(action_config, "input").to_owned();
+        let input = HclBlock::traversal_to_string(&input);
+        let key_to_set = PostActionAccessor::get_string_or_panic(action_config, "key").clone();
+        let overwrite =
+            PostActionAccessor::get_bool_or_panic(action_config, "overwrite").to_owned();
+        let env = PostActionAccessor::get_string_or_default(action_config, "env", "base").clone();
+
+        let mut details = serde_json::Map::new();
+        details.insert("input".to_string(), input.clone().into());
+        details.insert("key".to_string(), key_to_set.clone().into());
+        details.insert("env".to_string(), env.clone().into());
+
+        // todo- refactor into Result<>
+        let mut matched = false;
+
+        let persister_lock = PersisterProvider::get_lock_to_persister();
+
+        if persister_lock.is_none() {
+            // warn
+        } else {
+
+            let persister = &mut persister_lock.unwrap();
+
+            let outputs = call_record.output_records();
+
+            if let Some(outputs) = outputs {
+                for output_record in outputs {
+                    let output_key = output_record.key();
+
+                    if *output_key == input {
+                        matched = true;
+                        let output_value = output_record.value();
+
+                        // todo
+                        log::trace!(
+                            "input {input} key_to_set {key_to_set} output_value {output_value}"
+                        );
+
+                        persister.insert_secret_into_env(
+                            &key_to_set,
+                            output_value,
+                            &env,
+                            overwrite,
+                            &format!("after {}", call_record.drop_id()),
+                        );
+                    }
+                }
+            }
+
+            if !matched {
+                log::error!("{} error: {} not found in outputs", "post action set_secret".blue(), input.yellow());
+            }
+        }
+
+        AfterActionEffect {
+            action_type: AFTER_SET_SECRET_TYPE_VALUE.to_string(),
+            success: matched,
+            skipped: None,
+            details,
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use hcl::Value;
+use wasmtime::{Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+use crate::{
+    action::{ActionValue, AfterActionEffect, PostAction},
+    constants::PLUGINS_DIR,
+    parser::hcl_block::HclBlock,
+    record::CallRecord,
+};
+
+use super::{AfterAction, DropFunction, PluginRegistry};
+
+/// exports named `drop_fn_[name]` are registered as functions
+const FUNCTION_EXPORT_PREFIX: &str = "drop_fn_";
+
+/// exports named `drop_action_[type]` are registered as after actions
+const ACTION_EXPORT_PREFIX: &str = "drop_action_";
+
+/// instructions a plugin can run per call, so
+/// a plugin that doesn't return can't hang drop
+const WASM_PLUGIN_FUEL: u64 = 1_000_000_000;
+
+/// linear memory a plugin can grow to, so a plugin
+/// can't exhaust drop's memory
+const WASM_PLUGIN_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// a wasm module in the dropfile directory's `plugins` folder
+///
+/// the module exports `memory` and `alloc(len) -> ptr`, and
+/// each `drop_fn_*` and `drop_action_*` export takes the
+/// pointer and length of a json document and returns the
+/// pointer and length of its json result, packed in an i64
+/// as `ptr << 32 | len`- the result is `{ "ok" : ... }`
+/// or `{ "error" : "..." }`
+///
+/// functions are passed their arguments as a json array,
+/// after actions `{ "config" : {...}, "call" : {...} }`
+pub struct WasmPlugin {
+    name: String,
+    instance: Mutex<(Store<StoreLimits>, Instance)>,
+}
+
+impl WasmPlugin {
+    /// `.wasm` and `.wat` files in `[dir]/plugins`, in name order
+    pub fn load_plugins(dropfile_dir: &str, registry: &mut PluginRegistry) -> Result<(), anyhow::Error> {
+        let plugins_dir = Path::new(dropfile_dir).join(PLUGINS_DIR);
+
+        if !plugins_dir.is_dir() {
+            return Ok(());
+        }
+
+        let mut plugin_paths = fs::read_dir(&plugins_dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| matches!(path.extension().and_then(|ext| ext.to_str()), Some("wasm" | "wat")))
+            .collect::<Vec<_>>();
+
+        plugin_paths.sort();
+
+        let mut config = Config::new();
+        config.consume_fuel(true);
+
+        let engine = Engine::new(&config)?;
+
+        for plugin_path in plugin_paths {
+            let plugin = WasmPlugin::load(&engine, &plugin_path)
+                .map_err(|err| anyhow!("plugin {}: {err}", plugin_path.display()))?;
+
+            WasmPlugin::register_exports(Arc::new(plugin), registry);
+        }
+
+        Ok(())
+    }
+
+    fn load(engine: &Engine, plugin_path: &Path) -> Result<WasmPlugin, anyhow::Error> {
+        let module = Module::from_file(engine, plugin_path)?;
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(WASM_PLUGIN_MEMORY_LIMIT)
+            .instances(1)
+            .build();
+
+        let mut store = Store::new(engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(WASM_PLUGIN_FUEL)?;
+
+        // plugins have no imports- they can't reach
+        // the filesystem or network
+        let instance = Instance::new(&mut store, &module, &[])?;
+
+        let name = plugin_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        log::debug!("WasmPlugin loaded {name}");
+
+        Ok(WasmPlugin {
+            name,
+            instance: Mutex::new((store, instance)),
+        })
+    }
+
+    fn register_exports(plugin: Arc<WasmPlugin>, registry: &mut PluginRegistry) {
+        let export_names = {
+            let mut instance_lock = plugin.instance.lock().unwrap();
+            let (store, instance) = &mut *instance_lock;
+
+            instance
+                .exports(store)
+                .map(|export| export.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        for export_name in export_names {
+            if let Some(name) = export_name.strip_prefix(FUNCTION_EXPORT_PREFIX) {
+                registry.register_function(Box::new(WasmFunction {
+                    name: name.to_string(),
+                    export_name: export_name.to_string(),
+                    plugin: plugin.clone(),
+                }));
+            } else if let Some(action_type) = export_name.strip_prefix(ACTION_EXPORT_PREFIX) {
+                registry.register_after_action(Box::new(WasmAfterAction {
+                    action_type: action_type.to_string(),
+                    export_name: export_name.to_string(),
+                    plugin: plugin.clone(),
+                }));
+            }
+        }
+    }
+
+    /// writes `input` to the module's memory, calls
+    /// the export and reads its json result
+    fn call(&self, export_name: &str, input: &serde_json::Value) -> Result<serde_json::Value, String> {
+        let mut instance_lock = self.instance.lock().map_err(|err| err.to_string())?;
+        let (store, instance) = &mut *instance_lock;
+
+        let error = |err: wasmtime::Error| format!("plugin {} {export_name}: {}", self.name, err.root_cause());
+
+        store.set_fuel(WASM_PLUGIN_FUEL).map_err(error)?;
+
+        let memory: Memory = instance
+            .get_memory(&mut *store, "memory")
+            .ok_or(format!("plugin {} must export memory", self.name))?;
+
+        let alloc: TypedFunc<i32, i32> = instance
+            .get_typed_func(&mut *store, "alloc")
+            .map_err(error)?;
+
+        let export: TypedFunc<(i32, i32), i64> = instance
+            .get_typed_func(&mut *store, export_name)
+            .map_err(error)?;
+
+        let input = input.to_string();
+
+        let input_len = i32::try_from(input.len()).map_err(|err| err.to_string())?;
+        let input_ptr = alloc.call(&mut *store, input_len).map_err(error)?;
+
+        memory
+            .write(&mut *store, input_ptr as u32 as usize, input.as_bytes())
+            .map_err(|err| err.to_string())?;
+
+        let packed = export.call(&mut *store, (input_ptr, input_len)).map_err(error)? as u64;
+
+        let output_ptr = (packed >> 32) as usize;
+        let output_len = (packed & 0xffff_ffff) as usize;
+
+        // checked before allocating, as the length is the plugin's
+        if output_ptr.saturating_add(output_len) > memory.data_size(&*store) {
+            return Err(format!("plugin {} {export_name} returned a result outside its memory", self.name));
+        }
+
+        let mut output = vec![0u8; output_len];
+
+        memory
+            .read(&*store, output_ptr, &mut output)
+            .map_err(|err| err.to_string())?;
+
+        let mut result: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&output)
+            .map_err(|err| format!("plugin {} {export_name} returned invalid json: {err}", self.name))?;
+
+        match (result.remove("ok"), result.remove("error")) {
+            (_, Some(err)) => Err(err.as_str().map_or(err.to_string(), |err| err.to_string())),
+            (Some(ok), None) => Ok(ok),
+            (None, None) => Err(format!("plugin {} {export_name} must return ok or error", self.name)),
+        }
+    }
+}
+
+/// `drop_fn_[name]`
+pub struct WasmFunction {
+    name: String,
+    export_name: String,
+    plugin: Arc<WasmPlugin>,
+}
+
+impl DropFunction for WasmFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(&self, args: &[Value]) -> Result<Value, String> {
+        let args = serde_json::to_value(args).map_err(|err| err.to_string())?;
+
+        let result = self.plugin.call(&self.export_name, &args)?;
+
+        hcl::to_value(result).map_err(|err| err.to_string())
+    }
+}
+
+/// `drop_action_[type]`- the result's object
+/// is the action's details
+pub struct WasmAfterAction {
+    action_type: String,
+    export_name: String,
+    plugin: Arc<WasmPlugin>,
+}
+
+impl AfterAction for WasmAfterAction {
+    fn action_type(&self) -> &str {
+        &self.action_type
+    }
+
+    fn run(
+        &self,
+        action_config: &HashMap<String, ActionValue>,
+        call_record: &CallRecord,
+    ) -> AfterActionEffect {
+        let config = action_config
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = match value {
+                    ActionValue::String(str) => serde_json::Value::String(str.to_string()),
+                    ActionValue::Traversal(trav) => HclBlock::traversal_to_string(trav).into(),
+                    ActionValue::Bool(b) => serde_json::Value::Bool(*b),
+                    ActionValue::List(list) => list.clone().into(),
+                    ActionValue::Expression(_) => return None,
+                };
+
+                Some((key.to_string(), value))
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        let mut call = PostAction::get_summary(call_record);
+
+        let outputs = call_record
+            .output_records()
+            .iter()
+            .flatten()
+            .map(|output_record| (output_record.key().to_string(), output_record.value().to_string().into()))
+            .collect::<serde_json::Map<_, _>>();
+
+        call["outputs"] = outputs.into();
+        call["body"] = call_record.full_response().clone().unwrap_or_default().into();
+
+        let input = serde_json::json!({ "config": config, "call": call });
+
+        let mut details = serde_json::Map::new();
+
+        let action_res = self.plugin.call(&self.export_name, &input).map(|result| {
+            if let serde_json::Value::Object(result) = result {
+                details.extend(result);
+            }
+        });
+
+        PostAction::to_effect(&self.action_type, details, action_res)
+    }
+}
@@ -1,35 +1,122 @@
 use derive_getters::Getters;
 use hcl::Traversal;
-use isahc::http::{HeaderMap, HeaderValue, StatusCode};
+use indexmap::IndexMap;
+use isahc::{
+    http::{HeaderMap, HeaderValue, StatusCode},
+    HttpClient, ResponseExt,
+};
 use log::{error, trace};
 use output_record::OutputRecord;
 use response_walker::{OutputType, ResponseWalker};
+use serde::Serialize;
 
-use crate::{action::AfterActionConfig, call::DropCall, parser::hcl_block::HclBlock, s};
+use crate::{
+    action::{AfterActionConfig, AfterActionEffect},
+    call::DropCall,
+    cmd::ctx::CmdContext,
+    parser::hcl_block::HclBlock,
+    s,
+    util::redact::Redactor,
+};
 
-mod output_record;
+pub mod output_record;
 pub mod response_walker;
+
+/// one request of a call with retries
+#[derive(Clone, Debug, Serialize)]
+pub struct CallAttempt {
+    pub attempt: u32,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u128,
+    pub timings: Option<CallTimings>,
+    pub retry_in_ms: Option<u128>,
+}
+
+/// phases of a request in milliseconds, from the client's metrics-
+/// connect and tls are 0 when a pooled connection is reused
+#[derive(Clone, Debug, Serialize)]
+pub struct CallTimings {
+    pub http_version: String,
+    pub dns_ms: f64,
+    pub connect_ms: f64,
+    pub tls_ms: f64,
+    /// time to first byte
+    pub ttfb_ms: f64,
+    pub total_ms: f64,
+}
+
+impl CallTimings {
+    /// read after the body, so the total includes the transfer
+    pub fn from_response<T>(response: &isahc::Response<T>) -> Option<CallTimings> {
+        let metrics = response.metrics()?;
+
+        let as_ms = |duration: std::time::Duration| {
+            (duration.as_secs_f64() * 1000.0 * 100.0).round() / 100.0
+        };
+
+        Some(CallTimings {
+            http_version: format!("{:?}", response.version()),
+            dns_ms: as_ms(metrics.name_lookup_time()),
+            connect_ms: as_ms(metrics.connect_time()),
+            tls_ms: as_ms(metrics.secure_connect_time()),
+            ttfb_ms: as_ms(metrics.transfer_start_time()),
+            total_ms: as_ms(metrics.total_time()),
+        })
+    }
+
+    /// `HTTP/2.0 dns 0.4ms connect 1.2ms tls 8.1ms ttfb 32.6ms total 33ms`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} dns {}ms connect {}ms tls {}ms ttfb {}ms total {}ms",
+            self.http_version, self.dns_ms, self.connect_ms, self.tls_ms, self.ttfb_ms, self.total_ms
+        )
+    }
+}
+
 #[derive(Clone, Debug, Getters)]
 pub struct CallRecord {
     pub drop_id: String,
+    pub env: String,
+    pub method: String,
     pub full_url: String,
+    pub request_headers: IndexMap<String, String>,
     pub status_code: Option<StatusCode>,
+    pub duration_ms: Option<u128>,
+    pub attempts: Vec<CallAttempt>,
+    pub timings: Option<CallTimings>,
+    pub response_headers: IndexMap<String, String>,
     pub full_response: Option<String>,
     pub output_records: Option<Vec<OutputRecord>>,
+    pub assert_results: Vec<serde_json::Value>,
     pub after_action_config: Option<AfterActionConfig>,
+    pub after_action_effects: Vec<AfterActionEffect>,
     pub is_successful_call: bool,
+    /// the run's client, for after actions that
+    /// send requests, e.g. `http_notify`
+    pub client: Option<HttpClient>,
 }
 
 impl CallRecord {
     pub fn init(call: DropCall, is_successful_call: bool) -> CallRecord {
         CallRecord {
             drop_id: call.drop_id.drop_id().unwrap(),
+            env: CmdContext::get_env().to_string(),
+            method: call.method.to_string(),
             full_url: call.full_url(),
+            request_headers: CallRecord::headers_to_map(&call.headers),
             after_action_config: Some(call.after_action_config),
             status_code: None,
+            duration_ms: None,
+            attempts: Vec::new(),
+            timings: None,
+            response_headers: IndexMap::new(),
             full_response: None,
             output_records: None,
-            is_successful_call
+            assert_results: Vec::new(),
+            after_action_effects: Vec::new(),
+            is_successful_call,
+            client: None,
         }
     }
 
@@ -37,6 +124,51 @@ impl CallRecord {
         self.status_code = Some(status_code);
     }
 
+    pub fn headers_to_map(headers: &HeaderMap<HeaderValue>) -> IndexMap<String, String> {
+        headers
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// document for json and jsonl output- values are
+    /// redacted when the document is written
+    pub fn to_json(&self) -> serde_json::Value {
+        let outputs: serde_json::Map<String, serde_json::Value> = self
+            .output_records
+            .iter()
+            .flatten()
+            .map(|output| {
+                let value = serde_json::from_str(output.value())
+                    .unwrap_or_else(|_| serde_json::Value::String(output.value().to_string()));
+
+                (output.key().to_string(), value)
+            })
+            .collect();
+
+        serde_json::json!({
+            "drop_id": self.drop_id,
+            "env": self.env,
+            "method": self.method,
+            "url": self.full_url,
+            "status": self.status_code.map(|status| status.as_u16()),
+            "success": self.is_successful_call,
+            "duration_ms": self.duration_ms,
+            "timings": self.timings,
+            "attempts": self.attempts,
+            "request_headers": self.request_headers,
+            "response_headers": self.response_headers,
+            "outputs": outputs,
+            "asserts": self.assert_results,
+            "after": self.after_action_effects,
+        })
+    }
+
     pub fn process_output_from_response(
         &mut self,
         outputs: &Option<Vec<Traversal>>,
@@ -95,7 +227,7 @@ impl CallRecord {
                                     self.set_output(&output_trav_as_str, &serialized_headers);
                                 }
                                 Err(err) => {
-                                    eprintln!("error deserializing response header {response_headers:?}: {}", s!(err));
+                                    eprintln!("{}", Redactor::redact(&format!("error deserializing response header {response_headers:?}: {}", s!(err))));
                                 }
                             }
                         }
@@ -133,7 +265,7 @@ impl CallRecord {
                     }
                     Err(walk_err) => {
                         if is_successful_call {
-                            eprintln!("{walk_err}");
+                            eprintln!("{}", Redactor::redact(&walk_err.to_string()));
                         } else {
                             trace!("{walk_err}");
                         }
@@ -161,7 +293,7 @@ impl CallRecord {
         if let Ok(value) = &walk_result {
             self.set_output(&HclBlock::traversal_to_string(output_trav), value);
         } else {
-            eprintln!("{:?}", walk_result.err());
+            eprintln!("{}", Redactor::redact(&format!("{:?}", walk_result.err())));
         }
     }
 
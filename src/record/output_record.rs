@@ -1,4 +1,4 @@
-use crate::util::pretty_printed_json;
+use crate::util::{pretty_printed_json, redact::Redactor};
 
 
 #[derive(Clone, Debug)]
@@ -20,13 +20,11 @@ impl OutputRecord {
         let key = &self.key;
         let value = &self.value;
 
-        match pretty_printed_json(value) {
-            Some(pretty) => {
-                println!("\noutput {key:#?} \n{pretty}\n");
-            }
-            None => {
-                println!("\noutput {key:#?} \n{value:?}\n");
-            }
-        }
+        let printed = match pretty_printed_json(value) {
+            Some(pretty) => pretty,
+            None => format!("{value:?}"),
+        };
+
+        println!("\noutput {key:#?} \n{}\n", Redactor::redact(&printed));
     }
 }
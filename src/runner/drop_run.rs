@@ -3,11 +3,20 @@ use hcl::{
     Attribute, Block, Value,
 };
 use indexmap::IndexMap;
+use isahc::HttpClient;
+use std::sync::Arc;
 
+use crate::util::exit;
 use crate::{
+    action::pre_action::PreAction,
     call::DropCall,
-    constants::{CALL_AFTER, CALL_ASSERT, CALL_OUTPUT, INPUT_OBJECT_VAR_PREFIX},
-    interpreter::{evaluate::Evaluator, scope::Scope},
+    caller::cookies::CookieJar,
+    cmd::ctx::CmdContext,
+    constants::{
+        CALL_AFTER, CALL_ASSERT, CALL_OUTPUT, COOKIE_OBJECT_VAR_PREFIX, INPUT_OBJECT_VAR_PREFIX,
+        VARIABLE_OBJECT_VAR_PREFIX,
+    },
+    interpreter::{diagnostics::EvalDiagnostics, evaluate::Evaluator, scope::Scope},
     parser::{drop_block::DropBlock, drop_id::DropId},
 };
 
@@ -15,7 +24,7 @@ use crate::{
 /// final call block before execution
 #[derive(Debug)]
 pub struct DropRun {
-    pub call_drop_container: &'static DropBlock,
+    pub call_drop_container: Arc<DropBlock>,
     pub call_block_overwrites: Option<CallBlockOverWrites>,
     pub input_index_map: IndexMap<String, Value>,
     pub env_var_scope: Context<'static>,
@@ -29,19 +38,79 @@ impl DropRun {
 
         let evaluated_block = self.evaluate_call_block_with_inputs(inputs_from_dependencies);
 
+        self.drop_call_from_evaluated_block(&evaluated_block)
+    }
+
+    /// like `get_drop_call`, returning evaluation
+    /// errors to embedded callers instead of exiting
+    pub fn try_get_drop_call(
+        &mut self,
+        inputs_from_dependencies: IndexMap<String, Value>,
+    ) -> Result<DropCall, String> {
+        let (evaluated_block, eval_diagnostics) = self.evaluate_call_block(inputs_from_dependencies);
+
+        if eval_diagnostics.is_err() {
+            return Err(eval_diagnostics.error_messages().join("\n"));
+        }
+
+        Ok(self.drop_call_from_evaluated_block(&evaluated_block))
+    }
+
+    fn drop_call_from_evaluated_block(&self, evaluated_block: &hcl::Block) -> DropCall {
         let drop_id: DropId = self.call_drop_container.drop_id.as_ref().unwrap().clone();
 
         // overwrite assert and output for call block
-        let drop_call = match &self.call_block_overwrites {
+        let mut drop_call = match &self.call_block_overwrites {
             Some(call_block_overwrites) => DropCall::from_call_and_run_hcl_block(
-                &evaluated_block,
+                evaluated_block,
                 call_block_overwrites,
                 drop_id,
             ),
-            None => DropCall::from_call_hcl_block(&evaluated_block, drop_id),
+            None => DropCall::from_call_hcl_block(evaluated_block, drop_id),
         };
 
         drop_call
+            .settings
+            .apply_scope_defaults(&self.env_var_scope, &drop_call.drop_id.drop_id().unwrap());
+
+        // the run's inputs, with the defaults and values
+        // set by before actions, for the call's scripts
+        drop_call.inputs = Some(self.input_index_map.clone());
+
+        drop_call
+    }
+
+    /// the call's `before` actions, which can set inputs and
+    /// secrets for the call- not run when the call is only
+    /// evaluated, with `give`
+    pub fn run_before_actions(&mut self, client: &HttpClient) {
+        if let Err(err) = self.try_run_before_actions(client) {
+            let drop_id = self.call_drop_container.drop_id.as_ref().unwrap().drop_id().unwrap();
+            log::error!("{drop_id:#?}: {err}");
+            exit::exit(1)
+        }
+    }
+
+    /// like `run_before_actions`, returning the error
+    /// to embedded callers instead of exiting
+    pub fn try_run_before_actions(&mut self, client: &HttpClient) -> Result<(), String> {
+        self.try_run_nested_before_actions(client, &[])
+    }
+
+    /// `before_chain` is the calls whose before actions hit
+    /// this one, outermost first, to catch cycles
+    pub fn try_run_nested_before_actions(
+        &mut self,
+        client: &HttpClient,
+        before_chain: &[String],
+    ) -> Result<(), String> {
+        PreAction::run_pre_action_callbacks(
+            &self.call_drop_container,
+            &mut self.env_var_scope,
+            &mut self.input_index_map,
+            client,
+            before_chain,
+        )
     }
 
     pub fn evaluate_call_block_with_blank_inputs(&mut self) -> hcl::Block {
@@ -55,6 +124,19 @@ impl DropRun {
         &mut self,
         inputs_from_dependencies: IndexMap<String, Value>,
     ) -> hcl::Block {
+        let (evaluated_block, eval_diagnostics) = self.evaluate_call_block(inputs_from_dependencies);
+
+        if eval_diagnostics.is_err() {
+            eval_diagnostics.panic();
+        }
+
+        evaluated_block
+    }
+
+    fn evaluate_call_block(
+        &mut self,
+        inputs_from_dependencies: IndexMap<String, Value>,
+    ) -> (hcl::Block, EvalDiagnostics) {
         // todo- merge inputs_from_dependencies with self.input_index_map
 
         log::trace!("DropRun evaluate_call_block_with_inputs");
@@ -65,6 +147,20 @@ impl DropRun {
             &self.input_index_map,
         );
 
+        // cookies stored by earlier calls in the run
+        Scope::insert_object_into_hcl_context(
+            &mut self.env_var_scope,
+            COOKIE_OBJECT_VAR_PREFIX,
+            &CookieJar::get_values_for_env(&CmdContext::get_env()),
+        );
+
+        // variables stored by earlier calls in the run
+        Scope::insert_object_into_hcl_context(
+            &mut self.env_var_scope,
+            VARIABLE_OBJECT_VAR_PREFIX,
+            &Scope::get_variables_for_env(&CmdContext::get_env()),
+        );
+
         log::debug!(
             "DropRun evaluate_call_block_with_inputs env_var_scope {:?}",
             self.env_var_scope
@@ -77,11 +173,7 @@ impl DropRun {
 
         log::trace!("DropRun eval_diagnostics {eval_diagnostics:?}");
 
-        if eval_diagnostics.is_err() {
-            eval_diagnostics.panic();
-        }
-
-        (evaluated_block)
+        (evaluated_block, eval_diagnostics)
     }
 }
 
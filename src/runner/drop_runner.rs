@@ -1,10 +1,15 @@
 use std::sync::{Arc, Mutex};
 
 use indexmap::IndexMap;
+use isahc::HttpClient;
 use rand::Rng;
 use tokio::sync::broadcast::{self, error::SendError, Receiver, Sender};
 
-use crate::{action::PostAction, caller::Caller};
+use crate::{
+    action::PostAction,
+    caller::Caller,
+    cmd::{ctx::CmdContext, output::Output},
+};
 
 use super::{drop_run::DropRun, RunPoolMutex, RunPoolOutputMap};
 
@@ -19,6 +24,7 @@ pub struct DropRunner {
     pub result_mutex: Arc<Mutex<RunPoolOutputMap>>,
     pub tx: Sender<i32>,
     pub rx: Receiver<i32>,
+    pub client: HttpClient,
 }
 
 impl DropRunner {
@@ -36,13 +42,18 @@ impl DropRunner {
 
         let inputs_from_dependencies = IndexMap::<String, hcl::Value>::new();
 
+        self.drop_run.run_before_actions(&self.client);
+
         // evaluate 
 
         let drop_call = self.drop_run.get_drop_call(inputs_from_dependencies);
         
         log::trace!("DropRunner drop_call: {drop_call:?}");
 
-        let caller = Caller {drop_call};
+        let drop_id = drop_call.drop_id.drop_id().unwrap();
+        let full_url = drop_call.full_url();
+
+        let caller = Caller {drop_call, client: self.client.clone()};
 
         let call_record_res = caller.call();
 
@@ -51,6 +62,14 @@ impl DropRunner {
         if call_record_res.is_err() {
             // report error to pool manager to 
             // cancel dependency calls
+
+            Output::emit_document(&serde_json::json!({
+                "drop_id": drop_id,
+                "env": CmdContext::get_env(),
+                "url": full_url,
+                "success": false,
+                "error": call_record_res.unwrap_err().to_string(),
+            }));
         } else {
 
             let call_record = call_record_res.unwrap();
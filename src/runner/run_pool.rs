@@ -3,7 +3,8 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use crate::s;
+use crate::util::exit;
+use crate::{caller::client::DropClient, s};
 use hcl::Value;
 use tokio::{sync::broadcast::{self, Receiver}, task::JoinSet};
 
@@ -70,6 +71,16 @@ impl RunPool {
 
         let result_mutex = Arc::new(Mutex::new(RunPoolOutputMap::new()));
 
+        // one client for the run, so calls
+        // share pooled connections
+        let client = match DropClient::build() {
+            Ok(client) => client,
+            Err(err) => {
+                log::error!("error building http client: {err}");
+                exit::exit(1);
+            }
+        };
+
         let mut i = 0;
 
         let (tx, _) = tokio::sync::broadcast::channel::<i32>(drop_runs.capacity());
@@ -92,6 +103,7 @@ impl RunPool {
                     tx: tx.clone(),
                     rx: tx.clone().subscribe(),
                     depends_on,
+                    client: client.clone(),
                 }
             })
             .collect();
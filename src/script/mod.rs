@@ -0,0 +1,299 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use indexmap::IndexMap;
+use isahc::http::{HeaderMap, HeaderName, HeaderValue};
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Position, Scope};
+
+use crate::{
+    call::DropCall,
+    caller::verbose::VerboseDump,
+    cmd::ctx::CmdContext,
+    constants::*,
+    interpreter::scope::Scope as DropScope,
+    persist::PersisterProvider,
+    record::{output_record::OutputRecord, response_walker::ResponseWalker, CallRecord},
+};
+
+/// time a script can run without a `script_timeout` setting
+pub const DEFAULT_SCRIPT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// sizes a script's strings, arrays and objects can grow
+/// to, so a script can't exhaust drop's memory before
+/// its timeout- large enough for response bodies
+const MAX_SCRIPT_STRING_SIZE: usize = 64 * 1024 * 1024;
+const MAX_SCRIPT_COLLECTION_SIZE: usize = 1_000_000;
+
+/// rhai scripts run before a call is sent and after it
+/// responds, from files relative to the dropfile directory
+///
+/// `script_before = "scripts/nonce.rhai"`
+/// `script_after = "scripts/cursor.rhai"`
+/// `script_timeout = "500ms"`
+///
+/// scripts are sandboxed- they can't reach the filesystem
+/// or network, and are stopped after the timeout. a script
+/// that throws fails the call, which stops its run
+pub struct DropScript {}
+
+impl DropScript {
+    /// the script can read and set `request.method`,
+    /// `request.url`, `request.headers` and `request.body`,
+    /// and read `inputs`- variables set in `vars` are stored
+    /// for the environment
+    pub fn run_before(path: &str, drop_call: &mut DropCall) -> Result<(), String> {
+        let timeout = drop_call.settings.get_script_timeout();
+
+        let headers = DropScript::headers_to_map(&drop_call.headers);
+
+        let mut request = Map::new();
+        request.insert("method".into(), drop_call.method.to_string().into());
+        request.insert("url".into(), drop_call.full_url().into());
+        request.insert("headers".into(), headers.clone().into());
+        request.insert("body".into(), DropScript::to_dynamic(&drop_call.body)?);
+
+        let inputs = drop_call.inputs.clone().unwrap_or_default();
+
+        let mut scope = Scope::new();
+        scope.push("request", request);
+        scope.push_constant("inputs", DropScript::to_dynamic(&inputs)?);
+
+        DropScript::run(path, timeout, &mut scope)?;
+
+        let request = scope
+            .get_value::<Map>("request")
+            .ok_or(format!("{path}: request must be an object"))?;
+
+        if let Some(method) = request.get("method") {
+            let method = method.to_string().to_uppercase();
+
+            drop_call.method = method
+                .parse()
+                .map_err(|_| format!("{path}: invalid request.method {method}"))?;
+        }
+
+        if let Some(url) = request.get("url") {
+            // the url is set whole, so it isn't split
+            // into the base url and path again
+            drop_call.base_url = url.to_string();
+            drop_call.path = String::new();
+        }
+
+        if let Some(script_headers) = request.get("headers") {
+            DropScript::apply_headers(path, &headers, script_headers, &mut drop_call.headers)?;
+        }
+
+        if let Some(body) = request.get("body") {
+            drop_call.body = rhai::serde::from_dynamic::<Option<serde_json::Value>>(body)
+                .map_err(|err| format!("{path}: invalid request.body: {err}"))?;
+        }
+
+        DropScript::store_variables(path, &scope, &drop_call.drop_id.drop_id().unwrap_or_default())
+    }
+
+    /// the script can read `response.status`, `response.headers`
+    /// and `response.body`, and the call's `drop_id`, `url` and
+    /// `call_outputs`- values set in `outputs` are added to the call's
+    /// outputs as `script.[key]`, and variables set in `vars`
+    /// are stored for the environment
+    pub fn run_after(path: &str, timeout: Duration, call_record: &mut CallRecord) -> Result<(), String> {
+        let full_response = call_record.full_response.clone().unwrap_or_default();
+
+        let body = match ResponseWalker::deserialize_response_json(&full_response) {
+            Ok(body) => DropScript::to_dynamic(&body)?,
+            Err(_) => full_response.into(),
+        };
+
+        let mut response = Map::new();
+        response.insert(
+            "status".into(),
+            call_record.status_code.map_or(Dynamic::UNIT, |status| (status.as_u16() as i64).into()),
+        );
+        response.insert("headers".into(), DropScript::to_dynamic(&call_record.response_headers)?);
+        response.insert(BODY_PROP.into(), body);
+
+        let existing_outputs = call_record
+            .output_records
+            .iter()
+            .flatten()
+            .map(|output_record| (output_record.key.to_string(), output_record.value.to_string()))
+            .collect::<IndexMap<_, _>>();
+
+        let mut scope = Scope::new();
+        scope.push_constant(RESPONSE_PROP, response);
+        scope.push_constant("drop_id", call_record.drop_id.to_string());
+        scope.push_constant("url", call_record.full_url.to_string());
+        scope.push_constant("call_outputs", DropScript::to_dynamic(&existing_outputs)?);
+        scope.push("outputs", Map::new());
+
+        DropScript::run(path, timeout, &mut scope)?;
+
+        let outputs = scope
+            .get_value::<Map>("outputs")
+            .ok_or(format!("{path}: outputs must be an object"))?;
+
+        for (key, value) in outputs {
+            // strings are stored as is, like outputs from the response
+            let value = match value.clone().into_string() {
+                Ok(value) => value,
+                Err(_) => rhai::serde::from_dynamic::<serde_json::Value>(&value)
+                    .map_err(|err| format!("{path}: invalid outputs.{key}: {err}"))?
+                    .to_string(),
+            };
+
+            call_record
+                .output_records
+                .get_or_insert_with(Vec::new)
+                .push(OutputRecord {
+                    key: format!("{SCRIPT_OUTPUT_PREFIX}.{key}"),
+                    value,
+                });
+        }
+
+        DropScript::store_variables(path, &scope, &call_record.drop_id)
+    }
+
+    /// evaluates the script with `vars`, the environment's
+    /// variables, and stops it after the timeout
+    fn run(path: &str, timeout: Duration, scope: &mut Scope) -> Result<(), String> {
+        let script_path = Path::new(&CmdContext::get_dir()).join(path);
+
+        let script = fs::read_to_string(&script_path)
+            .map_err(|err| format!("{path}: error reading script: {err}"))?;
+
+        let variables = DropScope::get_variables_for_env(&CmdContext::get_env());
+        scope.push(VARIABLE_OBJECT_VAR_PREFIX, DropScript::to_dynamic(&variables)?);
+
+        let started = Instant::now();
+
+        let mut engine = Engine::new();
+
+        // `import` can't load modules from the filesystem
+        engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+
+        engine.set_max_string_size(MAX_SCRIPT_STRING_SIZE);
+        engine.set_max_array_size(MAX_SCRIPT_COLLECTION_SIZE);
+        engine.set_max_map_size(MAX_SCRIPT_COLLECTION_SIZE);
+
+        engine.on_progress(move |_| (started.elapsed() > timeout).then_some(Dynamic::UNIT));
+
+        engine.on_print(|text| VerboseDump::note(&format!("script {text}")));
+
+        let ast = engine
+            .compile(&script)
+            .map_err(|err| DropScript::format_error(path, err.1, &err.0.to_string()))?;
+
+        VerboseDump::note(&format!("running script {path}"));
+
+        engine
+            .run_ast_with_scope(scope, &ast)
+            .map_err(|mut err| {
+                let position = err.take_position();
+
+                let message = match *err {
+                    EvalAltResult::ErrorTerminated(..) => {
+                        format!("timed out after {}ms", timeout.as_millis())
+                    }
+                    _ => err.to_string(),
+                };
+
+                DropScript::format_error(path, position, &message)
+            })
+    }
+
+    /// `scripts/nonce.rhai line 3: ...`
+    fn format_error(path: &str, position: Position, message: &str) -> String {
+        match position.line() {
+            Some(line) => format!("{path} line {line}: {message}"),
+            None => format!("{path}: {message}"),
+        }
+    }
+
+    /// variables in `vars` that the script added or changed
+    fn store_variables(path: &str, scope: &Scope, drop_id: &str) -> Result<(), String> {
+        let Some(vars) = scope.get_value::<Map>(VARIABLE_OBJECT_VAR_PREFIX) else {
+            return Err(format!("{path}: {VARIABLE_OBJECT_VAR_PREFIX} must be an object"));
+        };
+
+        let env = &CmdContext::get_env();
+
+        let stored = DropScope::get_variables_for_env(env);
+
+        for (key, value) in vars {
+            let value = value.to_string();
+
+            let is_unchanged = matches!(stored.get(key.as_str()), Some(hcl::Value::String(stored)) if *stored == value);
+
+            if is_unchanged {
+                continue;
+            }
+
+            let Some(mut persister) = PersisterProvider::get_lock_to_persister() else {
+                return Err("error accessing the persister".to_string());
+            };
+
+            persister
+                .set_variable_in_env(&key, &value, env, &format!("script {drop_id}"))
+                .map_err(|err| format!("{path}: error storing {VARIABLE_OBJECT_VAR_PREFIX}.{key}: {err}"))?;
+
+            VerboseDump::note(&format!("script set {VARIABLE_OBJECT_VAR_PREFIX}.{key}"));
+        }
+
+        Ok(())
+    }
+
+    fn headers_to_map(headers: &HeaderMap) -> Map {
+        headers
+            .iter()
+            .map(|(key, value)| (key.as_str().into(), value.to_str().unwrap_or_default().into()))
+            .collect()
+    }
+
+    /// only headers the script added, changed or removed are
+    /// rewritten- the script sees one value per header, so
+    /// repeated headers it left alone keep every value
+    fn apply_headers(
+        path: &str,
+        headers: &Map,
+        script_headers: &Dynamic,
+        header_map: &mut HeaderMap,
+    ) -> Result<(), String> {
+        let script_headers = script_headers
+            .clone()
+            .try_cast::<Map>()
+            .ok_or(format!("{path}: request.headers must be an object"))?;
+
+        for key in headers.keys() {
+            if !script_headers.contains_key(key) {
+                header_map.remove(key.as_str());
+            }
+        }
+
+        for (key, value) in script_headers {
+            let value = value.to_string();
+
+            let is_unchanged = headers.get(&key).is_some_and(|header| header.to_string() == value);
+
+            if is_unchanged {
+                continue;
+            }
+
+            let name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|err| format!("{path}: invalid header {key}: {err}"))?;
+
+            let value = HeaderValue::from_str(&value)
+                .map_err(|err| format!("{path}: invalid value for header {key}: {err}"))?;
+
+            header_map.insert(name, value);
+        }
+
+        Ok(())
+    }
+
+    fn to_dynamic<T: serde::Serialize>(value: &T) -> Result<Dynamic, String> {
+        rhai::serde::to_dynamic(value).map_err(|err| err.to_string())
+    }
+}
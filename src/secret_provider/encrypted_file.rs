@@ -0,0 +1,441 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use age::x25519;
+use anyhow::anyhow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hcl::Value;
+use indexmap::IndexMap;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cmd::ctx::CmdContext,
+    parser::{block_type::secret_providers::SecretProviderConfig, GlobalDropConfigProvider},
+};
+
+use super::SecretProvider;
+
+/// age identity, or several separated by newlines
+pub static AGE_IDENTITY_ENV_VAR: &str = "DROP_AGE_IDENTITY";
+
+/// path to an age identity file, e.g. from `age-keygen`
+pub static AGE_IDENTITY_FILE_ENV_VAR: &str = "DROP_AGE_IDENTITY_FILE";
+
+/// key holding the file metadata- not available as a secret name
+pub static METADATA_KEY: &str = "drop";
+
+static DEFAULT_IDENTITY_FILE_DIR: &str = ".drop";
+static DEFAULT_IDENTITY_FILE_NAME: &str = "age.key";
+
+static ENCRYPTED_VALUE_PREFIX: &str = "ENC[chacha20poly1305,data:";
+static ENCRYPTED_VALUE_SUFFIX: &str = "]";
+
+const NONCE_LEN: usize = 12;
+const FILE_VERSION: u32 = 1;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EncryptedFileMetadata {
+    pub version: u32,
+    pub recipients: Vec<String>,
+    /// data key, age encrypted to every recipient
+    pub data_key: String,
+}
+
+/// sops style secrets file- keys are stored in plain text and
+/// each value is encrypted with a data key, which is itself
+/// encrypted to the age recipients of the environment
+///
+/// `{
+///   "api_key": "ENC[chacha20poly1305,data:...]",
+///   "drop": { "version": 1, "recipients": ["age1..."], "data_key": "..." }
+/// }`
+///
+/// unchanged values keep their ciphertext when the file is
+/// re-encrypted, so diffs only show the secrets that changed
+#[derive(Debug)]
+pub struct EncryptedSecretsFile {
+    pub metadata: EncryptedFileMetadata,
+    pub values: BTreeMap<String, String>,
+}
+
+impl EncryptedSecretsFile {
+    pub fn default_path(env: &str) -> String {
+        format!("secrets.{env}.enc.json")
+    }
+
+    /// path configured in the environment's encrypted_file
+    /// layer, relative to the dropfile directory
+    pub fn get_path_for_env(env: &str) -> PathBuf {
+        let configured_path = GlobalDropConfigProvider::get()
+            .get_secret_provider_layers(env)
+            .into_iter()
+            .find_map(|layer| match layer {
+                SecretProviderConfig::EncryptedFile { path } => path,
+                _ => None,
+            });
+
+        Path::new(&CmdContext::get_dir()).join(
+            configured_path.unwrap_or_else(|| EncryptedSecretsFile::default_path(env)),
+        )
+    }
+
+    pub fn read(path: &Path) -> Result<EncryptedSecretsFile, anyhow::Error> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| anyhow!("error reading {}: {err}", path.display()))?;
+
+        let mut json: BTreeMap<String, serde_json::Value> = serde_json::from_str(&contents)
+            .map_err(|err| anyhow!("{} is not a json object: {err}", path.display()))?;
+
+        let metadata: EncryptedFileMetadata = match json.remove(METADATA_KEY) {
+            Some(metadata) => serde_json::from_value(metadata)?,
+            None => {
+                return Err(anyhow!(
+                    "{} has no \"{METADATA_KEY}\" metadata- was it encrypted with `drop secret encrypt`?",
+                    path.display()
+                ))
+            }
+        };
+
+        if metadata.version != FILE_VERSION {
+            return Err(anyhow!(
+                "{} has unsupported version {}",
+                path.display(),
+                metadata.version
+            ));
+        }
+
+        let mut values = BTreeMap::<String, String>::new();
+
+        for (key, value) in json {
+            match value {
+                serde_json::Value::String(value) => {
+                    values.insert(key, value);
+                }
+                _ => return Err(anyhow!("{} value for {key} is not encrypted", path.display())),
+            }
+        }
+
+        Ok(EncryptedSecretsFile { metadata, values })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let mut json = serde_json::Map::new();
+
+        for (key, value) in &self.values {
+            json.insert(key.to_string(), serde_json::Value::String(value.to_string()));
+        }
+
+        json.insert(METADATA_KEY.to_string(), serde_json::to_value(&self.metadata)?);
+
+        let mut contents = serde_json::to_string_pretty(&json)?;
+        contents.push('\n');
+
+        fs::write(path, contents)
+            .map_err(|err| anyhow!("error writing {}: {err}", path.display()))
+    }
+
+    /// decrypt with the identities from the environment
+    pub fn decrypt(&self) -> Result<BTreeMap<String, String>, anyhow::Error> {
+        let data_key = self.decrypt_data_key(&EncryptedSecretsFile::get_identities()?)?;
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&data_key));
+
+        let mut plaintext = BTreeMap::<String, String>::new();
+
+        for (key, value) in &self.values {
+            plaintext.insert(
+                key.to_string(),
+                EncryptedSecretsFile::decrypt_value(&cipher, key, value)?,
+            );
+        }
+
+        Ok(plaintext)
+    }
+
+    /// encrypt to the recipients- when the previous file is encrypted to
+    /// the same recipients its data key and unchanged values are kept
+    pub fn encrypt(
+        plaintext: &BTreeMap<String, String>,
+        recipients: &[String],
+        previous: Option<&EncryptedSecretsFile>,
+    ) -> Result<EncryptedSecretsFile, anyhow::Error> {
+        if recipients.is_empty() {
+            return Err(anyhow!(
+                "no recipients for the encrypted secrets file- set `recipients` in a secret_providers block"
+            ));
+        }
+
+        if plaintext.contains_key(METADATA_KEY) {
+            return Err(anyhow!("\"{METADATA_KEY}\" is reserved and cannot be a secret name"));
+        }
+
+        let reusable = match previous {
+            Some(previous) if previous.metadata.recipients == recipients => {
+                let data_key = previous.decrypt_data_key(&EncryptedSecretsFile::get_identities()?)?;
+                Some((previous, data_key))
+            }
+            _ => None,
+        };
+
+        let (metadata, data_key) = match &reusable {
+            Some((previous, data_key)) => (previous.metadata.clone(), data_key.clone()),
+            None => {
+                let mut data_key = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut data_key);
+
+                let metadata = EncryptedFileMetadata {
+                    version: FILE_VERSION,
+                    recipients: recipients.to_vec(),
+                    data_key: EncryptedSecretsFile::encrypt_data_key(&data_key, recipients)?,
+                };
+
+                (metadata, data_key)
+            }
+        };
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&data_key));
+
+        let mut values = BTreeMap::<String, String>::new();
+
+        for (key, value) in plaintext {
+            let previous_value = reusable
+                .as_ref()
+                .and_then(|(previous, _)| previous.values.get(key))
+                .filter(|previous_value| {
+                    EncryptedSecretsFile::decrypt_value(&cipher, key, previous_value)
+                        .is_ok_and(|previous_plaintext| &previous_plaintext == value)
+                });
+
+            let encrypted = match previous_value {
+                Some(previous_value) => previous_value.to_string(),
+                None => EncryptedSecretsFile::encrypt_value(&cipher, key, value)?,
+            };
+
+            values.insert(key.to_string(), encrypted);
+        }
+
+        Ok(EncryptedSecretsFile { metadata, values })
+    }
+
+    /// plaintext secrets from a json object- scalars are
+    /// stored as strings, nested values are not supported
+    pub fn plaintext_from_json(contents: &str) -> Result<BTreeMap<String, String>, anyhow::Error> {
+        let json: BTreeMap<String, serde_json::Value> = serde_json::from_str(contents)
+            .map_err(|err| anyhow!("secrets must be a json object: {err}"))?;
+
+        let mut plaintext = BTreeMap::<String, String>::new();
+
+        for (key, value) in json {
+            let value = match value {
+                serde_json::Value::String(str) => str,
+                serde_json::Value::Number(num) => num.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                _ => return Err(anyhow!("value for {key} must be a string, number or bool")),
+            };
+
+            plaintext.insert(key, value);
+        }
+
+        Ok(plaintext)
+    }
+
+    fn encrypt_data_key(data_key: &[u8], recipients: &[String]) -> Result<String, anyhow::Error> {
+        let mut parsed_recipients = Vec::<x25519::Recipient>::new();
+
+        for recipient in recipients {
+            parsed_recipients.push(
+                recipient
+                    .parse()
+                    .map_err(|err| anyhow!("invalid age recipient {recipient}: {err}"))?,
+            );
+        }
+
+        let encryptor = age::Encryptor::with_recipients(
+            parsed_recipients
+                .iter()
+                .map(|recipient| recipient as &dyn age::Recipient),
+        )?;
+
+        let mut encrypted = vec![];
+        let mut writer = encryptor.wrap_output(&mut encrypted)?;
+        writer.write_all(data_key)?;
+        writer.finish()?;
+
+        Ok(BASE64_STANDARD.encode(encrypted))
+    }
+
+    fn decrypt_data_key(&self, identities: &[x25519::Identity]) -> Result<Vec<u8>, anyhow::Error> {
+        let encrypted = BASE64_STANDARD.decode(&self.metadata.data_key)?;
+
+        let decryptor = age::Decryptor::new_buffered(&encrypted[..])?;
+
+        let mut reader = decryptor
+            .decrypt(
+                identities
+                    .iter()
+                    .map(|identity| identity as &dyn age::Identity),
+            )
+            .map_err(|err| {
+                anyhow!(
+                    "error decrypting secrets file- no identity matches its recipients, check {AGE_IDENTITY_ENV_VAR} or {AGE_IDENTITY_FILE_ENV_VAR}: {err}"
+                )
+            })?;
+
+        let mut data_key = vec![];
+        reader.read_to_end(&mut data_key)?;
+
+        Ok(data_key)
+    }
+
+    /// the key name is authenticated with the value so
+    /// values cannot be swapped between keys
+    fn encrypt_value(
+        cipher: &ChaCha20Poly1305,
+        key: &str,
+        value: &str,
+    ) -> Result<String, anyhow::Error> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: value.as_bytes(),
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|err| anyhow!("error encrypting {key}: {err}"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+
+        Ok(format!(
+            "{ENCRYPTED_VALUE_PREFIX}{}{ENCRYPTED_VALUE_SUFFIX}",
+            BASE64_STANDARD.encode(payload)
+        ))
+    }
+
+    fn decrypt_value(
+        cipher: &ChaCha20Poly1305,
+        key: &str,
+        value: &str,
+    ) -> Result<String, anyhow::Error> {
+        let encoded = value
+            .strip_prefix(ENCRYPTED_VALUE_PREFIX)
+            .and_then(|value| value.strip_suffix(ENCRYPTED_VALUE_SUFFIX))
+            .ok_or_else(|| anyhow!("value for {key} is not encrypted"))?;
+
+        let payload = BASE64_STANDARD.decode(encoded)?;
+
+        if payload.len() < NONCE_LEN {
+            return Err(anyhow!("invalid encrypted value for {key}"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("error decrypting value for {key}"))?;
+
+        Ok(String::from_utf8(plaintext)?)
+    }
+
+    /// identities from, in order:
+    /// - `DROP_AGE_IDENTITY`
+    /// - the file at `DROP_AGE_IDENTITY_FILE`
+    /// - `~/.drop/age.key`
+    fn get_identities() -> Result<Vec<x25519::Identity>, anyhow::Error> {
+        let contents = if let Ok(identity) = std::env::var(AGE_IDENTITY_ENV_VAR) {
+            identity
+        } else if let Ok(identity_file) = std::env::var(AGE_IDENTITY_FILE_ENV_VAR) {
+            fs::read_to_string(&identity_file).map_err(|err| {
+                anyhow!("error reading {AGE_IDENTITY_FILE_ENV_VAR} {identity_file}: {err}")
+            })?
+        } else {
+            let home = std::env::var("HOME")
+                .or_else(|_| std::env::var("USERPROFILE"))
+                .unwrap_or_default();
+
+            let identity_file = PathBuf::from(home)
+                .join(DEFAULT_IDENTITY_FILE_DIR)
+                .join(DEFAULT_IDENTITY_FILE_NAME);
+
+            fs::read_to_string(&identity_file).map_err(|_| {
+                anyhow!(
+                    "no age identity found- set {AGE_IDENTITY_ENV_VAR}, {AGE_IDENTITY_FILE_ENV_VAR} or create {}",
+                    identity_file.display()
+                )
+            })?
+        };
+
+        let mut identities = Vec::<x25519::Identity>::new();
+
+        for line in contents.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            identities.push(
+                line.parse()
+                    .map_err(|err| anyhow!("invalid age identity: {err}"))?,
+            );
+        }
+
+        Ok(identities)
+    }
+}
+
+/// the shared encrypted secrets file- a missing
+/// file provides no secrets
+pub struct EncryptedFileSecretProvider {
+    pub path: Option<String>,
+}
+
+impl SecretProvider for EncryptedFileSecretProvider {
+    fn name(&self) -> String {
+        format!(
+            "encrypted_file {}",
+            self.path.as_deref().unwrap_or("secrets.[env].enc.json")
+        )
+    }
+
+    fn get_secrets_for_env(&self, env: &str) -> Result<IndexMap<String, Value>, anyhow::Error> {
+        let path = Path::new(&CmdContext::get_dir()).join(
+            self.path
+                .clone()
+                .unwrap_or_else(|| EncryptedSecretsFile::default_path(env)),
+        );
+
+        if !path.exists() {
+            if self.path.is_some() {
+                return Err(anyhow!("{} does not exist", path.display()));
+            }
+
+            return Ok(IndexMap::new());
+        }
+
+        let plaintext = EncryptedSecretsFile::read(&path)?.decrypt()?;
+
+        Ok(plaintext
+            .into_iter()
+            .map(|(key, value)| (key, Value::String(value)))
+            .collect())
+    }
+}
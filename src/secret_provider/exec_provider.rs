@@ -0,0 +1,43 @@
+use std::process::Command;
+
+use anyhow::anyhow;
+use hcl::Value;
+use indexmap::IndexMap;
+
+use super::{SecretProvider, SecretProviderManager};
+
+/// runs a command and reads a json object of secrets
+/// from stdout, e.g. `["pass", "show", "drop/staging"]`
+///
+/// the environment is passed to the command as `DROP_ENV`
+pub struct ExecSecretProvider {
+    pub command: Vec<String>,
+}
+
+impl SecretProvider for ExecSecretProvider {
+    fn name(&self) -> String {
+        format!("exec {}", self.command.join(" "))
+    }
+
+    fn get_secrets_for_env(&self, env: &str) -> Result<IndexMap<String, Value>, anyhow::Error> {
+        let Some((program, args)) = self.command.split_first() else {
+            return Err(anyhow!("command must not be empty"));
+        };
+
+        let output = Command::new(program)
+            .args(args)
+            .env("DROP_ENV", env)
+            .output()
+            .map_err(|err| anyhow!("failed to run {program}: {err}"))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("{program} exited with {}: {}", output.status, stderr.trim()));
+        }
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .map_err(|err| anyhow!("stdout is not a json object: {err}"))?;
+
+        SecretProviderManager::map_from_json_object(json)
+    }
+}
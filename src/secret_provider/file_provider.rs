@@ -0,0 +1,107 @@
+use std::{fs, path::Path};
+
+use anyhow::anyhow;
+use hcl::Value;
+use indexmap::IndexMap;
+
+use crate::cmd::ctx::CmdContext;
+
+use super::{SecretProvider, SecretProviderManager};
+
+/// reads secrets from a json, yaml or dotenv file,
+/// relative paths resolve from the dropfile directory
+pub struct FileSecretProvider {
+    pub path: String,
+    pub format: Option<String>,
+}
+
+impl SecretProvider for FileSecretProvider {
+    fn name(&self) -> String {
+        format!("file {}", self.path)
+    }
+
+    fn get_secrets_for_env(&self, _env: &str) -> Result<IndexMap<String, Value>, anyhow::Error> {
+        let path = Path::new(&CmdContext::get_dir()).join(&self.path);
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|err| anyhow!("error reading {}: {err}", path.display()))?;
+
+        FileSecretProvider::parse_contents(
+            &FileSecretProvider::get_format(&self.path, self.format.as_deref()),
+            &contents,
+        )
+    }
+}
+
+impl FileSecretProvider {
+    pub fn parse_contents(
+        format: &str,
+        contents: &str,
+    ) -> Result<IndexMap<String, Value>, anyhow::Error> {
+        match format {
+            "json" => {
+                let json: serde_json::Value = serde_json::from_str(contents)?;
+                SecretProviderManager::map_from_json_object(json)
+            }
+            "yaml" => {
+                let json: serde_json::Value = serde_yaml::from_str(contents)?;
+                SecretProviderManager::map_from_json_object(json)
+            }
+            "dotenv" => Ok(FileSecretProvider::parse_dotenv(contents)),
+            format => Err(anyhow!(
+                "invalid format {format}- valid formats are json, yaml and dotenv"
+            )),
+        }
+    }
+
+    /// the format if set, otherwise inferred from the extension
+    pub fn get_format(path: &str, format: Option<&str>) -> String {
+        if let Some(format) = format {
+            return format.to_string();
+        }
+
+        if path.ends_with(".json") {
+            "json".to_string()
+        } else if path.ends_with(".yaml") || path.ends_with(".yml") {
+            "yaml".to_string()
+        } else {
+            "dotenv".to_string()
+        }
+    }
+
+    /// `KEY=value` lines, ignoring comments,
+    /// blank lines and an `export` prefix
+    pub fn parse_dotenv(contents: &str) -> IndexMap<String, Value> {
+        let mut secrets = IndexMap::<String, Value>::new();
+
+        for line in contents.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let line = line.strip_prefix("export ").unwrap_or(line);
+
+            let Some((key, value)) = line.split_once('=') else {
+                log::warn!("invalid dotenv line ignored: {line}");
+                continue;
+            };
+
+            let value = value.trim();
+
+            let unquoted = if value.len() >= 2
+                && ((value.starts_with('"') && value.ends_with('"'))
+                    || (value.starts_with('\'') && value.ends_with('\'')))
+            {
+                &value[1..value.len() - 1]
+            } else {
+                value
+            };
+
+            secrets.insert(key.trim().to_string(), Value::String(unquoted.to_string()));
+        }
+
+        secrets
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::anyhow;
+use encrypted_file::EncryptedFileSecretProvider;
+use exec_provider::ExecSecretProvider;
+use file_provider::FileSecretProvider;
+use hcl::Value;
+use indexmap::IndexMap;
+use sqlite_provider::SqliteSecretProvider;
+
+use crate::{
+    parser::{block_type::secret_providers::SecretProviderConfig, GlobalDropConfigProvider},
+    util::redact::Redactor,
+};
+
+pub mod encrypted_file;
+pub mod exec_provider;
+pub mod file_provider;
+pub mod sqlite_provider;
+
+/// source of secrets for an environment
+pub trait SecretProvider {
+    fn name(&self) -> String;
+    fn get_secrets_for_env(&self, env: &str) -> Result<IndexMap<String, Value>, anyhow::Error>;
+}
+
+/// resolves secrets from the provider layers
+/// configured for the environment
+pub struct SecretProviderManager {}
+
+impl SecretProviderManager {
+    pub fn get_providers_for_env(env: &str) -> Vec<Box<dyn SecretProvider>> {
+        GlobalDropConfigProvider::get()
+            .get_secret_provider_layers(env)
+            .into_iter()
+            .map(SecretProviderManager::provider_from_config)
+            .collect()
+    }
+
+    pub fn provider_from_config(config: SecretProviderConfig) -> Box<dyn SecretProvider> {
+        match config {
+            SecretProviderConfig::Sqlite => Box::new(SqliteSecretProvider {}),
+            SecretProviderConfig::File { path, format } => {
+                Box::new(FileSecretProvider { path, format })
+            }
+            SecretProviderConfig::Exec { command } => Box::new(ExecSecretProvider { command }),
+            SecretProviderConfig::EncryptedFile { path } => {
+                Box::new(EncryptedFileSecretProvider { path })
+            }
+        }
+    }
+
+    /// merge each layer in order, later layers
+    /// overwrite keys from earlier layers- the values
+    /// are registered with the `Redactor` as they're read
+    #[log_attributes::log(debug, "{fn} env: {env}")]
+    pub fn resolve_secrets_for_env(env: &str) -> Result<IndexMap<String, Value>, anyhow::Error> {
+        let mut secrets = IndexMap::<String, Value>::new();
+
+        for provider in SecretProviderManager::get_providers_for_env(env) {
+            let provider_secrets = provider
+                .get_secrets_for_env(env)
+                .map_err(|err| anyhow!("secret provider {}: {err}", provider.name()))?;
+
+            // registered before anything can log them
+            Redactor::register_secrets(&provider_secrets);
+
+            log::trace!(
+                "SecretProviderManager {} provided keys {:?}",
+                provider.name(),
+                provider_secrets.keys()
+            );
+
+            secrets.extend(provider_secrets);
+        }
+
+        Ok(secrets)
+    }
+
+    /// scalars are stored as strings, like
+    /// values from the local secret store
+    pub fn value_from_json(value: serde_json::Value) -> Result<Value, anyhow::Error> {
+        match value {
+            serde_json::Value::String(str) => Ok(Value::String(str)),
+            serde_json::Value::Number(num) => Ok(Value::String(num.to_string())),
+            serde_json::Value::Bool(b) => Ok(Value::String(b.to_string())),
+            serde_json::Value::Null => Ok(Value::Null),
+            other => Ok(hcl::to_value(other)?),
+        }
+    }
+
+    pub fn map_from_json_object(
+        json: serde_json::Value,
+    ) -> Result<IndexMap<String, Value>, anyhow::Error> {
+        match json {
+            serde_json::Value::Object(obj) => {
+                let mut secrets = IndexMap::<String, Value>::new();
+
+                for (key, value) in obj {
+                    secrets.insert(key, SecretProviderManager::value_from_json(value)?);
+                }
+
+                Ok(secrets)
+            }
+            _ => Err(anyhow!("secrets must be an object of key value pairs")),
+        }
+    }
+}
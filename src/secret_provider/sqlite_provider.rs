@@ -0,0 +1,23 @@
+use anyhow::anyhow;
+use hcl::Value;
+use indexmap::IndexMap;
+
+use crate::persist::PersisterProvider;
+
+use super::SecretProvider;
+
+/// the local secret store
+pub struct SqliteSecretProvider {}
+
+impl SecretProvider for SqliteSecretProvider {
+    fn name(&self) -> String {
+        "sqlite".to_string()
+    }
+
+    fn get_secrets_for_env(&self, env: &str) -> Result<IndexMap<String, Value>, anyhow::Error> {
+        match PersisterProvider::get_lock_to_persister() {
+            Some(mut persister) => persister.get_secrets_for_env(env),
+            None => Err(anyhow!("failed to obtain lock to persister")),
+        }
+    }
+}
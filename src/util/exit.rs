@@ -0,0 +1,49 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+static EMBEDDED: AtomicBool = AtomicBool::new(false);
+
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+/// payload of the panic that replaces `process::exit`
+/// when drop is embedded- caught by `DropSession`
+#[derive(Debug)]
+pub struct DropExit {
+    pub code: i32,
+}
+
+/// exits the process from the cli, or unwinds to
+/// the caller when drop is embedded as a library
+pub fn exit(code: i32) -> ! {
+    if EMBEDDED.load(Ordering::SeqCst) {
+        std::panic::panic_any(DropExit { code })
+    }
+
+    std::process::exit(code)
+}
+
+pub fn set_embedded() {
+    EMBEDDED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_embedded() -> bool {
+    EMBEDDED.load(Ordering::SeqCst)
+}
+
+/// error logs are kept when embedded, so the
+/// reason for an exit reaches the caller
+pub fn record_error(message: &str) {
+    if !is_embedded() {
+        return;
+    }
+
+    if let Ok(mut last_error) = LAST_ERROR.lock() {
+        *last_error = Some(message.to_string());
+    }
+}
+
+pub fn take_last_error() -> Option<String> {
+    LAST_ERROR.lock().ok().and_then(|mut last_error| last_error.take())
+}
@@ -0,0 +1,308 @@
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use openssl::{
+    bn::BigNum,
+    ecdsa::EcdsaSig,
+    hash::{hash, MessageDigest},
+    memcmp,
+    pkey::{PKey, Private, Public},
+    sign::{Signer, Verifier},
+    x509::X509,
+};
+use serde_json::{json, Value};
+
+use super::unix_now;
+
+/// `alg` values for `jwt_sign` and `jwt_verify`- `HS*` keys are
+/// the shared secret, `RS*` and `ES*` keys are pem encoded
+pub const JWT_ALGORITHMS: [&str; 8] = ["HS256", "HS384", "HS512", "RS256", "RS384", "RS512", "ES256", "ES384"];
+
+/// json web tokens, for the `jwt_decode`, `jwt_verify` and
+/// `jwt_sign` functions and `jwt(...)` assertions
+pub struct Jwt {}
+
+impl Jwt {
+    /// `{ header = {...}, claims = {...} }`, without verifying the signature
+    pub fn decode(token: &str) -> Result<Value, String> {
+        let parts: Vec<&str> = token.trim().split('.').collect();
+
+        if parts.len() != 3 {
+            return Err("invalid jwt, expected three dot separated parts".to_string());
+        }
+
+        let decode_part = |name: &str, part: &str| -> Result<Value, String> {
+            let bytes = BASE64_URL_SAFE_NO_PAD
+                .decode(part.trim_end_matches('='))
+                .map_err(|err| format!("invalid jwt {name}: {err}"))?;
+
+            serde_json::from_slice(&bytes).map_err(|err| format!("invalid jwt {name}: {err}"))
+        };
+
+        Ok(json!({
+            "header": decode_part("header", parts[0])?,
+            "claims": decode_part("claims", parts[1])?,
+        }))
+    }
+
+    /// whether the signature is valid for the key, and the
+    /// token is within its `nbf` and `exp` claims
+    pub fn verify(token: &str, key: &str) -> Result<bool, String> {
+        let decoded = Jwt::decode(token)?;
+
+        let alg = decoded["header"]["alg"].as_str().unwrap_or_default();
+
+        // `none` and unknown algorithms are never valid
+        let Some(digest) = Jwt::get_digest(alg) else {
+            return Ok(false);
+        };
+
+        let token = token.trim();
+        let (signing_input, signature) = token.rsplit_once('.').unwrap_or_default();
+
+        let Ok(signature) = BASE64_URL_SAFE_NO_PAD.decode(signature) else {
+            return Ok(false);
+        };
+
+        let is_valid_signature = match &alg[..2] {
+            // a public key used as the shared secret
+            // would accept tokens anyone can sign
+            "HS" if key.contains("-----BEGIN") => false,
+            "HS" => {
+                let expected = Jwt::hmac(digest, key, signing_input)?;
+                expected.len() == signature.len() && memcmp::eq(&expected, &signature)
+            }
+            "RS" => {
+                let public_key = Jwt::get_public_key(key)?;
+                let mut verifier = Verifier::new(digest, &public_key).map_err(Jwt::to_string)?;
+                verifier.update(signing_input.as_bytes()).map_err(Jwt::to_string)?;
+                verifier.verify(&signature).unwrap_or(false)
+            }
+            _ => {
+                // es signatures are r and s concatenated
+                let half = signature.len() / 2;
+
+                let der_signature = BigNum::from_slice(&signature[..half])
+                    .and_then(|r| Ok((r, BigNum::from_slice(&signature[half..])?)))
+                    .and_then(|(r, s)| EcdsaSig::from_private_components(r, s))
+                    .and_then(|ecdsa_sig| ecdsa_sig.to_der());
+
+                let Ok(der_signature) = der_signature else {
+                    return Ok(false);
+                };
+
+                let public_key = Jwt::get_public_key(key)?;
+                let mut verifier = Verifier::new(digest, &public_key).map_err(Jwt::to_string)?;
+                verifier.update(signing_input.as_bytes()).map_err(Jwt::to_string)?;
+                verifier.verify(&der_signature).unwrap_or(false)
+            }
+        };
+
+        let now = unix_now();
+        let claims = &decoded["claims"];
+
+        let is_expired = claims["exp"].as_i64().is_some_and(|exp| exp <= now);
+        let is_not_yet_valid = claims["nbf"].as_i64().is_some_and(|nbf| nbf > now);
+
+        Ok(is_valid_signature && !is_expired && !is_not_yet_valid)
+    }
+
+    pub fn sign(claims: &Value, key: &str, alg: &str) -> Result<String, String> {
+        let alg = alg.to_uppercase();
+
+        let Some(digest) = Jwt::get_digest(&alg) else {
+            return Err(format!("invalid jwt alg {alg}, expected one of {}", JWT_ALGORITHMS.join(", ")));
+        };
+
+        if !claims.is_object() {
+            return Err("jwt claims must be an object".to_string());
+        }
+
+        let header = json!({ "alg": alg, "typ": "JWT" });
+
+        let signing_input = format!(
+            "{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(header.to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+
+        let signature = match &alg[..2] {
+            "HS" => Jwt::hmac(digest, key, &signing_input)?,
+            "RS" => {
+                let private_key = Jwt::get_private_key(key)?;
+                let mut signer = Signer::new(digest, &private_key).map_err(Jwt::to_string)?;
+                signer.update(signing_input.as_bytes()).map_err(Jwt::to_string)?;
+                signer.sign_to_vec().map_err(Jwt::to_string)?
+            }
+            _ => {
+                let private_key = Jwt::get_private_key(key)?;
+                let ec_key = private_key.ec_key().map_err(|_| format!("{alg} requires an ec private key"))?;
+
+                let digest_bytes = hash(digest, signing_input.as_bytes()).map_err(Jwt::to_string)?;
+                let ecdsa_sig = EcdsaSig::sign(&digest_bytes, &ec_key).map_err(Jwt::to_string)?;
+
+                // r and s padded to the curve's size
+                let size = if alg == "ES256" { 32 } else { 48 };
+
+                let mut signature = ecdsa_sig.r().to_vec_padded(size).map_err(Jwt::to_string)?;
+                signature.extend(ecdsa_sig.s().to_vec_padded(size).map_err(Jwt::to_string)?);
+                signature
+            }
+        };
+
+        Ok(format!("{signing_input}.{}", BASE64_URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    /// the `exp` claim, for tokens without a
+    /// separate expiry- `None` for opaque tokens
+    pub fn get_expiry(token: &str) -> Option<i64> {
+        Jwt::decode(token).ok()?["claims"]["exp"].as_i64()
+    }
+
+    /// whether the token's `exp` claim has passed-
+    /// false for opaque tokens and tokens without one
+    pub fn is_expired(token: &str) -> bool {
+        Jwt::get_expiry(token).is_some_and(|exp| exp <= unix_now())
+    }
+
+    fn get_digest(alg: &str) -> Option<MessageDigest> {
+        match alg {
+            "HS256" | "RS256" | "ES256" => Some(MessageDigest::sha256()),
+            "HS384" | "RS384" | "ES384" => Some(MessageDigest::sha384()),
+            "HS512" | "RS512" => Some(MessageDigest::sha512()),
+            _ => None,
+        }
+    }
+
+    fn hmac(digest: MessageDigest, key: &str, signing_input: &str) -> Result<Vec<u8>, String> {
+        let key = PKey::hmac(key.as_bytes()).map_err(Jwt::to_string)?;
+        let mut signer = Signer::new(digest, &key).map_err(Jwt::to_string)?;
+        signer.update(signing_input.as_bytes()).map_err(Jwt::to_string)?;
+        signer.sign_to_vec().map_err(Jwt::to_string)
+    }
+
+    /// a pem public key, or certificate
+    fn get_public_key(key: &str) -> Result<PKey<Public>, String> {
+        PKey::public_key_from_pem(key.as_bytes())
+            .or_else(|_| X509::from_pem(key.as_bytes()).and_then(|cert| cert.public_key()))
+            .map_err(|_| "invalid jwt key, expected a pem public key or certificate".to_string())
+    }
+
+    fn get_private_key(key: &str) -> Result<PKey<Private>, String> {
+        PKey::private_key_from_pem(key.as_bytes())
+            .map_err(|_| "invalid jwt key, expected a pem private key".to_string())
+    }
+
+    fn to_string(err: openssl::error::ErrorStack) -> String {
+        format!("jwt error: {err}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::{
+        ec::{EcGroup, EcKey},
+        nid::Nid,
+        rsa::Rsa,
+    };
+
+    use super::*;
+
+    const HS_SECRET: &str = "drop-test-secret";
+
+    /// a private and public pem key pair
+    fn rsa_keys() -> (String, String) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        pem_pair(&key)
+    }
+
+    fn ec_keys() -> (String, String) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        pem_pair(&key)
+    }
+
+    fn pem_pair(key: &PKey<Private>) -> (String, String) {
+        (
+            String::from_utf8(key.private_key_to_pem_pkcs8().unwrap()).unwrap(),
+            String::from_utf8(key.public_key_to_pem().unwrap()).unwrap(),
+        )
+    }
+
+    fn claims() -> Value {
+        json!({ "sub": "drop", "exp": unix_now() + 3600 })
+    }
+
+    #[test]
+    fn signed_tokens_verify() {
+        let (rsa_private, rsa_public) = rsa_keys();
+        let (ec_private, ec_public) = ec_keys();
+
+        for (alg, signing_key, verifying_key) in [
+            ("HS256", HS_SECRET, HS_SECRET),
+            ("RS256", rsa_private.as_str(), rsa_public.as_str()),
+            ("ES256", ec_private.as_str(), ec_public.as_str()),
+        ] {
+            let token = Jwt::sign(&claims(), signing_key, alg).unwrap();
+
+            assert_eq!(Jwt::decode(&token).unwrap()["header"]["alg"], alg);
+            assert_eq!(Jwt::verify(&token, verifying_key), Ok(true), "{alg}");
+            assert!(!Jwt::verify(&token, "another-secret").unwrap_or(false), "{alg}");
+        }
+    }
+
+    #[test]
+    fn alg_none_is_rejected() {
+        let token = format!(
+            "{}.{}.",
+            BASE64_URL_SAFE_NO_PAD.encode(json!({ "alg": "none", "typ": "JWT" }).to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(claims().to_string())
+        );
+
+        assert_eq!(Jwt::verify(&token, HS_SECRET), Ok(false));
+        assert!(Jwt::sign(&claims(), HS_SECRET, "none").is_err());
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let token = Jwt::sign(&claims(), HS_SECRET, "HS256").unwrap();
+        let (signing_input, signature) = token.rsplit_once('.').unwrap();
+
+        // a different signature for the same claims
+        let mut signature = BASE64_URL_SAFE_NO_PAD.decode(signature).unwrap();
+        signature[0] ^= 1;
+        let tampered_signature = format!("{signing_input}.{}", BASE64_URL_SAFE_NO_PAD.encode(&signature));
+
+        assert_eq!(Jwt::verify(&tampered_signature, HS_SECRET), Ok(false));
+
+        // different claims with the original signature
+        let (header, _) = signing_input.split_once('.').unwrap();
+        let admin_claims = json!({ "sub": "admin", "exp": unix_now() + 3600 });
+        let tampered_claims = format!(
+            "{header}.{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(admin_claims.to_string()),
+            token.rsplit_once('.').unwrap().1
+        );
+
+        assert_eq!(Jwt::verify(&tampered_claims, HS_SECRET), Ok(false));
+    }
+
+    #[test]
+    fn hs_tokens_signed_with_a_public_key_are_rejected() {
+        let (_, rsa_public) = rsa_keys();
+
+        // anyone with the public key can sign this token
+        let token = Jwt::sign(&claims(), &rsa_public, "HS256").unwrap();
+
+        assert_eq!(Jwt::verify(&token, &rsa_public), Ok(false));
+    }
+
+    #[test]
+    fn expired_and_not_yet_valid_tokens_are_rejected() {
+        let expired = Jwt::sign(&json!({ "exp": unix_now() - 60 }), HS_SECRET, "HS256").unwrap();
+        let not_yet_valid = Jwt::sign(&json!({ "nbf": unix_now() + 3600 }), HS_SECRET, "HS256").unwrap();
+
+        assert_eq!(Jwt::verify(&expired, HS_SECRET), Ok(false));
+        assert!(Jwt::is_expired(&expired));
+        assert_eq!(Jwt::verify(&not_yet_valid, HS_SECRET), Ok(false));
+        assert!(!Jwt::is_expired(&not_yet_valid));
+    }
+}
@@ -22,3 +22,25 @@ macro_rules! s {
         $value.to_string()
     }};
 }
+
+#[macro_export]
+#[doc = "println in text output mode- skipped for json and jsonl output"]
+macro_rules! text_println {
+    ($($arg:tt)*) => {{
+        if $crate::cmd::output::Output::is_text() {
+            println!($($arg)*);
+        }
+    }};
+}
+
+#[macro_export]
+#[doc = "println in text output mode, eprintln for json and jsonl output- keeps prompts and errors off stdout documents"]
+macro_rules! prompt_println {
+    ($($arg:tt)*) => {{
+        if $crate::cmd::output::Output::is_text() {
+            println!($($arg)*);
+        } else {
+            eprintln!($($arg)*);
+        }
+    }};
+}
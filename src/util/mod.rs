@@ -1,4 +1,18 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod exit;
+pub mod jwt;
 pub mod macros;
+pub mod redact;
+
+/// seconds since the unix epoch, for
+/// token, jwt and cookie expiries
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() as i64)
+        .unwrap_or_default()
+}
 
 pub fn pretty_printed_json(value: &str) -> Option<String> {
     let deserialization_result: Result<serde_json::Value, serde_json::Error> =
@@ -0,0 +1,134 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    RwLock,
+};
+
+use hcl::Value;
+use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use log::{Level, Log, Metadata, Record};
+
+use super::exit;
+
+/// replaces sensitive values in output
+pub static REDACTED: &str = "***";
+
+/// shorter values are not redacted- masking
+/// e.g. "1" or "true" would garble all output
+const MIN_REDACTED_LEN: usize = 4;
+
+lazy_static! {
+    static ref SENSITIVE_VALUES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+}
+
+static REDACTION_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// tracks values from `secrets.*` and outputs marked
+/// `sensitive = true` and masks them in console
+/// output, logs and persisted call records
+pub struct Redactor {}
+
+impl Redactor {
+    /// `--unsafe-show-secrets`
+    pub fn disable() {
+        REDACTION_DISABLED.store(true, Ordering::Relaxed);
+    }
+
+    pub fn register(value: &str) {
+        if value.len() < MIN_REDACTED_LEN {
+            return;
+        }
+
+        let Ok(mut sensitive_values) = SENSITIVE_VALUES.write() else {
+            log::trace!("Redactor error obtaining lock to sensitive values");
+            return;
+        };
+
+        // debug formatting escapes quotes and control
+        // characters, so the escaped form is masked too
+        let escaped = format!("{value:?}");
+        let escaped = &escaped[1..escaped.len() - 1];
+
+        for value in [value, escaped] {
+            if !sensitive_values.iter().any(|sensitive| sensitive == value) {
+                sensitive_values.push(value.to_string());
+            }
+        }
+
+        // longest first so a value containing another
+        // value is masked entirely
+        sensitive_values.sort_by_key(|sensitive| std::cmp::Reverse(sensitive.len()));
+    }
+
+    pub fn register_secrets(secrets: &IndexMap<String, Value>) {
+        for value in secrets.values() {
+            Redactor::register_value(value);
+        }
+    }
+
+    fn register_value(value: &Value) {
+        match value {
+            Value::String(str) => Redactor::register(str),
+            Value::Array(values) => values.iter().for_each(Redactor::register_value),
+            Value::Object(obj) => obj.values().for_each(Redactor::register_value),
+            _ => {}
+        }
+    }
+
+    pub fn redact(text: &str) -> String {
+        if REDACTION_DISABLED.load(Ordering::Relaxed) {
+            return text.to_string();
+        }
+
+        let Ok(sensitive_values) = SENSITIVE_VALUES.read() else {
+            return text.to_string();
+        };
+
+        let mut redacted = text.to_string();
+
+        for sensitive in sensitive_values.iter() {
+            if redacted.contains(sensitive.as_str()) {
+                redacted = redacted.replace(sensitive.as_str(), REDACTED);
+            }
+        }
+
+        redacted
+    }
+}
+
+/// redacts every log line before it is written
+pub struct RedactingLogger {
+    pub inner: Box<dyn Log>,
+}
+
+impl Log for RedactingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+
+        let redacted = Redactor::redact(&record.args().to_string());
+
+        if record.level() == Level::Error {
+            exit::record_error(&redacted);
+        }
+
+        self.inner.log(
+            &Record::builder()
+                .metadata(record.metadata().clone())
+                .args(format_args!("{redacted}"))
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}